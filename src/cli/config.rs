@@ -0,0 +1,368 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use everscale_crypto::ed25519;
+
+use super::{CliContext, ProjectDirs};
+use crate::config::*;
+use crate::contracts::wallet;
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// App config inspection and editing
+#[argh(subcommand, name = "config")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub fn run(self, ctx: CliContext) -> Result<()> {
+        match self.subcommand {
+            SubCmd::Show(cmd) => cmd.run(ctx),
+            SubCmd::Get(cmd) => cmd.run(ctx),
+            SubCmd::Set(cmd) => cmd.run(ctx),
+            SubCmd::Validate(cmd) => cmd.run(ctx),
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Show(CmdShow),
+    Get(CmdGet),
+    Set(CmdSet),
+    Validate(CmdValidate),
+}
+
+#[derive(FromArgs)]
+/// Prints the app config
+#[argh(subcommand, name = "show")]
+struct CmdShow {
+    /// print secrets as is
+    #[argh(switch)]
+    reveal: bool,
+}
+
+impl CmdShow {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+
+        let mut value = toml::Value::try_from(&config).context("failed to serialize config")?;
+        if !self.reveal {
+            redact_secrets(&mut value);
+        }
+
+        let data = toml::to_string_pretty(&value).context("failed to serialize config")?;
+        print_output(data);
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Prints the app config field
+#[argh(subcommand, name = "get")]
+struct CmdGet {
+    /// dot separated path to the field (e.g. `validator.stake_per_round`)
+    #[argh(positional)]
+    path: String,
+
+    /// print secrets as is
+    #[argh(switch)]
+    reveal: bool,
+}
+
+impl CmdGet {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+
+        let mut value = toml::Value::try_from(&config).context("failed to serialize config")?;
+        if !self.reveal {
+            redact_secrets(&mut value);
+        }
+
+        let mut value = &value;
+        for part in split_path(&self.path)? {
+            value = value
+                .get(part)
+                .ok_or_else(|| ConfigCmdError::FieldNotFound(self.path.clone()))?;
+        }
+
+        let value = serde_json::to_value(value).context("failed to serialize field")?;
+        print_output(value);
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Updates the app config field
+#[argh(subcommand, name = "set")]
+struct CmdSet {
+    /// dot separated path to the field (e.g. `control.query_timeout`)
+    #[argh(positional)]
+    path: String,
+
+    /// new field value (TOML literal or a raw string)
+    #[argh(positional)]
+    value: String,
+}
+
+impl CmdSet {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let dirs = ctx.dirs();
+
+        let config = ctx.load_config()?;
+        let mut root = toml::Value::try_from(&config).context("failed to serialize config")?;
+
+        // Find the parent table, creating intermediate tables if needed
+        let parts = split_path(&self.path)?;
+        let (field, parents) = parts.split_last().expect("path is never empty");
+
+        let mut table = &mut root;
+        for part in parents {
+            table = table
+                .as_table_mut()
+                .ok_or_else(|| ConfigCmdError::NotATable(self.path.clone()))?
+                .entry(part.to_string())
+                .or_insert_with(|| toml::Value::Table(Default::default()));
+        }
+
+        let value = parse_value(&self.value);
+        table
+            .as_table_mut()
+            .ok_or_else(|| ConfigCmdError::NotATable(self.path.clone()))?
+            .insert(field.to_string(), value);
+
+        // Check the updated config against the model
+        let config: AppConfig = root
+            .try_into()
+            .map_err(|e| ConfigCmdError::InvalidValue(self.path.clone(), e))?;
+        config.store(&dirs.app_config)?;
+
+        print_output(serde_json::json!({}));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Checks the app config against keys and the node config
+#[argh(subcommand, name = "validate")]
+struct CmdValidate {}
+
+impl CmdValidate {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let dirs = ctx.dirs();
+        let config = ctx.load_config()?;
+
+        let mut issues = Vec::new();
+        check_node_config(&config, dirs, &mut issues);
+        check_validator(&config, dirs, &mut issues);
+
+        let valid = issues.is_empty();
+        print_output(serde_json::json!({
+            "valid": valid,
+            "issues": issues,
+        }));
+
+        if !valid {
+            anyhow::bail!("config is invalid");
+        }
+        Ok(())
+    }
+}
+
+fn check_node_config(config: &AppConfig, dirs: &ProjectDirs, issues: &mut Vec<String>) {
+    if config.control.is_none() {
+        issues.push("control config is empty".to_owned());
+    }
+    if config.adnl.is_none() {
+        issues.push("adnl config is empty".to_owned());
+    }
+
+    let node_config = match NodeConfig::load(&dirs.node_config) {
+        Ok(node_config) => node_config,
+        Err(e) => {
+            issues.push(format!("{e:?}"));
+            return;
+        }
+    };
+
+    if let Some(control) = &config.control {
+        match node_config.get_control_server() {
+            Ok(Some(server)) => {
+                if server.address.port() != control.server_address.port() {
+                    issues.push(format!(
+                        "control server port mismatch: node uses {}, config uses {}",
+                        server.address.port(),
+                        control.server_address.port()
+                    ));
+                }
+
+                let server_pubkey = ed25519::PublicKey::from(&server.server_key);
+                if server_pubkey.as_bytes() != control.server_pubkey.as_bytes() {
+                    issues.push("control server pubkey mismatch".to_owned());
+                }
+
+                let client_pubkey = ed25519::PublicKey::from(&control.client_secret);
+                if let Some(clients) = &server.clients {
+                    if !clients
+                        .iter()
+                        .any(|client| client.as_bytes() == client_pubkey.as_bytes())
+                    {
+                        issues.push("control client is not allowed by the node".to_owned());
+                    }
+                }
+            }
+            Ok(None) => issues.push("node control server is not configured".to_owned()),
+            Err(e) => issues.push(format!("invalid node control server config: {e:?}")),
+        }
+    }
+
+    if let Some(adnl) = &config.adnl {
+        match node_config.get_adnl_node() {
+            Ok(Some(node)) => {
+                if node.ip_address != adnl.server_address {
+                    issues.push(format!(
+                        "ADNL address mismatch: node uses {}, config uses {}",
+                        node.ip_address, adnl.server_address
+                    ));
+                }
+
+                match node.overlay_pubkey() {
+                    Ok(pubkey) if pubkey.as_bytes() != adnl.server_pubkey.as_bytes() => {
+                        issues.push("ADNL server pubkey mismatch".to_owned());
+                    }
+                    Ok(_) => {}
+                    Err(e) => issues.push(format!("{e:?}")),
+                }
+            }
+            Ok(None) => issues.push("node ADNL is not configured".to_owned()),
+            Err(e) => issues.push(format!("invalid node ADNL config: {e:?}")),
+        }
+    }
+}
+
+fn check_validator(config: &AppConfig, dirs: &ProjectDirs, issues: &mut Vec<String>) {
+    const MIN_STAKE_FACTOR: u32 = 1 << 16;
+    const MAX_STAKE_FACTOR: u32 = 3 << 16;
+
+    let load_keys =
+        |path: &Path, name: &str, issues: &mut Vec<String>| match StoredKeys::load_public(path) {
+            Ok(public) => Some(public),
+            Err(e) => {
+                issues.push(format!("failed to load {name} keys: {e:?}"));
+                None
+            }
+        };
+
+    let stake_factor = match &config.validator {
+        None => return,
+        Some(AppConfigValidator::Single(single)) => {
+            if let Some(public) = load_keys(&dirs.validator_keys, "validator", issues) {
+                let address = wallet::compute_wallet_address(-1, &public);
+                if address != single.address {
+                    issues.push(format!(
+                        "validator wallet address mismatch: keys correspond to {address}"
+                    ));
+                }
+            }
+            single.stake_factor
+        }
+        Some(AppConfigValidator::DePool(depool)) => {
+            if let Some(public) = load_keys(&dirs.validator_keys, "validator", issues) {
+                let address = wallet::compute_wallet_address(0, &public);
+                if address != depool.owner {
+                    issues.push(format!(
+                        "DePool owner address mismatch: keys correspond to {address}"
+                    ));
+                }
+            }
+
+            if let Some(public) = load_keys(&dirs.depool_keys, "DePool", issues) {
+                match depool.depool_type.compute_depool_address(&public) {
+                    Ok(address) if address != depool.depool => issues.push(format!(
+                        "DePool address mismatch: keys correspond to {address}"
+                    )),
+                    Ok(_) => {}
+                    Err(e) => issues.push(format!("failed to compute DePool address: {e:?}")),
+                }
+            }
+
+            if depool.strategy.is_some() && depool.strategy_factory.is_some() {
+                issues.push("both strategy and strategy factory are specified".to_owned());
+            }
+
+            let balances = &depool.balances;
+            if balances.critical_balance >= balances.initial_balance {
+                issues.push(
+                    "DePool critical balance must be less than the initial balance".to_owned(),
+                );
+            }
+            if balances.min_proxy_balance >= balances.initial_proxy_balance {
+                issues.push(
+                    "min proxy balance must be less than the initial proxy balance".to_owned(),
+                );
+            }
+
+            depool.stake_factor
+        }
+    };
+
+    if let Some(stake_factor) = stake_factor {
+        if !(MIN_STAKE_FACTOR..=MAX_STAKE_FACTOR).contains(&stake_factor) {
+            issues.push(format!(
+                "stake factor {stake_factor} is out of range {MIN_STAKE_FACTOR}..={MAX_STAKE_FACTOR}"
+            ));
+        }
+    }
+}
+
+fn redact_secrets(value: &mut toml::Value) {
+    const SECRETS: &[&str] = &["client_secret"];
+    const REDACTED: &str = "<redacted>";
+
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if SECRETS.contains(&key.as_str()) {
+                    *value = toml::Value::String(REDACTED.to_owned());
+                } else {
+                    redact_secrets(value);
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+fn split_path(path: &str) -> Result<Vec<&str>, ConfigCmdError> {
+    let parts = path.split('.').collect::<Vec<_>>();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(ConfigCmdError::InvalidPath(path.to_owned()));
+    }
+    Ok(parts)
+}
+
+/// Parses the value as a TOML literal, falling back to a raw string
+fn parse_value(value: &str) -> toml::Value {
+    format!("value = {value}")
+        .parse::<toml::Value>()
+        .ok()
+        .and_then(|mut parsed| parsed.as_table_mut()?.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
+
+#[derive(thiserror::Error, Debug)]
+enum ConfigCmdError {
+    #[error("invalid field path `{0}`")]
+    InvalidPath(String),
+    #[error("field `{0}` not found")]
+    FieldNotFound(String),
+    #[error("field `{0}` is not inside a table")]
+    NotATable(String),
+    #[error("invalid value for `{0}`")]
+    InvalidValue(String, #[source] toml::de::Error),
+}
@@ -6,7 +6,9 @@ use nekoton_abi::FunctionExt;
 use ton_block::{Deserializable, Serializable};
 
 use super::CliContext;
-use crate::config::{AppConfig, StoredKeys};
+use crate::config::AppConfig;
+use crate::contracts::signer::{SigningAction, SigningContext};
+use crate::contracts::Signer;
 use crate::network::{NodeTcpRpc, NodeUdpRpc, Subscription};
 use crate::util::*;
 
@@ -216,9 +218,17 @@ struct CmdSend {
     #[argh(option, short = 's')]
     sign: Option<PathBuf>,
 
+    /// hash of the node keystore key to sign with (hex encoded string)
+    #[argh(option)]
+    keystore_key: Option<String>,
+
     /// base64 encoded state init
     #[argh(option, short = 'i')]
     state_init: Option<String>,
+
+    /// execute the message locally without sending it
+    #[argh(switch)]
+    emulate: bool,
 }
 
 impl CmdSend {
@@ -241,20 +251,32 @@ impl CmdSend {
             .with_context(|| format!("method `{}` not found", self.method))?;
 
         let input = nekoton_abi::parse_abi_tokens(&method.inputs, self.args)?;
-        let keys = self.sign.map(StoredKeys::load_as_keypair).transpose()?;
+        let signer = match (self.sign, self.keystore_key) {
+            (Some(_), Some(_)) => anyhow::bail!("`--sign` and `--keystore-key` are exclusive"),
+            (Some(path), None) => Some(Signer::load(path, &node_tcp_rpc)?),
+            (None, Some(key_hash)) => {
+                let key_hash = parse_key_hash(&key_hash)?;
+                Some(Signer::node_keystore(node_tcp_rpc.clone(), key_hash).await?)
+            }
+            (None, None) => None,
+        };
         let state_init = parse_optional_state_init(self.state_init)?;
 
         // Prepare external message
-        let (expire_at, headers) =
-            make_default_headers(keys.as_ref().map(|keypair| keypair.public), self.timeout);
-
-        let body = method.encode_input(
-            &headers,
-            &input,
-            false,
-            keys.as_ref(),
-            Some(address.clone()),
-        )?;
+        let (expire_at, headers) = make_default_headers(
+            signer.as_ref().map(|signer| *signer.public_key()),
+            self.timeout,
+        );
+
+        let body = match &signer {
+            Some(signer) => {
+                let context = SigningContext::new(SigningAction::ContractSend, &address, method);
+                signer
+                    .encode_input(method, &headers, &input, &address, &context)
+                    .await?
+            }
+            None => method.encode_input(&headers, &input, false, None, Some(address.clone()))?,
+        };
 
         let mut message =
             ton_block::Message::with_ext_in_header(ton_block::ExternalInboundMessageHeader {
@@ -274,6 +296,29 @@ impl CmdSend {
         // Create subscription
         let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc);
 
+        // Execute external message locally on top of the current state
+        if self.emulate {
+            let emulation = subscription
+                .emulate_message(&message, broxus_util::now())
+                .await?;
+
+            let (output, events) = match &emulation.transaction {
+                Some(tx) if emulation.success => {
+                    let (output, events) = parse_transaction(&abi, method, &tx.data)?;
+                    (Some(output), events)
+                }
+                _ => (None, Vec::new()),
+            };
+
+            return Ok(serde_json::json!({
+                "emulation": emulation,
+                "error": emulation.error(),
+                "tx_hash": emulation.transaction.as_ref().map(|tx| tx.hash.to_hex_string()),
+                "output": output,
+                "events": events,
+            }));
+        }
+
         // Send external message and wait until it is delivered
         let TransactionWithHash {
             hash: tx_hash,
@@ -286,26 +331,11 @@ impl CmdSend {
         // Parse transaction
         let msg_hash = tx
             .in_msg
+            .as_ref()
             .context("external inbound message not found")?
             .hash();
 
-        let mut output = None;
-        let mut events = Vec::new();
-
-        tx.out_msgs.iterate(|ton_block::InRefValue(msg)| {
-            match parse_message(&abi, method, &msg)? {
-                Some(ParsedData::Output(data)) => output = Some(data),
-                Some(ParsedData::Event(event)) => events.push(event),
-                None => {}
-            }
-            Ok(true)
-        })?;
-
-        let output = if method.has_output() {
-            nekoton_abi::make_abi_tokens(&output.context("method output not found")?)?
-        } else {
-            serde_json::Value::Object(Default::default())
-        };
+        let (output, events) = parse_transaction(&abi, method, &tx)?;
 
         // Done
         Ok(serde_json::json!({
@@ -317,6 +347,33 @@ impl CmdSend {
     }
 }
 
+/// Decodes method output and events from the transaction external outbound messages
+fn parse_transaction<'a>(
+    abi: &'a ton_abi::Contract,
+    method: &'a ton_abi::Function,
+    tx: &ton_block::Transaction,
+) -> Result<(serde_json::Value, Vec<ParsedEvent<'a>>)> {
+    let mut output = None;
+    let mut events = Vec::new();
+
+    tx.out_msgs.iterate(|ton_block::InRefValue(msg)| {
+        match parse_message(abi, method, &msg)? {
+            Some(ParsedData::Output(data)) => output = Some(data),
+            Some(ParsedData::Event(event)) => events.push(event),
+            None => {}
+        }
+        Ok(true)
+    })?;
+
+    let output = if method.has_output() {
+        nekoton_abi::make_abi_tokens(&output.context("method output not found")?)?
+    } else {
+        serde_json::Value::Object(Default::default())
+    };
+
+    Ok((output, events))
+}
+
 fn parse_message<'a, 'b: 'a>(
     abi: &'b ton_abi::Contract,
     method: &'b ton_abi::Function,
@@ -0,0 +1,1226 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use broxus_util::{now, serde_optional_string, serde_string};
+use serde::Serialize;
+
+use super::CliContext;
+use crate::config::*;
+use crate::contracts::depool::{
+    CompletionReason, ComplexStakeParams, DePoolInfo, DePoolRegistry, RoundSnapshot, RoundStep,
+    RoundsHistory,
+};
+use crate::contracts::*;
+use crate::network::{NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// DePool inspection and participant operations
+#[argh(subcommand, name = "depool")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        let configured = || ConfiguredDePool::load(&ctx);
+        let subscription = || async { make_subscription(&ctx.load_config()?).await };
+
+        let response = match self.subcommand {
+            // Registry listing doesn't require the configured DePool
+            SubCmd::Versions(_) => list_versions(),
+            // Delegator commands work with any DePool
+            SubCmd::Stake(cmd) => cmd.run(&ctx, &subscription().await?).await?,
+            SubCmd::Unstake(cmd) => cmd.run(&ctx, &subscription().await?).await?,
+            SubCmd::Status(cmd) => cmd.run(&ctx, &subscription().await?).await?,
+            // Other commands work with the DePool from the config
+            SubCmd::Info(cmd) => cmd.run(&configured().await?)?,
+            SubCmd::Rounds(cmd) => cmd.run(&configured().await?)?,
+            SubCmd::Participants(cmd) => cmd.run(&configured().await?)?,
+            SubCmd::Participant(cmd) => cmd.run(&configured().await?)?,
+            SubCmd::Balance(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::Report(cmd) => return cmd.run(&configured().await?).await,
+            SubCmd::AddStake(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::AddVesting(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::AddLock(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::Withdraw(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::WithdrawAll(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::CancelWithdrawal(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::TransferStake(cmd) => cmd.run(&configured().await?).await?,
+            SubCmd::Allowed(cmd) => cmd.run(&configured().await?).await?,
+        };
+
+        print_output(response);
+        Ok(())
+    }
+}
+
+/// Creates the subscription with the node clients from the config
+async fn make_subscription(config: &AppConfig) -> Result<Arc<Subscription>> {
+    let node_tcp_rpc = NodeTcpRpc::new(config.control()?)
+        .await
+        .context("failed to build node TCP client")?;
+    let node_udp_rpc = NodeUdpRpc::new(config.adnl()?)
+        .await
+        .context("failed to build node UDP client")?;
+    Ok(Subscription::new(node_tcp_rpc, node_udp_rpc))
+}
+
+/// DePool from the config with its current state
+struct ConfiguredDePool<'a> {
+    ctx: &'a CliContext,
+    subscription: Arc<Subscription>,
+    contract: DePool,
+    state: ton_block::AccountStuff,
+    owner: ton_block::MsgAddressInt,
+}
+
+impl<'a> ConfiguredDePool<'a> {
+    async fn load(ctx: &'a CliContext) -> Result<Self> {
+        let config = ctx.load_config()?;
+
+        let (depool_type, address, owner) = match &config.validator {
+            Some(AppConfigValidator::DePool(depool)) => (
+                depool.depool_type.clone(),
+                depool.depool.clone(),
+                depool.owner.clone(),
+            ),
+            Some(AppConfigValidator::Single(_)) => return Err(DePoolError::NotDePool.into()),
+            None => return Err(DePoolError::ValidatorNotConfigured.into()),
+        };
+
+        let subscription = make_subscription(&config).await?;
+        let contract = DePool::new(depool_type, address, subscription.clone());
+        let state = contract.get_state().await?;
+
+        Ok(Self {
+            ctx,
+            subscription,
+            contract,
+            state,
+            owner,
+        })
+    }
+
+    fn get_info(&self) -> Result<DePoolInfo> {
+        self.contract
+            .get_info(&self.state)
+            .context("failed to get DePool info")
+    }
+
+    /// Loads the validator wallet which owns the DePool
+    async fn owner_wallet(&self) -> Result<ParticipantWallet> {
+        ParticipantWallet::load_owner(self.ctx, &self.subscription, &self.owner).await
+    }
+
+    /// Sends the participant request from the owner wallet
+    async fn send_owner_request(&self, message: InternalMessage) -> Result<serde_json::Value> {
+        let owner = self.owner_wallet().await?;
+        owner.send_request(&self.contract, message).await
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Info(CmdInfo),
+    Rounds(CmdRounds),
+    Participants(CmdParticipants),
+    Participant(CmdParticipant),
+    Balance(CmdBalance),
+    Report(CmdReport),
+    Versions(CmdVersions),
+    AddStake(CmdAddStake),
+    AddVesting(CmdAddVesting),
+    AddLock(CmdAddLock),
+    Withdraw(CmdWithdraw),
+    WithdrawAll(CmdWithdrawAll),
+    CancelWithdrawal(CmdCancelWithdrawal),
+    TransferStake(CmdTransferStake),
+    Allowed(CmdAllowed),
+    Stake(CmdStake),
+    Unstake(CmdUnstake),
+    Status(CmdStatus),
+}
+
+#[derive(FromArgs)]
+/// Shows the DePool parameters
+#[argh(subcommand, name = "info")]
+struct CmdInfo {}
+
+impl CmdInfo {
+    fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({
+            "address": depool.contract.address().to_string(),
+            "depool_type": depool.contract.ty(),
+            "info": depool.get_info()?,
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows the DePool rounds
+#[argh(subcommand, name = "rounds")]
+struct CmdRounds {}
+
+impl CmdRounds {
+    fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let rounds = depool
+            .contract
+            .get_rounds(&depool.state)
+            .context("failed to get DePool rounds")?;
+        Ok(serde_json::json!({
+            "rounds": rounds.values().collect::<Vec<_>>(),
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows all participants with their stakes
+#[argh(subcommand, name = "participants")]
+struct CmdParticipants {}
+
+impl CmdParticipants {
+    fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let participants = depool
+            .contract
+            .get_participants(&depool.state)
+            .context("failed to get DePool participants")?;
+
+        let mut result = Vec::with_capacity(participants.len());
+        for address in participants {
+            let info = depool
+                .contract
+                .get_participant_info(&depool.state, &address)
+                .with_context(|| format!("failed to get participant info for {address}"))?;
+            result.push(serde_json::json!({
+                "address": address.to_string(),
+                "info": info,
+            }));
+        }
+
+        Ok(serde_json::json!({
+            "participants": result,
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows stakes of the single participant
+#[argh(subcommand, name = "participant")]
+struct CmdParticipant {
+    /// participant address
+    #[argh(positional)]
+    address: AddressInput,
+}
+
+impl CmdParticipant {
+    fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let info = depool
+            .contract
+            .get_participant_info(&depool.state, &self.address.0)
+            .context("failed to get participant info")?;
+        Ok(serde_json::json!({
+            "address": self.address.0.to_string(),
+            "info": info,
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows the DePool and proxies balances
+#[argh(subcommand, name = "balance")]
+struct CmdBalance {}
+
+impl CmdBalance {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let info = depool.get_info()?;
+        let balance = depool
+            .contract
+            .get_depool_balance(&depool.state)
+            .context("failed to get DePool balance")?;
+
+        let mut proxies = Vec::with_capacity(info.proxies.len());
+        for proxy in info.proxies {
+            let account = depool
+                .subscription
+                .get_account_state(&proxy)
+                .await
+                .with_context(|| format!("failed to get proxy state {proxy}"))?;
+            let proxy_balance = account.map(|account| account.storage.balance.grams.0);
+            proxies.push(serde_json::json!({
+                "address": proxy.to_string(),
+                "balance": proxy_balance.map(|balance| balance.to_string()),
+            }));
+        }
+
+        Ok(serde_json::json!({
+            "address": depool.contract.address().to_string(),
+            "account_balance": depool.state.storage.balance.grams.0.to_string(),
+            "balance": balance.to_string(),
+            "proxies": proxies,
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows rewards and costs of the DePool rounds
+#[argh(subcommand, name = "report")]
+struct CmdReport {
+    /// include rounds elected since this unix timestamp
+    #[argh(option, default = "0")]
+    from: u32,
+
+    /// include rounds elected until this unix timestamp (now by default)
+    #[argh(option)]
+    to: Option<u32>,
+
+    /// output format: `json` (default) or `csv`
+    #[argh(option, default = "ReportFormat::Json")]
+    format: ReportFormat,
+}
+
+impl CmdReport {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<()> {
+        let info = depool.get_info()?;
+        let rounds = depool
+            .contract
+            .get_rounds(&depool.state)
+            .context("failed to get DePool rounds")?;
+
+        // Merge stored snapshots with the current rounds
+        let address = depool.contract.address();
+        let mut history = RoundsHistory::load(depool.ctx.dirs().depool_rounds(address), address)?;
+        history.update(&rounds, &info, now());
+
+        let report = self
+            .build_report(&history, &info, &depool.subscription)
+            .await?;
+        match self.format {
+            ReportFormat::Json => print_output(serde_json::to_value(report)?),
+            ReportFormat::Csv => print!("{}", report.to_csv()),
+        }
+        Ok(())
+    }
+
+    async fn build_report(
+        &self,
+        history: &RoundsHistory,
+        info: &DePoolInfo,
+        subscription: &Subscription,
+    ) -> Result<Report> {
+        // Stakes are sent to the elector at most a day before the round is elected
+        const PROXY_HISTORY_MARGIN: u32 = 86400;
+
+        let range = self.from..=self.to.unwrap_or_else(now);
+
+        let mut rounds = history
+            .rounds
+            .values()
+            .filter(|round| range.contains(&round.supposed_elected_at))
+            .map(|round| (round.id, RoundReport::new(round)))
+            .collect::<BTreeMap<_, _>>();
+
+        // Rounds are served by two proxies in turn
+        let mut unattributed_proxy_fees = Some(0);
+        let mut proxy_fees_error = None;
+        let since = self.from.saturating_sub(PROXY_HISTORY_MARGIN);
+        for (i, proxy) in info.proxies.iter().enumerate() {
+            // Proxy fees are reported as unavailable instead of failing the whole report
+            let transactions = match get_proxy_fees(subscription, proxy, since).await {
+                Ok(transactions) => transactions,
+                Err(e) => {
+                    proxy_fees_error =
+                        Some(format!("failed to get proxy transactions {proxy}: {e:?}"));
+                    unattributed_proxy_fees = None;
+                    for round in rounds.values_mut() {
+                        round.proxy_fees = None;
+                    }
+                    break;
+                }
+            };
+
+            for (time, query_id, fees) in transactions {
+                let fees_entry = match query_id {
+                    Some(id) if id as usize % 2 == i && history.rounds.contains_key(&id) => rounds
+                        .get_mut(&id)
+                        .and_then(|round| round.proxy_fees.as_mut()),
+                    _ if range.contains(&time) => unattributed_proxy_fees.as_mut(),
+                    _ => None,
+                };
+                if let Some(entry) = fees_entry {
+                    *entry += fees;
+                }
+            }
+        }
+
+        let rounds = rounds.into_values().collect::<Vec<_>>();
+
+        let mut totals = ReportTotals {
+            proxy_fees: rounds.iter().map(|round| round.proxy_fees).sum(),
+            unattributed_proxy_fees,
+            ..Default::default()
+        };
+        for round in &rounds {
+            totals.reward += round.reward as u128;
+            totals.participant_reward += round.participant_reward as u128;
+            totals.validator_reward += round.validator_reward as u128;
+            totals.validator_stake_reward += round.validator_stake_reward as u128;
+        }
+
+        Ok(Report {
+            from: *range.start(),
+            to: *range.end(),
+            rounds,
+            totals,
+            proxy_fees_error,
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+enum ReportFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(anyhow::anyhow!("unknown format (neither `json` nor `csv`)")),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    from: u32,
+    to: u32,
+    rounds: Vec<RoundReport>,
+    totals: ReportTotals,
+    /// Why the proxy fees are unavailable (all of them are `null` in this case)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proxy_fees_error: Option<String>,
+}
+
+impl Report {
+    fn to_csv(&self) -> String {
+        fn as_str<T: Serialize>(value: &T) -> String {
+            match serde_json::to_value(value) {
+                Ok(serde_json::Value::String(value)) => value,
+                _ => Default::default(),
+            }
+        }
+
+        let mut result = String::from(
+            "id,elected_at,unfreeze,step,completion_reason,stake,validator_stake,\
+            reward,participant_reward,validator_reward,validator_stake_reward,\
+            participant_reward_fraction,applied_reward_fraction,proxy_fees\n",
+        );
+        for round in &self.rounds {
+            let applied_reward_fraction = round
+                .applied_reward_fraction
+                .map(|fraction| format!("{fraction:.2}"))
+                .unwrap_or_default();
+
+            result += &format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                round.id,
+                round.elected_at,
+                round.unfreeze,
+                as_str(&round.step),
+                as_str(&round.completion_reason),
+                round.stake,
+                round.validator_stake,
+                round.reward,
+                round.participant_reward,
+                round.validator_reward,
+                round.validator_stake_reward,
+                round.participant_reward_fraction,
+                applied_reward_fraction,
+                round
+                    .proxy_fees
+                    .map(|fees| fees.to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        result
+    }
+}
+
+#[derive(Serialize)]
+struct RoundReport {
+    id: u64,
+    elected_at: u32,
+    unfreeze: u32,
+    step: RoundStep,
+    completion_reason: CompletionReason,
+    #[serde(with = "serde_string")]
+    stake: u64,
+    #[serde(with = "serde_string")]
+    validator_stake: u64,
+    /// Total reward received from the elector
+    #[serde(with = "serde_string")]
+    reward: u64,
+    /// Reward distributed between participants
+    #[serde(with = "serde_string")]
+    participant_reward: u64,
+    /// Reward which was not distributed between participants
+    #[serde(with = "serde_string")]
+    validator_reward: u64,
+    /// Validator share of the participants reward
+    #[serde(with = "serde_string")]
+    validator_stake_reward: u64,
+    participant_reward_fraction: u8,
+    applied_reward_fraction: Option<f64>,
+    /// Fees paid by the proxy for this round
+    #[serde(with = "serde_optional_string")]
+    proxy_fees: Option<u128>,
+}
+
+impl RoundReport {
+    fn new(round: &RoundSnapshot) -> Self {
+        Self {
+            id: round.id,
+            elected_at: round.supposed_elected_at,
+            unfreeze: round.unfreeze,
+            step: round.step,
+            completion_reason: round.completion_reason,
+            stake: round.stake,
+            validator_stake: round.validator_stake,
+            reward: round.reward(),
+            participant_reward: round.participant_reward,
+            validator_reward: round.validator_reward(),
+            validator_stake_reward: round.validator_stake_reward(),
+            participant_reward_fraction: round.participant_reward_fraction,
+            applied_reward_fraction: round.applied_reward_fraction(),
+            proxy_fees: Some(0),
+        }
+    }
+}
+
+#[derive(Default, Serialize)]
+struct ReportTotals {
+    #[serde(with = "serde_string")]
+    reward: u128,
+    #[serde(with = "serde_string")]
+    participant_reward: u128,
+    #[serde(with = "serde_string")]
+    validator_reward: u128,
+    #[serde(with = "serde_string")]
+    validator_stake_reward: u128,
+    #[serde(with = "serde_optional_string")]
+    proxy_fees: Option<u128>,
+    /// Proxy fees which are not related to any known round (e.g. replenishment)
+    #[serde(with = "serde_optional_string")]
+    unattributed_proxy_fees: Option<u128>,
+}
+
+/// Collects `(time, query id, fees)` of the proxy transactions since the specified time
+async fn get_proxy_fees(
+    subscription: &Subscription,
+    proxy: &ton_block::MsgAddressInt,
+    since: u32,
+) -> Result<Vec<(u32, Option<u64>, u128)>> {
+    const BATCH_SIZE: usize = 64;
+
+    let mut result = Vec::new();
+    let mut from = None;
+    loop {
+        let page = subscription
+            .get_account_transactions(proxy, from.take(), BATCH_SIZE)
+            .await?;
+
+        for tx in &page.transactions {
+            if tx.data.now < since {
+                return Ok(result);
+            }
+            let query_id = parse_query_id(&tx.data);
+            result.push((tx.data.now, query_id, tx.data.total_fees.grams.0));
+        }
+
+        match page.next {
+            Some(next) => from = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(result)
+}
+
+/// Reads the query id of the DePool request or the elector answer.
+///
+/// Both start with a 32-bit function id (or opcode) followed by
+/// the 64-bit query id, which DePool sets to the round id.
+fn parse_query_id(tx: &ton_block::Transaction) -> Option<u64> {
+    const BOUNCED_ID: u32 = 0xffffffff;
+
+    let msg = tx.in_msg.as_ref()?.read_struct().ok()?;
+    msg.int_header()?;
+
+    let mut body = msg.body()?;
+    if body.get_next_u32().ok()? == BOUNCED_ID {
+        body.get_next_u32().ok()?;
+    }
+    body.get_next_u64().ok()
+}
+
+#[derive(FromArgs)]
+/// Shows built-in and custom DePool versions
+#[argh(subcommand, name = "versions")]
+struct CmdVersions {}
+
+fn list_versions() -> serde_json::Value {
+    let versions = DePoolRegistry::get()
+        .versions()
+        .map(|version| {
+            let code_hash = version.tvc.code.as_ref().map(|code| code.repr_hash());
+            serde_json::json!({
+                "depool_type": version.ty,
+                "custom": version.abi.is_some(),
+                "code_hash": code_hash.map(|hash| hash.to_hex_string()),
+                "proxy_code_hash": version.proxy_code.repr_hash().to_hex_string(),
+                "capabilities": version.capabilities,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::json!({
+        "versions": versions,
+    })
+}
+
+#[derive(FromArgs)]
+/// Adds an ordinary stake from the validator wallet
+#[argh(subcommand, name = "addstake")]
+struct CmdAddStake {
+    /// stake amount in EVER
+    #[argh(positional)]
+    amount: Ever<u128>,
+}
+
+impl CmdAddStake {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let message = depool
+            .contract
+            .add_ordinary_stake(parse_stake(self.amount)?)?;
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Adds a vesting stake from the validator wallet
+#[argh(subcommand, name = "addvesting")]
+struct CmdAddVesting {
+    /// stake amount in EVER
+    #[argh(positional)]
+    amount: Ever<u128>,
+
+    /// address which receives the withdrawn parts
+    #[argh(option, short = 'b')]
+    beneficiary: AddressInput,
+
+    /// withdrawal period in seconds
+    #[argh(option, short = 'w')]
+    withdrawal_period: u32,
+
+    /// total period in seconds
+    #[argh(option, short = 't')]
+    total_period: u32,
+}
+
+impl CmdAddVesting {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let message = depool.contract.add_vesting_stake(ComplexStakeParams {
+            stake: parse_stake(self.amount)?,
+            beneficiary: self.beneficiary.0,
+            withdrawal_period: self.withdrawal_period,
+            total_period: self.total_period,
+        })?;
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Adds a lock stake from the validator wallet
+#[argh(subcommand, name = "addlock")]
+struct CmdAddLock {
+    /// stake amount in EVER
+    #[argh(positional)]
+    amount: Ever<u128>,
+
+    /// address which receives the withdrawn parts
+    #[argh(option, short = 'b')]
+    beneficiary: AddressInput,
+
+    /// withdrawal period in seconds
+    #[argh(option, short = 'w')]
+    withdrawal_period: u32,
+
+    /// total period in seconds
+    #[argh(option, short = 't')]
+    total_period: u32,
+}
+
+impl CmdAddLock {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let message = depool.contract.add_lock_stake(ComplexStakeParams {
+            stake: parse_stake(self.amount)?,
+            beneficiary: self.beneficiary.0,
+            withdrawal_period: self.withdrawal_period,
+            total_period: self.total_period,
+        })?;
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Requests a part of the validator ordinary stake to be returned
+#[argh(subcommand, name = "withdraw")]
+struct CmdWithdraw {
+    /// amount in EVER
+    #[argh(positional)]
+    amount: Ever<u128>,
+
+    /// withdraw immediately from the pooling round
+    #[argh(switch)]
+    from_pooling: bool,
+}
+
+impl CmdWithdraw {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let amount = parse_stake(self.amount)?;
+        let message = if self.from_pooling {
+            depool.contract.withdraw_from_pooling_round(amount)?
+        } else {
+            depool.contract.withdraw_part(amount)?
+        };
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Disables reinvest for the validator ordinary stake
+#[argh(subcommand, name = "withdrawall")]
+struct CmdWithdrawAll {}
+
+impl CmdWithdrawAll {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let message = depool.contract.withdraw_all()?;
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Cancels withdrawal requests and enables reinvest for the validator ordinary stake
+#[argh(subcommand, name = "cancelwithdrawal")]
+struct CmdCancelWithdrawal {}
+
+impl CmdCancelWithdrawal {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let message = depool.contract.cancel_withdrawal()?;
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Transfers a part of the validator ordinary stake to another participant
+#[argh(subcommand, name = "transferstake")]
+struct CmdTransferStake {
+    /// destination participant address
+    #[argh(positional)]
+    dst: AddressInput,
+
+    /// amount in EVER
+    #[argh(positional)]
+    amount: Ever<u128>,
+}
+
+impl CmdTransferStake {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let message = depool
+            .contract
+            .transfer_stake(&self.dst.0, parse_stake(self.amount)?)?;
+        depool.send_owner_request(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Allowed participants of the stEVER DePool
+#[argh(subcommand, name = "allowed")]
+struct CmdAllowed {
+    #[argh(subcommand)]
+    subcommand: AllowedSubCmd,
+}
+
+impl CmdAllowed {
+    async fn run(self, depool: &ConfiguredDePool<'_>) -> Result<serde_json::Value> {
+        let depool_type = depool.contract.ty();
+        anyhow::ensure!(
+            depool_type.version()?.capabilities.allowed_participants,
+            "allowed participants are not supported by `{depool_type}` DePool"
+        );
+
+        let address = match self.subcommand {
+            AllowedSubCmd::List(_) => {
+                let participants = depool
+                    .contract
+                    .get_allowed_participants(&depool.state)
+                    .context("failed to get allowed participants")?;
+                let participants = participants
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+
+                return Ok(serde_json::json!({
+                    "allowed_participants": participants,
+                }));
+            }
+            AllowedSubCmd::Add(cmd) => cmd.address.0,
+            AllowedSubCmd::Remove(cmd) => {
+                return Err(DePoolError::AllowedParticipantRemoval(cmd.address.0).into())
+            }
+        };
+
+        // The DePool accepts the list changes only from its validator wallet,
+        // so the loaded keys must control the wallet stored in the DePool itself
+        let owner = depool.owner_wallet().await?;
+        let info = depool.get_info()?;
+        anyhow::ensure!(
+            &info.validator_wallet == owner.address(),
+            "validator keys don't control the DePool validator wallet {}",
+            info.validator_wallet
+        );
+
+        // The owner signs an external message to the validator wallet, which forwards
+        // the call to the DePool. Emulate the whole path before broadcasting it,
+        // so that the call rejected by the DePool doesn't get into the blockchain
+        depool.subscription.set_emulate_messages(true);
+
+        let message = depool.contract.set_allowed_participant(&address)?;
+        let tx = owner.send(message).await?;
+        anyhow::ensure!(
+            !is_aborted(&tx.data)?,
+            "DePool rejected the request (tx: {})",
+            tx.hash.to_hex_string()
+        );
+
+        // Check whether the list was updated
+        let participants = depool
+            .contract
+            .get_allowed_participants(&depool.contract.get_state().await?)
+            .context("failed to get allowed participants")?;
+        anyhow::ensure!(
+            participants.contains(&address),
+            "allowed participants were not updated"
+        );
+
+        Ok(serde_json::json!({
+            "tx_hash": tx.hash.to_hex_string(),
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum AllowedSubCmd {
+    List(CmdAllowedList),
+    Add(CmdAllowedAdd),
+    Remove(CmdAllowedRemove),
+}
+
+#[derive(FromArgs)]
+/// Shows allowed participants
+#[argh(subcommand, name = "list")]
+struct CmdAllowedList {}
+
+#[derive(FromArgs)]
+/// Adds an allowed participant (e.g. a redeployed strategy)
+#[argh(subcommand, name = "add")]
+struct CmdAllowedAdd {
+    /// participant address
+    #[argh(positional)]
+    address: AddressInput,
+}
+
+#[derive(FromArgs)]
+/// Not supported: the stEVER DePool has no method to remove allowed participants
+#[argh(subcommand, name = "remove")]
+struct CmdAllowedRemove {
+    /// participant address
+    #[argh(positional)]
+    address: AddressInput,
+}
+
+/// Wallet which sends participant requests
+struct ParticipantWallet {
+    wallet: Wallet,
+}
+
+impl ParticipantWallet {
+    /// Loads the validator wallet which owns the configured DePool
+    async fn load_owner(
+        ctx: &CliContext,
+        subscription: &Arc<Subscription>,
+        owner: &ton_block::MsgAddressInt,
+    ) -> Result<Self> {
+        let wallet = Self::load(ctx, subscription, None).await?;
+        anyhow::ensure!(
+            wallet.address() == owner,
+            "validator keys don't match the DePool owner address from the config"
+        );
+        Ok(wallet)
+    }
+
+    /// Loads the wallet with the specified keys (validator keys by default)
+    async fn load(
+        ctx: &CliContext,
+        subscription: &Arc<Subscription>,
+        keys: Option<&Path>,
+    ) -> Result<Self> {
+        let keys = keys.unwrap_or(&ctx.dirs().validator_keys);
+        let signer = Signer::load(keys, subscription.tcp_rpc())
+            .with_context(|| format!("failed to load wallet keys {}", keys.display()))?;
+
+        let wallet = Wallet::new(0, signer, subscription.clone());
+
+        subscription.ensure_ready().await?;
+        Ok(Self { wallet })
+    }
+
+    fn address(&self) -> &ton_block::MsgAddressInt {
+        self.wallet.address()
+    }
+
+    /// Sends the participant request and checks the DePool answer
+    async fn send_request(
+        &self,
+        depool: &DePool,
+        message: InternalMessage,
+    ) -> Result<serde_json::Value> {
+        let tx = self.send(message).await?;
+        let answer = depool
+            .parse_answer(&tx.data, self.wallet.address())?
+            .context("DePool answer not found")?;
+        if answer.errcode != 0 {
+            return Err(DePoolError::Rejected {
+                errcode: answer.errcode,
+                comment: answer.comment,
+            }
+            .into());
+        }
+
+        Ok(serde_json::json!({
+            "tx_hash": tx.hash.to_hex_string(),
+        }))
+    }
+
+    /// Sends the internal message, returns the destination transaction
+    async fn send(&self, message: InternalMessage) -> Result<TransactionWithHash> {
+        let balance = self.wallet.get_balance().await?.unwrap_or_default();
+        anyhow::ensure!(
+            balance > message.amount,
+            "insufficient wallet balance ({} EVER)",
+            Ever(balance)
+        );
+
+        self.wallet.call(message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Adds an ordinary stake to any DePool
+#[argh(subcommand, name = "stake")]
+struct CmdStake {
+    /// DePool address
+    #[argh(option, short = 'd')]
+    depool: AddressInput,
+
+    /// stake amount in EVER (without the stake fee)
+    #[argh(positional)]
+    amount: Ever<u128>,
+
+    /// path to the wallet keys (validator keys by default)
+    #[argh(option, short = 's')]
+    sign: Option<PathBuf>,
+}
+
+impl CmdStake {
+    async fn run(
+        self,
+        ctx: &CliContext,
+        subscription: &Arc<Subscription>,
+    ) -> Result<serde_json::Value> {
+        let stake = parse_stake(self.amount)?;
+
+        let (depool, state) = load_any_depool(self.depool.0, subscription).await?;
+        let wallet = ParticipantWallet::load(ctx, subscription, self.sign.as_deref()).await?;
+
+        let info = depool
+            .get_info(&state)
+            .context("failed to get DePool info")?;
+        anyhow::ensure!(!info.pool_closed, "DePool is closed");
+        anyhow::ensure!(
+            stake >= info.min_stake,
+            "stake is less than the DePool min stake ({} EVER)",
+            Ever(info.min_stake)
+        );
+
+        if depool.ty().version()?.capabilities.allowed_participants {
+            let allowed_participants = depool
+                .get_allowed_participants(&state)
+                .context("failed to get allowed participants")?;
+            anyhow::ensure!(
+                allowed_participants.contains(wallet.address()),
+                "wallet {} is not an allowed participant of the DePool",
+                wallet.address()
+            );
+        }
+
+        let message = depool.add_ordinary_stake(stake)?;
+        wallet.send_request(&depool, message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Withdraws the stake from any DePool
+#[argh(subcommand, name = "unstake")]
+struct CmdUnstake {
+    /// DePool address
+    #[argh(option, short = 'd')]
+    depool: AddressInput,
+
+    /// amount in EVER (withdraws all stakes after their rounds complete if not specified)
+    #[argh(positional)]
+    amount: Option<Ever<u128>>,
+
+    /// withdraw the amount immediately from the pooling round
+    #[argh(switch)]
+    from_pooling: bool,
+
+    /// path to the wallet keys (validator keys by default)
+    #[argh(option, short = 's')]
+    sign: Option<PathBuf>,
+}
+
+impl CmdUnstake {
+    async fn run(
+        self,
+        ctx: &CliContext,
+        subscription: &Arc<Subscription>,
+    ) -> Result<serde_json::Value> {
+        let (depool, state) = load_any_depool(self.depool.0, subscription).await?;
+        let wallet = ParticipantWallet::load(ctx, subscription, self.sign.as_deref()).await?;
+
+        depool
+            .get_participant_info(&state, wallet.address())
+            .context("failed to get participant info")?
+            .with_context(|| format!("wallet {} is not a DePool participant", wallet.address()))?;
+
+        let message = match (self.amount, self.from_pooling) {
+            (Some(amount), true) => depool.withdraw_from_pooling_round(parse_stake(amount)?)?,
+            (Some(amount), false) => depool.withdraw_part(parse_stake(amount)?)?,
+            (None, true) => anyhow::bail!("amount is required to withdraw from the pooling round"),
+            (None, false) => depool.withdraw_all()?,
+        };
+        wallet.send_request(&depool, message).await
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows our stakes and rewards across DePools
+#[argh(subcommand, name = "status")]
+struct CmdStatus {
+    /// DePool address (can be specified multiple times)
+    #[argh(option, short = 'd')]
+    depool: Vec<AddressInput>,
+
+    /// participant address (address of the wallet with `--sign` keys by default)
+    #[argh(option, short = 'p')]
+    participant: Option<AddressInput>,
+
+    /// path to the wallet keys (validator keys by default)
+    #[argh(option, short = 's')]
+    sign: Option<PathBuf>,
+}
+
+impl CmdStatus {
+    async fn run(
+        self,
+        ctx: &CliContext,
+        subscription: &Arc<Subscription>,
+    ) -> Result<serde_json::Value> {
+        anyhow::ensure!(!self.depool.is_empty(), "no DePools specified");
+
+        let participant = match self.participant {
+            Some(participant) => participant.0,
+            None => {
+                let keys = self.sign.as_deref().unwrap_or(&ctx.dirs().validator_keys);
+                let signer = Signer::load(keys, subscription.tcp_rpc())
+                    .with_context(|| format!("failed to load wallet keys {}", keys.display()))?;
+                Wallet::new(0, signer, subscription.clone())
+                    .address()
+                    .clone()
+            }
+        };
+
+        let mut totals = StatusTotals::default();
+        let mut depools = Vec::with_capacity(self.depool.len());
+        for AddressInput(address) in self.depool {
+            // Unknown or broken DePools must not hide the rest of the report
+            match get_depool_status(&address, &participant, subscription, &mut totals).await {
+                Ok(status) => depools.push(status),
+                Err(e) => depools.push(serde_json::json!({
+                    "depool": address.to_string(),
+                    "error": format!("{e:?}"),
+                })),
+            }
+        }
+
+        Ok(serde_json::json!({
+            "participant": participant.to_string(),
+            "depools": depools,
+            "totals": {
+                "stake": totals.stake.to_string(),
+                "reward": totals.reward.to_string(),
+                "pending_reward": totals.pending_reward.to_string(),
+            },
+        }))
+    }
+}
+
+#[derive(Default)]
+struct StatusTotals {
+    stake: u128,
+    reward: u128,
+    pending_reward: u128,
+}
+
+/// Returns participant stakes in the DePool, adds them to the totals
+async fn get_depool_status(
+    address: &ton_block::MsgAddressInt,
+    participant: &ton_block::MsgAddressInt,
+    subscription: &Arc<Subscription>,
+    totals: &mut StatusTotals,
+) -> Result<serde_json::Value> {
+    let (depool, state) = load_any_depool(address.clone(), subscription).await?;
+
+    let info = depool
+        .get_participant_info(&state, participant)
+        .context("failed to get participant info")?;
+    let Some(info) = info else {
+        return Ok(serde_json::json!({
+            "depool": depool.address().to_string(),
+            "depool_type": depool.ty(),
+            "participant": false,
+        }));
+    };
+
+    let rounds = depool
+        .get_rounds(&state)
+        .context("failed to get DePool rounds")?;
+
+    let mut pending_reward = 0u64;
+    let mut round_stakes = Vec::new();
+    for round in rounds.values() {
+        let stake = info.compute_total_stake(round.id);
+        if stake == 0 {
+            continue;
+        }
+
+        let round_pending_reward = compute_pending_reward(round, stake);
+        pending_reward += round_pending_reward;
+
+        round_stakes.push(serde_json::json!({
+            "id": round.id,
+            "step": round.step,
+            "completion_reason": round.completion_reason,
+            "supposed_elected_at": round.supposed_elected_at,
+            "unfreeze": round.unfreeze,
+            "stake": stake.to_string(),
+            "pending_reward": round_pending_reward.to_string(),
+        }));
+    }
+
+    totals.stake += info.total as u128;
+    totals.reward += info.reward as u128;
+    totals.pending_reward += pending_reward as u128;
+
+    Ok(serde_json::json!({
+        "depool": depool.address().to_string(),
+        "depool_type": depool.ty(),
+        "participant": true,
+        "total": info.total.to_string(),
+        "withdraw_value": info.withdraw_value.to_string(),
+        "reinvest": info.reinvest,
+        "reward": info.reward.to_string(),
+        "pending_reward": pending_reward.to_string(),
+        "rounds": round_stakes,
+    }))
+}
+
+/// Loads a DePool of any operator, its version is detected by the code hash
+async fn load_any_depool(
+    address: ton_block::MsgAddressInt,
+    subscription: &Arc<Subscription>,
+) -> Result<(DePool, ton_block::AccountStuff)> {
+    let state = subscription
+        .get_account_state(&address)
+        .await?
+        .with_context(|| format!("DePool {address} not deployed"))?;
+
+    let code_hash = match &state.storage.state {
+        ton_block::AccountState::AccountActive { state_init } => {
+            state_init.code.as_ref().map(|code| code.repr_hash())
+        }
+        _ => None,
+    }
+    .with_context(|| format!("DePool {address} is not active"))?;
+
+    let ty = DePoolRegistry::get()
+        .version_by_code_hash(&code_hash)
+        .map(|version| version.ty.clone())
+        .ok_or_else(|| DePoolError::UnknownDePoolCode(address.clone()))?;
+
+    Ok((DePool::new(ty, address, subscription.clone()), state))
+}
+
+/// Participant share of the reward which was received but not distributed yet
+fn compute_pending_reward(round: &depool::Round, stake: u64) -> u64 {
+    if round.completion_reason != CompletionReason::RewardIsReceived || round.stake == 0 {
+        return 0;
+    }
+    (round.participant_reward as u128 * stake as u128 / round.stake as u128) as u64
+}
+
+fn parse_stake(amount: Ever<u128>) -> Result<u64> {
+    u64::try_from(amount.0).map_err(|_| DePoolError::StakeTooBig.into())
+}
+
+#[derive(thiserror::Error, Debug)]
+enum DePoolError {
+    #[error("validator is not configured")]
+    ValidatorNotConfigured,
+    #[error("validator is not configured to use DePool")]
+    NotDePool,
+    #[error("stake amount is too big")]
+    StakeTooBig,
+    #[error("unknown DePool version of {0} (custom versions can be added to the registry)")]
+    UnknownDePoolCode(ton_block::MsgAddressInt),
+    #[error("DePool rejected the request with code {errcode} (comment: {comment})")]
+    Rejected { errcode: u32, comment: u64 },
+    #[error(
+        "can't remove allowed participant {0}: the stEVER DePool has no method for it \
+        (the list can only be extended, e.g. with a redeployed strategy)"
+    )]
+    AllowedParticipantRemoval(ton_block::MsgAddressInt),
+}
@@ -0,0 +1,515 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use everscale_crypto::ed25519;
+use serde::Serialize;
+use tokio::process::Command;
+
+use super::{CliContext, ProjectDirs, VALIDATOR_MANAGER_SERVICE, VALIDATOR_SERVICE};
+use crate::config::*;
+use crate::contracts::wallet;
+use crate::network::{NodeRpcError, NodeTcpRpc, NodeUdpRpc};
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// Checks the environment and prints a diagnostics report
+#[argh(subcommand, name = "doctor")]
+pub struct Cmd {
+    /// print report as json
+    #[argh(switch)]
+    json: bool,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        let dirs = ctx.dirs();
+        let mut report = Report::default();
+
+        // Check files layout
+        check_layout(dirs, &mut report);
+
+        // Check app config
+        let config = match ctx.load_config() {
+            Ok(config) => {
+                report.pass("app config");
+                Some(config)
+            }
+            Err(e) => {
+                report.fail(
+                    "app config",
+                    format!("{e:?}"),
+                    "run `stever init` or fix the config with `stever config validate`",
+                );
+                None
+            }
+        };
+
+        // Check keys
+        check_keys(dirs, config.as_ref(), &mut report);
+
+        // Check node config
+        let node_config = match NodeConfig::load(&dirs.node_config) {
+            Ok(node_config) => {
+                report.pass("node config");
+                Some(node_config)
+            }
+            Err(e) => {
+                report.fail("node config", format!("{e:?}"), "run `stever init node`");
+                None
+            }
+        };
+
+        if let (Some(config), Some(node_config)) = (&config, &node_config) {
+            check_control_server_pubkey(config, node_config, &mut report);
+        }
+
+        // Check network
+        if let Some(config) = &config {
+            check_control_server(config, &mut report).await;
+            check_adnl(config, &mut report).await;
+        }
+
+        // Check disk space
+        if let Some(node_config) = &node_config {
+            check_disk_space(node_config, &mut report);
+        }
+
+        // Check systemd services
+        check_systemd(dirs, &mut report).await;
+
+        // Print report
+        let failed = report.failed();
+        if self.json {
+            print_output(serde_json::to_value(&report.checks)?);
+        } else {
+            report.print();
+        }
+
+        if failed > 0 {
+            anyhow::bail!("{failed} checks failed");
+        }
+        Ok(())
+    }
+}
+
+fn check_layout(dirs: &ProjectDirs, report: &mut Report) {
+    let items = [
+        ("root dir", &dirs.root, "run `stever init`"),
+        (
+            "node configs dir",
+            &dirs.node_configs_dir,
+            "run `stever init node`",
+        ),
+        ("keys dir", &dirs.keys_dir, "run `stever init contracts`"),
+        (
+            "node binary",
+            &dirs.node_binary,
+            "run `stever init node --rebuild`",
+        ),
+        ("app config file", &dirs.app_config, "run `stever init`"),
+        (
+            "node config file",
+            &dirs.node_config,
+            "run `stever init node`",
+        ),
+        (
+            "node log config file",
+            &dirs.node_log_config,
+            "run `stever init node`",
+        ),
+        (
+            "global config file",
+            &dirs.global_config,
+            "run `stever init node`",
+        ),
+    ];
+
+    for (name, path, hint) in items {
+        if path.exists() {
+            report.pass(name);
+        } else {
+            report.fail(name, format!("{} not found", path.display()), hint);
+        }
+    }
+}
+
+fn check_keys(dirs: &ProjectDirs, config: Option<&AppConfig>, report: &mut Report) {
+    let validator = match config.and_then(|config| config.validator.as_ref()) {
+        Some(validator) => validator,
+        None => {
+            report.skip("validator keys", "validator is not configured");
+            return;
+        }
+    };
+
+    let Some(validator_public) = check_keys_file("validator keys", &dirs.validator_keys, report)
+    else {
+        return;
+    };
+
+    match validator {
+        AppConfigValidator::Single(single) => {
+            let address = wallet::compute_wallet_address(-1, &validator_public);
+            if address == single.address {
+                report.pass("validator wallet address");
+            } else {
+                report.fail(
+                    "validator wallet address",
+                    format!(
+                        "keys correspond to {address}, config has {}",
+                        single.address
+                    ),
+                    "restore the original `vld.keys.json` or rerun `stever init contracts`",
+                );
+            }
+        }
+        AppConfigValidator::DePool(depool) => {
+            let address = wallet::compute_wallet_address(0, &validator_public);
+            if address == depool.owner {
+                report.pass("validator wallet address");
+            } else {
+                report.fail(
+                    "validator wallet address",
+                    format!("keys correspond to {address}, config has {}", depool.owner),
+                    "restore the original `vld.keys.json` or rerun `stever init contracts`",
+                );
+            }
+
+            let Some(depool_public) = check_keys_file("DePool keys", &dirs.depool_keys, report)
+            else {
+                return;
+            };
+
+            match depool.depool_type.compute_depool_address(&depool_public) {
+                Ok(address) if address == depool.depool => report.pass("DePool address"),
+                Ok(address) => report.fail(
+                    "DePool address",
+                    format!("keys correspond to {address}, config has {}", depool.depool),
+                    "restore the original `depool.keys.json` or rerun `stever init contracts`",
+                ),
+                Err(e) => report.fail(
+                    "DePool address",
+                    format!("{e:?}"),
+                    "check `validator.depool_type` in the app config",
+                ),
+            }
+        }
+    }
+}
+
+fn check_keys_file(
+    name: &'static str,
+    path: &Path,
+    report: &mut Report,
+) -> Option<ed25519_dalek::PublicKey> {
+    let mut secure = true;
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o077 != 0 => {
+            secure = false;
+            report.fail(
+                name,
+                format!(
+                    "{} is accessible by other users (mode {:o})",
+                    path.display(),
+                    metadata.permissions().mode() & 0o777
+                ),
+                format!("run `chmod 600 {}`", path.display()),
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            report.fail(
+                name,
+                format!("{}: {e}", path.display()),
+                "run `stever init contracts`",
+            );
+            return None;
+        }
+    }
+
+    match StoredKeys::load_public(path) {
+        Ok(public) => {
+            if secure {
+                report.pass(name);
+            }
+            Some(public)
+        }
+        Err(e) => {
+            report.fail(name, format!("{e:?}"), "restore keys from the backup");
+            None
+        }
+    }
+}
+
+fn check_control_server_pubkey(config: &AppConfig, node_config: &NodeConfig, report: &mut Report) {
+    const NAME: &str = "control server pubkey";
+    const HINT: &str = "run `stever init node` to sync configs";
+
+    let Some(control) = &config.control else {
+        report.skip(NAME, "control config is empty");
+        return;
+    };
+
+    match node_config.get_control_server() {
+        Ok(Some(server)) => {
+            let server_pubkey = ed25519::PublicKey::from(&server.server_key);
+            if server_pubkey.as_bytes() == control.server_pubkey.as_bytes() {
+                report.pass(NAME);
+            } else {
+                report.fail(
+                    NAME,
+                    "node config control server key doesn't match `control.server_pubkey`",
+                    HINT,
+                );
+            }
+        }
+        Ok(None) => report.fail(NAME, "node control server is not configured", HINT),
+        Err(e) => report.fail(NAME, format!("{e:?}"), HINT),
+    }
+}
+
+async fn check_control_server(config: &AppConfig, report: &mut Report) {
+    const NAME: &str = "control server";
+
+    let Some(control) = &config.control else {
+        report.skip(NAME, "control config is empty");
+        return;
+    };
+
+    let res = async {
+        let rpc = NodeTcpRpc::new(control).await?;
+        rpc.get_stats().await
+    };
+
+    match tokio::time::timeout(NETWORK_TIMEOUT, res).await {
+        Ok(Ok(_)) => report.pass(NAME),
+        // Node is reachable but rejected the query
+        Ok(Err(e)) if matches!(e.downcast_ref(), Some(NodeRpcError::NodeError { .. })) => report
+            .fail(
+                NAME,
+                format!("{e:?}"),
+                "node is running but rejected the query, check the node logs",
+            ),
+        Ok(Err(e)) => report.fail(
+            NAME,
+            format!("{e:?}"),
+            format!(
+                "make sure the node is running (`systemctl status {VALIDATOR_SERVICE}`) \
+                and listens on {}",
+                control.server_address
+            ),
+        ),
+        Err(_) => report.fail(
+            NAME,
+            "timeout",
+            format!("check that {} is reachable", control.server_address),
+        ),
+    }
+}
+
+async fn check_adnl(config: &AppConfig, report: &mut Report) {
+    const NAME: &str = "ADNL";
+
+    let Some(adnl) = &config.adnl else {
+        report.skip(NAME, "adnl config is empty");
+        return;
+    };
+
+    let res = async {
+        let rpc = NodeUdpRpc::new(adnl).await?;
+        rpc.get_capabilities().await
+    };
+
+    let hint = || {
+        format!(
+            "make sure the UDP port {} is open and the public ip is correct",
+            adnl.server_address.port()
+        )
+    };
+
+    match tokio::time::timeout(NETWORK_TIMEOUT, res).await {
+        Ok(Ok(_)) => report.pass(NAME),
+        Ok(Err(e)) => report.fail(NAME, format!("{e:?}"), hint()),
+        Err(_) => report.fail(NAME, "timeout", hint()),
+    }
+}
+
+fn check_disk_space(node_config: &NodeConfig, report: &mut Report) {
+    const NAME: &str = "disk space";
+    const MIN_AVAILABLE_PERCENT: u64 = 10;
+
+    let db_path = match node_config.get_internal_db_path() {
+        Ok(Some(db_path)) => db_path,
+        Ok(None) => {
+            report.fail(
+                NAME,
+                "node DB path is not configured",
+                "run `stever init node`",
+            );
+            return;
+        }
+        Err(e) => {
+            report.fail(NAME, format!("{e:?}"), "run `stever init node`");
+            return;
+        }
+    };
+
+    // Use the nearest existing parent directory
+    let Some(path) = db_path.ancestors().find(|path| path.exists()) else {
+        report.fail(
+            NAME,
+            format!("{} not found", db_path.display()),
+            "run `stever init node`",
+        );
+        return;
+    };
+
+    match system::disk_space(path) {
+        Ok(space) if space.available * 100 < space.total * MIN_AVAILABLE_PERCENT => report.fail(
+            NAME,
+            format!(
+                "only {} GiB of {} GiB available at {}",
+                space.available >> 30,
+                space.total >> 30,
+                path.display()
+            ),
+            "free some space or move the node DB to a bigger disk",
+        ),
+        Ok(_) => report.pass(NAME),
+        Err(e) => report.fail(NAME, format!("{e:?}"), "check the node DB path"),
+    }
+}
+
+async fn check_systemd(dirs: &ProjectDirs, report: &mut Report) {
+    let services = [
+        (VALIDATOR_SERVICE, &dirs.validator_service),
+        (VALIDATOR_MANAGER_SERVICE, &dirs.validator_manager_service),
+    ];
+
+    for (service, path) in services {
+        if !path.exists() {
+            report.fail(
+                service,
+                format!("{} not found", path.display()),
+                "run `sudo stever init systemd`",
+            );
+            continue;
+        }
+
+        match systemd_is_active(service).await {
+            Ok(true) => report.pass(service),
+            Ok(false) => report.fail(
+                service,
+                "service is not active",
+                format!(
+                    "run `sudo systemctl restart {service}` and check `journalctl -u {service}`"
+                ),
+            ),
+            Err(e) => report.fail(service, format!("{e:?}"), "make sure systemd is available"),
+        }
+    }
+}
+
+async fn systemd_is_active(service: &str) -> Result<bool> {
+    let status = Command::new("systemctl")
+        .stdout(Stdio::null())
+        .arg("is-active")
+        .arg("--quiet")
+        .arg(service)
+        .status()
+        .await
+        .context("failed to get service status")?;
+    Ok(status.success())
+}
+
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn pass(&mut self, name: &'static str) {
+        self.checks.push(Check {
+            name,
+            status: CheckStatus::Pass,
+            details: None,
+            hint: None,
+        });
+    }
+
+    fn skip(&mut self, name: &'static str, details: &'static str) {
+        self.checks.push(Check {
+            name,
+            status: CheckStatus::Skip,
+            details: Some(details.to_owned()),
+            hint: None,
+        });
+    }
+
+    fn fail(&mut self, name: &'static str, details: impl Into<String>, hint: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            status: CheckStatus::Fail,
+            details: Some(details.into()),
+            hint: Some(hint.into()),
+        });
+    }
+
+    fn failed(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Fail)
+            .count()
+    }
+
+    fn print(&self) {
+        for check in &self.checks {
+            match check.status {
+                CheckStatus::Pass => {
+                    println!("{}", console::style(format!("✔ {}", check.name)).green());
+                }
+                CheckStatus::Skip => {
+                    println!(
+                        "{} {}",
+                        console::style(format!("- {}", check.name)).dim(),
+                        note(check.details.as_deref().unwrap_or_default()),
+                    );
+                }
+                CheckStatus::Fail => {
+                    print_error(format_args!(
+                        "{}: {}",
+                        check.name,
+                        check.details.as_deref().unwrap_or_default()
+                    ));
+                    if let Some(hint) = &check.hint {
+                        println!("  {}", console::style(format!("hint: {hint}")).dim());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Pass,
+    Skip,
+    Fail,
+}
@@ -6,7 +6,11 @@ use anyhow::Result;
 use argh::FromArgs;
 
 use super::CliContext;
-use crate::exporter::{Exporter, ExporterTarget, FileExporterTarget, HttpExporterTarget, Metrics};
+use crate::config::{AppConfig, AppConfigValidator};
+use crate::contracts::DePool;
+use crate::exporter::{
+    Exporter, ExporterTarget, FileExporterTarget, HttpExporterTarget, Metrics, StrategiesSource,
+};
 use crate::network::NodeTcpRpc;
 
 #[derive(FromArgs)]
@@ -34,6 +38,7 @@ impl Cmd {
     pub async fn run(self, ctx: CliContext) -> Result<()> {
         let config = ctx.load_config()?;
         let node_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let strategies = make_strategies_source(&config, &node_rpc);
 
         match (self.once, self.file, self.addr) {
             (_, None, None) => Err(ExporterError::NoExporters.into()),
@@ -41,9 +46,14 @@ impl Cmd {
             (true, Some(file), _) => {
                 let exporter = FileExporterTarget::new(file);
                 let stats = node_rpc.get_stats().await?;
+                let strategies = match &strategies {
+                    Some(source) => Some(source.collect().await?),
+                    None => None,
+                };
                 let metrics = Metrics {
                     collected_at: broxus_util::now(),
                     stats: &stats,
+                    strategies: strategies.as_deref(),
                 };
                 exporter.write(&metrics)
             }
@@ -57,13 +67,39 @@ impl Cmd {
                 }
 
                 let interval = Duration::from_secs(self.interval as u64);
-                Exporter::new(node_rpc, interval, targets).serve().await;
+                let mut exporter = Exporter::new(node_rpc, interval, targets);
+                if let Some(strategies) = strategies {
+                    exporter = exporter.with_strategies(strategies);
+                }
+                exporter.serve().await;
                 Ok(())
             }
         }
     }
 }
 
+/// Strategies are only exported for the stEVER DePool validator
+fn make_strategies_source(config: &AppConfig, node_rpc: &NodeTcpRpc) -> Option<StrategiesSource> {
+    let validator = match &config.validator {
+        Some(AppConfigValidator::DePool(validator)) => validator,
+        _ => return None,
+    };
+    match validator.depool_type.version() {
+        Ok(version) if version.capabilities.stever => {}
+        Ok(_) => return None,
+        Err(e) => {
+            tracing::warn!("strategies are not exported: {e:?}");
+            return None;
+        }
+    }
+
+    let depool = DePool::offline(validator.depool_type.clone(), validator.depool.clone());
+    Some(StrategiesSource {
+        depool,
+        node_rpc: node_rpc.clone(),
+    })
+}
+
 #[derive(thiserror::Error, Debug)]
 enum ExporterError {
     #[error("no exporters specified")]
@@ -0,0 +1,171 @@
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use broxus_util::serde_optional_string;
+use serde::Deserialize;
+
+use crate::config::DePoolType;
+
+/// Predefined answers for the non-interactive initialization
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Answers {
+    /// Allow overwriting the existing configs which differ from the answers
+    pub overwrite: bool,
+    /// Node configuration answers
+    pub node: Option<NodeAnswers>,
+    /// Systemd services answers
+    pub systemd: Option<SystemdAnswers>,
+    /// Validation configuration answers
+    pub validator: Option<ValidatorAnswers>,
+}
+
+impl Answers {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(path).context("failed to read answers file")?;
+        toml::from_str(&content).context("failed to deserialize answers file")
+    }
+
+    pub fn systemd(&self) -> SystemdAnswers {
+        self.systemd.clone().unwrap_or_default()
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeAnswers {
+    /// `mainnet`, `testnet` or an URL of the global config
+    pub network: Option<String>,
+    /// Control server listen address (`localhost` if not specified)
+    pub control_listen: ListenAddrAnswer,
+    /// Control server port
+    pub control_port: Option<u16>,
+    /// Public ip of the node (resolved automatically if not specified)
+    pub public_ip: Option<Ipv4Addr>,
+    /// Server ADNL port
+    pub adnl_port: Option<u16>,
+    /// Absolute path to the node DB
+    pub db_path: Option<PathBuf>,
+    /// Node repo URL (the default repo if not specified)
+    pub node_repo: Option<String>,
+    /// Force download and build the latest node
+    pub rebuild: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenAddrAnswer {
+    #[default]
+    Localhost,
+    Any,
+}
+
+impl From<ListenAddrAnswer> for Ipv4Addr {
+    fn from(addr: ListenAddrAnswer) -> Self {
+        match addr {
+            ListenAddrAnswer::Localhost => Ipv4Addr::LOCALHOST,
+            ListenAddrAnswer::Any => Ipv4Addr::UNSPECIFIED,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystemdAnswers {
+    /// The user from which the services will work
+    pub user: Option<String>,
+    /// Enable autostart services at system startup
+    pub enable: bool,
+    /// Restart services after configuration
+    pub restart: bool,
+}
+
+impl Default for SystemdAnswers {
+    fn default() -> Self {
+        Self {
+            user: None,
+            enable: true,
+            restart: true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "lowercase", tag = "type")]
+pub enum ValidatorAnswers {
+    Single(SingleValidatorAnswers),
+    DePool(DePoolValidatorAnswers),
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SingleValidatorAnswers {
+    /// Validator wallet keys source
+    #[serde(default)]
+    pub wallet_keys: Option<KeysAnswer>,
+    /// Stake per round (in EVER)
+    pub stake_per_round: Option<u64>,
+    /// Stake factor (1.0..3.0)
+    pub stake_factor: Option<f64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DePoolValidatorAnswers {
+    /// Validator wallet keys source
+    #[serde(default)]
+    pub wallet_keys: Option<KeysAnswer>,
+    /// DePool keys source
+    #[serde(default)]
+    pub depool_keys: Option<KeysAnswer>,
+    /// Existing DePool address (a new DePool is deployed if not specified)
+    #[serde(default, with = "serde_optional_string")]
+    pub depool: Option<ton_block::MsgAddressInt>,
+    /// DePool type for the new DePool
+    #[serde(default)]
+    pub depool_type: Option<DePoolType>,
+    /// Minimum participant stake (in EVER)
+    #[serde(default)]
+    pub min_stake: Option<u64>,
+    /// Validator assurance (in EVER)
+    #[serde(default)]
+    pub validator_assurance: Option<u64>,
+    /// Participant reward fraction (%, 1..99)
+    #[serde(default)]
+    pub participant_reward_fraction: Option<u8>,
+    /// Stake factor (1.0..3.0)
+    #[serde(default)]
+    pub stake_factor: Option<f64>,
+    /// stEVER strategy factory to deploy new strategy
+    #[serde(default, with = "serde_optional_string")]
+    pub strategy_factory: Option<ton_block::MsgAddressInt>,
+    /// Existing stEVER strategy
+    #[serde(default, with = "serde_optional_string")]
+    pub strategy: Option<ton_block::MsgAddressInt>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub enum KeysAnswer {
+    /// Use keys which are already stored in the keys directory
+    Existing,
+    /// Generate new keys
+    Generate,
+    /// Import seed phrase or secret from the file
+    SeedFile(PathBuf),
+    /// Import seed phrase
+    Seed(String),
+    /// Import secret key (hex or base64)
+    Secret(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnswersError {
+    #[error("missing answer `{0}`")]
+    MissingAnswer(String),
+    #[error("invalid answer `{0}`: {1}")]
+    InvalidAnswer(&'static str, String),
+    #[error("{0} (set `{1} = true` to overwrite)")]
+    OverwriteNotAllowed(String, &'static str),
+}
@@ -1,12 +1,14 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::{Context, Result};
 use argh::FromArgs;
 use console::style;
 use dialoguer::theme::Theme;
-use dialoguer::{Input, Select};
+use dialoguer::{Input, Password};
 
+use super::answers::*;
+use super::prompt::Prompt;
 use crate::cli::{CliContext, ProjectDirs};
 use crate::config::*;
 use crate::contracts::*;
@@ -25,13 +27,24 @@ const DEFAULT_STRATEGY_FACTORY: &str =
 #[derive(FromArgs)]
 /// Deploys contracts required for validation
 #[argh(subcommand, name = "contracts")]
-pub struct Cmd {}
+pub struct Cmd {
+    /// path to the answers file for the non-interactive mode
+    #[argh(option)]
+    pub answers: Option<PathBuf>,
+}
 
 impl Cmd {
-    pub async fn run(self, theme: &dyn Theme, ctx: &CliContext) -> Result<()> {
+    pub async fn run(mut self, theme: &dyn Theme, ctx: &CliContext) -> Result<()> {
+        let (prompt, answers) = Prompt::with_answers(theme, self.answers.take())?;
+        self.run_with(prompt, &answers, ctx)
+    }
+
+    pub fn run_with(self, prompt: Prompt, answers: &Answers, ctx: &CliContext) -> Result<()> {
         let mut config = ctx.load_config()?;
         let dirs = ctx.dirs();
 
+        let validator = prompt.required(answers.validator.as_ref(), "validator")?;
+
         // Create keys directory if it doesn't exist
         if !dirs.keys_dir.exists() {
             std::fs::create_dir_all(&dirs.keys_dir).context("failed to create keys dir")?;
@@ -39,77 +52,83 @@ impl Cmd {
 
         // Check whether validation was already configured
         if config.validator.is_some()
-            && !confirm(
-                theme,
-                false,
+            && !prompt.confirm_overwrite(
                 "Validator is already configured. Update config?",
+                false,
+                answers.overwrite,
+                "overwrite",
             )?
         {
             return Ok(());
         }
 
         // Select validator type
-        match Select::with_theme(theme)
-            .with_prompt("Select validator type")
-            .item("Single")
-            .item("DePool")
-            .default(0)
-            .interact()?
-        {
+        match prompt.select(
+            "Select validator type",
+            &["Single", "DePool"],
+            0,
+            validator.map(|answers| matches!(answers, ValidatorAnswers::DePool(_)) as usize),
+            "validator.type",
+        )? {
             // Prepare validator as a single node
-            0 => prepare_single_validator(theme, dirs, &mut config),
+            0 => {
+                let default_answers = SingleValidatorAnswers::default();
+                let answers = match validator {
+                    Some(ValidatorAnswers::Single(answers)) => answers,
+                    _ => &default_answers,
+                };
+                prepare_single_validator(prompt, answers, dirs, &mut config)
+            }
             // Prepare validator as a depool
-            _ => prepare_depool_validator(theme, dirs, &mut config),
+            _ => {
+                let default_answers = DePoolValidatorAnswers::default();
+                let answers = match validator {
+                    Some(ValidatorAnswers::DePool(answers)) => answers,
+                    _ => &default_answers,
+                };
+                prepare_depool_validator(prompt, answers, dirs, &mut config)
+            }
         }
     }
 }
 
 fn prepare_single_validator(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &SingleValidatorAnswers,
     dirs: &ProjectDirs,
     app_config: &mut AppConfig,
 ) -> Result<()> {
     use crate::contracts::*;
 
-    const MIN_STAKE: u64 = 10_000 * ONE_EVER as u64;
-    const MAX_STAKE: u64 = 10_000_000 * ONE_EVER as u64;
-
     let mut steps = Steps::new(2);
 
     // Prepare validator wallet
     steps.next("Creating validator wallet");
-    let (_, keypair) = KeysSelector {
-        theme,
-        prompt: "Validator wallet",
+    let (_, public) = KeysSelector {
+        prompt,
+        title: "Validator wallet",
         path: &dirs.validator_keys,
         allow_new: true,
+        answer: answers.wallet_keys.as_ref(),
+        name: "validator.wallet_keys",
     }
     .interact()?;
 
-    let wallet_address = wallet::compute_wallet_address(-1, &keypair.public);
+    let wallet_address = wallet::compute_wallet_address(-1, &public);
 
     // Configure stake params
     steps.next("Configuring the stake");
 
     // Configure stake per round
-    let stake_per_round: u64 = Input::with_theme(theme)
-        .with_prompt("Stake per round (EVER)")
-        .validate_with(|stake: &u64| match stake.saturating_mul(ONE_EVER as u64) {
-            x if x > MAX_STAKE => Err(format!(
-                "Too big stake (max stake is {} EVER)",
-                Ever(MAX_STAKE)
-            )),
-            x if x < MIN_STAKE => Err(format!(
-                "Too small stake (min stake is {} EVER)",
-                Ever(MIN_STAKE)
-            )),
-            _ => Ok(()),
-        })
-        .interact_text()?;
+    let stake_per_round: u64 = prompt
+        .input("Stake per round (EVER)", "validator.stake_per_round")
+        .answer(answers.stake_per_round)
+        .validate(|stake: &u64| validate_stake_per_round(*stake))
+        .interact()?;
     let stake_per_round = stake_per_round.saturating_mul(ONE_EVER as u64);
 
     // Configure stake factor
-    let stake_factor = configure_stake_factor(theme)?;
+    let stake_factor = configure_stake_factor(prompt, answers.stake_factor)?;
 
     // Save config
     app_config.validator = Some(AppConfigValidator::Single(AppConfigValidatorSingle {
@@ -122,6 +141,32 @@ fn prepare_single_validator(
     // Done
     steps.next("Validator configured successfully. Great!");
 
+    print_single_validator_summary(dirs, wallet_address, stake_per_round);
+    Ok(())
+}
+
+fn validate_stake_per_round(stake: u64) -> Result<(), String> {
+    const MIN_STAKE: u64 = 10_000 * ONE_EVER as u64;
+    const MAX_STAKE: u64 = 10_000_000 * ONE_EVER as u64;
+
+    match stake.saturating_mul(ONE_EVER as u64) {
+        x if x > MAX_STAKE => Err(format!(
+            "Too big stake (max stake is {} EVER)",
+            Ever(MAX_STAKE)
+        )),
+        x if x < MIN_STAKE => Err(format!(
+            "Too small stake (min stake is {} EVER)",
+            Ever(MIN_STAKE)
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn print_single_validator_summary(
+    dirs: &ProjectDirs,
+    wallet_address: ton_block::MsgAddressInt,
+    stake_per_round: u64,
+) {
     let target_balance = stake_per_round as u128 * 2 + Wallet::INITIAL_BALANCE;
 
     println!(
@@ -144,25 +189,33 @@ fn prepare_single_validator(
             .bold(),
         console::style(dirs.validator_keys.display()).bold()
     );
-
-    Ok(())
 }
 
 fn prepare_depool_validator(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &DePoolValidatorAnswers,
     dirs: &ProjectDirs,
     app_config: &mut AppConfig,
 ) -> Result<()> {
     use crate::contracts::*;
 
-    let (mut steps, params) = match Select::with_theme(theme)
-        .item("Deploy new DePool")
-        .item("Use existing DePool")
-        .default(0)
-        .interact()?
-    {
-        0 => prepare_new_depool(theme, dirs)?,
-        _ => prepare_existing_depool(theme, dirs)?,
+    if answers.strategy.is_some() && answers.strategy_factory.is_some() {
+        return Err(AnswersError::InvalidAnswer(
+            "validator.strategy",
+            "strategy and strategy factory are mutually exclusive".to_owned(),
+        )
+        .into());
+    }
+
+    let (mut steps, params) = match prompt.select(
+        "DePool",
+        &["Deploy new DePool", "Use existing DePool"],
+        0,
+        Some(answers.depool.is_some() as usize),
+        "validator.depool",
+    )? {
+        0 => prepare_new_depool(prompt, answers, dirs)?,
+        _ => prepare_existing_depool(prompt, answers, dirs)?,
     };
 
     // Save config
@@ -172,6 +225,11 @@ fn prepare_depool_validator(
     // Done
     steps.next("Everything is ready for the validation!");
 
+    print_depool_validator_summary(dirs, params);
+    Ok(())
+}
+
+fn print_depool_validator_summary(dirs: &ProjectDirs, params: AppConfigValidatorDePool) {
     println!(
         "\n{}\n{}\n\n{}\n{}",
         style("Validator wallet address:").green().bold(),
@@ -183,9 +241,10 @@ fn prepare_depool_validator(
     if let Some(deployment) = params.deploy {
         let strategy_fee = StrategyFactory::DEPLOYMENT_FEE;
 
+        let depool_initial_balance = params.balances.initial_balance as u128;
         let mut target_balance = deployment.validator_assurance as u128 * 2
             + Wallet::INITIAL_BALANCE
-            + DePool::INITIAL_BALANCE;
+            + depool_initial_balance;
 
         let mut factory_deployment_note = "".to_owned();
         if params.strategy_factory.is_some() {
@@ -204,7 +263,7 @@ fn prepare_depool_validator(
                  {factory_deployment_note}\
                  \n  • 2 x {} EVER, stakes for each round",
                 Ever(Wallet::INITIAL_BALANCE),
-                Ever(DePool::INITIAL_BALANCE),
+                Ever(depool_initial_balance),
                 Ever(deployment.validator_assurance),
             ))
             .dim()
@@ -219,12 +278,11 @@ fn prepare_depool_validator(
         console::style(dirs.validator_keys.display()).bold(),
         console::style(dirs.depool_keys.display()).bold(),
     );
-
-    Ok(())
 }
 
 fn prepare_new_depool(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &DePoolValidatorAnswers,
     dirs: &ProjectDirs,
 ) -> Result<(Steps, AppConfigValidatorDePool)> {
     let mut steps = Steps::new(2);
@@ -232,84 +290,100 @@ fn prepare_new_depool(
     // Prepare validator wallet
     steps.next("Creating validator wallet");
 
-    let (is_new_wallet, wallet_keypair) = KeysSelector {
-        theme,
-        prompt: "Validator wallet",
+    let (is_new_wallet, wallet_public) = KeysSelector {
+        prompt,
+        title: "Validator wallet",
         path: &dirs.validator_keys,
         allow_new: true,
+        answer: answers.wallet_keys.as_ref(),
+        name: "validator.wallet_keys",
     }
     .interact()?;
 
     // TODO: Select wallet type
 
-    let wallet_address = wallet::compute_wallet_address(0, &wallet_keypair.public);
+    let wallet_address = wallet::compute_wallet_address(0, &wallet_public);
 
     // Create depool
     steps.next("Creating DePool");
 
     // Generate depool keys
-    let (is_new_depool, depool_keypair) = KeysSelector {
-        theme,
-        prompt: "DePool",
+    let (is_new_depool, depool_public) = KeysSelector {
+        prompt,
+        title: "DePool",
         path: &dirs.depool_keys,
         allow_new: true,
+        answer: answers.depool_keys.as_ref(),
+        name: "validator.depool_keys",
     }
     .interact()?;
 
     // Select depool type
-    let depool_type = match Select::with_theme(theme)
-        .with_prompt("Select DePool type")
-        .item("stEVER")
-        .item("DePoolV3")
-        .default(0)
-        .interact()?
-    {
-        0 => DePoolType::StEver,
-        _ => DePoolType::DefaultV3,
+    let versions = depool::DePoolRegistry::get().versions().collect::<Vec<_>>();
+    let items = versions
+        .iter()
+        .map(|version| match &version.ty {
+            DePoolType::StEver => "stEVER",
+            DePoolType::DefaultV3 => "DePoolV3",
+            DePoolType::Custom(name) => name.as_str(),
+        })
+        .collect::<Vec<_>>();
+    let position = |ty: &DePoolType| versions.iter().position(|version| &version.ty == ty);
+    let depool_type_answer = match &answers.depool_type {
+        Some(ty) => Some(position(ty).ok_or_else(|| {
+            AnswersError::InvalidAnswer("validator.depool_type", format!("unknown type {ty}"))
+        })?),
+        None => None,
     };
+    let depool_type = prompt.select(
+        "Select DePool type",
+        &items,
+        0,
+        depool_type_answer,
+        "validator.depool_type",
+    )?;
+    let depool_type = versions[depool_type].ty.clone();
 
     // Compute depool address
     let depool_address = depool_type
-        .compute_depool_address(&depool_keypair.public)
+        .compute_depool_address(&depool_public)
         .context("failed to compute DePool address")?;
 
     // Configure min participants stake
-    let min_stake: u64 = Input::with_theme(theme)
-        .with_prompt("Minimum participant stake (EVER)")
+    let min_stake: u64 = prompt
+        .input("Minimum participant stake (EVER)", "validator.min_stake")
         .default(DEFAULT_MIN_STAKE)
-        .validate_with(|value: &u64| match *value {
-            x if x < 10 => Err("Minimum stake is too small (< 10 EVER)"),
-            _ => Ok(()),
-        })
-        .interact_text()?;
+        .answer(answers.min_stake)
+        .validate(|value: &u64| validate_min_stake(*value))
+        .interact()?;
 
     // Configure validator assurance
-    let validator_assurance: u64 = Input::with_theme(theme)
-        .with_prompt("Validator assurance (EVER)")
+    let validator_assurance: u64 = prompt
+        .input(
+            "Validator assurance (EVER)",
+            "validator.validator_assurance",
+        )
         .default(DEFAULT_VALIDATOR_ASSURANCE)
-        .validate_with(|value: &u64| match *value {
-            x if x < 10 => Err("Too small validator assurance (< 10 EVER)"),
-            x if x < min_stake => Err("Validator assurance is less than minimum stake"),
-            _ => Ok(()),
-        })
-        .interact_text()?;
+        .answer(answers.validator_assurance)
+        .validate(|value: &u64| validate_validator_assurance(*value, min_stake))
+        .interact()?;
 
     let min_stake = min_stake.saturating_mul(ONE_EVER as u64);
     let validator_assurance = validator_assurance.saturating_mul(ONE_EVER as u64);
 
     // Configure participant reward fraction
-    let participant_reward_fraction: u8 = Input::with_theme(theme)
-        .with_prompt("Participant reward fraction (%, 1..99)")
+    let participant_reward_fraction: u8 = prompt
+        .input(
+            "Participant reward fraction (%, 1..99)",
+            "validator.participant_reward_fraction",
+        )
         .default(DEFAULT_PARTICIPANT_REWARD_FRACTION)
-        .validate_with(|value: &u8| match *value {
-            x if x < 1 => Err("Too small fraction (< 1%)"),
-            x if x > 99 => Err("Too big fraction (> 99%)"),
-            _ => Ok(()),
-        })
-        .interact_text()?;
+        .answer(answers.participant_reward_fraction)
+        .validate(|value: &u8| validate_participant_reward_fraction(*value))
+        .interact()?;
 
     // Configure stake factor
-    let stake_factor = configure_stake_factor(theme)?;
+    let stake_factor = configure_stake_factor(prompt, answers.stake_factor)?;
 
     let mut params = AppConfigValidatorDePool {
         owner: wallet_address,
@@ -323,21 +397,29 @@ fn prepare_new_depool(
             validator_assurance,
             participant_reward_fraction,
         }),
+        balances: Default::default(),
     };
 
     // Configure stEVER strategies stuff
-    if let DePoolType::StEver = depool_type {
+    if params.depool_type.version()?.capabilities.stever {
         let strategy = if is_new_wallet || is_new_depool {
             // Always deploy new strategy is new keys were generated
-            StrategyAction::DeployNew.run(theme)?
+            anyhow::ensure!(
+                answers.strategy.is_none(),
+                "existing strategy can't be used with new keys"
+            );
+            StrategyAction::DeployNew.run(prompt, answers)?
         } else {
             // Allow specifying existing strategy otherwise
             let items = [StrategyAction::DeployNew, StrategyAction::SetExisting];
-            let action = Select::with_theme(theme)
-                .items(&items)
-                .default(0)
-                .interact()?;
-            items[action].run(theme)?
+            let action = prompt.select(
+                "stEVER strategy",
+                &items,
+                0,
+                Some(answers.strategy.is_some() as usize),
+                "validator.strategy",
+            )?;
+            items[action].run(prompt, answers)?
         };
         params.strategy_factory = strategy.factory;
         params.strategy = strategy.existing;
@@ -347,8 +429,35 @@ fn prepare_new_depool(
     Ok((steps, params))
 }
 
+fn validate_min_stake(min_stake: u64) -> Result<(), &'static str> {
+    match min_stake {
+        x if x < 10 => Err("Minimum stake is too small (< 10 EVER)"),
+        _ => Ok(()),
+    }
+}
+
+fn validate_validator_assurance(
+    validator_assurance: u64,
+    min_stake: u64,
+) -> Result<(), &'static str> {
+    match validator_assurance {
+        x if x < 10 => Err("Too small validator assurance (< 10 EVER)"),
+        x if x < min_stake => Err("Validator assurance is less than minimum stake"),
+        _ => Ok(()),
+    }
+}
+
+fn validate_participant_reward_fraction(fraction: u8) -> Result<(), &'static str> {
+    match fraction {
+        x if x < 1 => Err("Too small fraction (< 1%)"),
+        x if x > 99 => Err("Too big fraction (> 99%)"),
+        _ => Ok(()),
+    }
+}
+
 fn prepare_existing_depool(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &DePoolValidatorAnswers,
     dirs: &ProjectDirs,
 ) -> Result<(Steps, AppConfigValidatorDePool)> {
     let mut steps = Steps::new(2);
@@ -356,42 +465,47 @@ fn prepare_existing_depool(
     // Prepare validator wallet
     steps.next("Creating validator wallet");
 
-    let (_, wallet_keypair) = KeysSelector {
-        theme,
-        prompt: "Validator wallet seed phrase",
+    let (_, wallet_public) = KeysSelector {
+        prompt,
+        title: "Validator wallet",
         path: &dirs.validator_keys,
         allow_new: false,
+        answer: answers.wallet_keys.as_ref(),
+        name: "validator.wallet_keys",
     }
     .interact()?;
 
     // TODO: Select wallet type
 
-    let wallet_address = wallet::compute_wallet_address(0, &wallet_keypair.public);
+    let wallet_address = wallet::compute_wallet_address(0, &wallet_public);
 
     // Prepare validator wallet
     steps.next("Creating DePool");
 
     // Configure existing depool address
-    let AddressInput(depool_address) = Input::with_theme(theme)
-        .with_prompt("Specify existing DePool address")
-        .interact_text()?;
+    let AddressInput(depool_address) = prompt
+        .input("Specify existing DePool address", "validator.depool")
+        .answer(answers.depool.clone().map(AddressInput))
+        .interact()?;
 
     // Generate depool keys
-    let (_, depool_keypair) = KeysSelector {
-        theme,
-        prompt: "DePool seed phrase",
+    let (_, depool_public) = KeysSelector {
+        prompt,
+        title: "DePool",
         path: &dirs.depool_keys,
         allow_new: false,
+        answer: answers.depool_keys.as_ref(),
+        name: "validator.depool_keys",
     }
     .interact()?;
 
     // Guess depool type from pubkey and the specified address
-    let depool_type = DePoolType::guess(&depool_address, &depool_keypair.public)
+    let depool_type = DePoolType::guess(&depool_address, &depool_public)
         .context("failed to guess depool type")?
         .context("invalid keys or unknown DePool contract")?;
 
     // Configure stake factor
-    let stake_factor = configure_stake_factor(theme)?;
+    let stake_factor = configure_stake_factor(prompt, answers.stake_factor)?;
 
     // Done
     let mut params = AppConfigValidatorDePool {
@@ -402,16 +516,25 @@ fn prepare_existing_depool(
         strategy_factory: None,
         strategy: None,
         deploy: None,
+        balances: Default::default(),
     };
 
     // Configure stEVER strategies stuff
-    if let DePoolType::StEver = depool_type {
+    if params.depool_type.version()?.capabilities.stever {
         let items = StrategyAction::all();
-        let action = Select::with_theme(theme)
-            .items(&items)
-            .default(0)
-            .interact()?;
-        let strategy = items[action].run(theme)?;
+        let answer = match (&answers.strategy, &answers.strategy_factory) {
+            (Some(_), _) => StrategyAction::SetExisting,
+            (None, Some(_)) => StrategyAction::DeployNew,
+            (None, None) => StrategyAction::Skip,
+        };
+        let action = prompt.select(
+            "stEVER strategy",
+            &items,
+            0,
+            items.iter().position(|item| *item == answer),
+            "validator.strategy",
+        )?;
+        let strategy = items[action].run(prompt, answers)?;
         params.strategy_factory = strategy.factory;
         params.strategy = strategy.existing;
     }
@@ -425,41 +548,50 @@ impl DePoolType {
         address: &ton_block::MsgAddressInt,
         pubkey: &ed25519_dalek::PublicKey,
     ) -> Result<Option<Self>> {
-        for ty in [Self::DefaultV3, Self::StEver] {
-            if address == &ty.compute_depool_address(pubkey)? {
-                return Ok(Some(ty));
+        for version in depool::DePoolRegistry::get().versions() {
+            if address == &version.compute_depool_address(pubkey)? {
+                return Ok(Some(version.ty.clone()));
             }
         }
         Ok(None)
     }
 }
 
-fn configure_stake_factor(theme: &dyn Theme) -> Result<u32> {
-    const MIN_STAKE_FACTOR: f64 = 1.0;
-    const MAX_STAKE_FACTOR: f64 = 3.0;
+const MIN_STAKE_FACTOR: f64 = 1.0;
+const MAX_STAKE_FACTOR: f64 = 3.0;
 
+fn configure_stake_factor(prompt: Prompt, answer: Option<f64>) -> Result<u32> {
+    // Configure factor
+    let stake_factor: f64 = prompt
+        .input("Stake factor", "validator.stake_factor")
+        .initial(DEFAULT_STAKE_FACTOR)
+        .answer(answer)
+        .validate(|factor: &f64| validate_stake_factor(*factor))
+        .interact()?;
+    Ok(to_stake_factor_repr(stake_factor))
+}
+
+fn validate_stake_factor(factor: f64) -> Result<(), String> {
+    match factor {
+        x if x > MAX_STAKE_FACTOR => {
+            Err(format!("Too big stake factor (max is {MAX_STAKE_FACTOR})"))
+        }
+        x if x < MIN_STAKE_FACTOR => Err(format!(
+            "Too small stake factor (min is {MIN_STAKE_FACTOR})"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn to_stake_factor_repr(stake_factor: f64) -> u32 {
     fn to_factor_repr(factor: f64) -> u32 {
         (factor * 65536.0) as u32
     }
 
-    // Configure factor
-    let stake_factor: f64 = Input::with_theme(theme)
-        .with_prompt("Stake factor")
-        .with_initial_text(DEFAULT_STAKE_FACTOR.to_string())
-        .validate_with(|factor: &f64| match *factor {
-            x if x > MAX_STAKE_FACTOR => {
-                Err(format!("Too big stake factor (max is {MAX_STAKE_FACTOR})"))
-            }
-            x if x < MIN_STAKE_FACTOR => Err(format!(
-                "Too small stake factor (min is {MIN_STAKE_FACTOR})"
-            )),
-            _ => Ok::<_, String>(()),
-        })
-        .interact_text()?;
-    Ok(std::cmp::min(
+    std::cmp::min(
         to_factor_repr(stake_factor),
         to_factor_repr(MAX_STAKE_FACTOR),
-    ))
+    )
 }
 
 selector_variant!(StrategyAction, {
@@ -469,7 +601,7 @@ selector_variant!(StrategyAction, {
 });
 
 impl StrategyAction {
-    fn run(self, theme: &dyn Theme) -> Result<Strategy> {
+    fn run(self, prompt: Prompt, answers: &DePoolValidatorAnswers) -> Result<Strategy> {
         let default_strategy_factory: ton_block::MsgAddressInt =
             DEFAULT_STRATEGY_FACTORY.parse().unwrap();
 
@@ -479,10 +611,14 @@ impl StrategyAction {
                 existing: None,
             },
             Self::DeployNew => {
-                let AddressInput(factory) = Input::with_theme(theme)
-                    .with_prompt("Specify stEVER strategy factory")
+                let AddressInput(factory) = prompt
+                    .input(
+                        "Specify stEVER strategy factory",
+                        "validator.strategy_factory",
+                    )
                     .default(AddressInput(default_strategy_factory))
-                    .interact_text()?;
+                    .answer(answers.strategy_factory.clone().map(AddressInput))
+                    .interact()?;
 
                 Strategy {
                     factory: Some(factory),
@@ -495,9 +631,10 @@ impl StrategyAction {
                     for the current DePool"
                 );
 
-                let AddressInput(existing) = Input::with_theme(theme)
-                    .with_prompt("Specify strategy address")
-                    .interact_text()?;
+                let AddressInput(existing) = prompt
+                    .input("Specify strategy address", "validator.strategy")
+                    .answer(answers.strategy.clone().map(AddressInput))
+                    .interact()?;
 
                 Strategy {
                     factory: None,
@@ -514,14 +651,16 @@ struct Strategy {
 }
 
 struct KeysSelector<'a, P> {
-    theme: &'a dyn Theme,
-    prompt: &'a str,
+    prompt: Prompt<'a>,
+    title: &'a str,
     path: P,
     allow_new: bool,
+    answer: Option<&'a KeysAnswer>,
+    name: &'static str,
 }
 
 impl<P: AsRef<Path>> KeysSelector<'_, P> {
-    fn interact(self) -> Result<(bool, ed25519_dalek::Keypair)> {
+    fn interact(self) -> Result<(bool, ed25519_dalek::PublicKey)> {
         selector_variant!(Action, {
             Existing => "Use existing keys",
             Generate => "Generate new keys",
@@ -529,11 +668,28 @@ impl<P: AsRef<Path>> KeysSelector<'_, P> {
         });
 
         let path = self.path.as_ref();
+        let prompt = self.prompt;
 
         // Helper method
         let store_keys = |keys: &StoredKeys| -> Result<bool> {
-            if path.exists() && !confirm(self.theme, false, "Overwrite existing keys?")? {
-                return Ok(false);
+            if path.exists() {
+                // Leave the file as is if it already contains the same keys
+                let same_keys = matches!(
+                    StoredKeys::load_public(path),
+                    Ok(public) if public == keys.as_keypair().public
+                );
+                if same_keys {
+                    return Ok(true);
+                }
+
+                if !prompt.confirm("Overwrite existing keys?", false, false)? {
+                    anyhow::ensure!(
+                        prompt.is_interactive(),
+                        "different keys file already exists at {}",
+                        path.display()
+                    );
+                    return Ok(false);
+                }
             }
             keys.store(path)?;
             Ok(true)
@@ -549,48 +705,137 @@ impl<P: AsRef<Path>> KeysSelector<'_, P> {
         }
         items.push(Action::Import);
 
-        let mut select = Select::with_theme(self.theme);
-        select.items(&items).default(0);
+        // Map the answer to the selector item
+        let mut seed_answer = None;
+        let answer = match prompt.required(self.answer, self.name)? {
+            Some(answer) => {
+                let parse_seed = |seed: &str| {
+                    SeedOrSecretInput::from_str(seed)
+                        .map_err(|e| AnswersError::InvalidAnswer(self.name, e.to_string()))
+                };
+                let invalid_kind = |expected: &str| {
+                    AnswersError::InvalidAnswer(self.name, format!("expected {expected}"))
+                };
+
+                let action = match answer {
+                    KeysAnswer::Existing => Action::Existing,
+                    KeysAnswer::Generate => Action::Generate,
+                    KeysAnswer::SeedFile(seed_file) => {
+                        let seed = std::fs::read_to_string(seed_file)
+                            .context("failed to read seed file")?;
+                        seed_answer = Some(parse_seed(&seed)?);
+                        Action::Import
+                    }
+                    // Seed phrase and secret are not guessed from the value here
+                    KeysAnswer::Seed(seed) => match parse_seed(seed)? {
+                        seed @ SeedOrSecretInput::Seed(_) => {
+                            seed_answer = Some(seed);
+                            Action::Import
+                        }
+                        SeedOrSecretInput::Secret(_) => {
+                            return Err(invalid_kind("seed phrase").into())
+                        }
+                    },
+                    KeysAnswer::Secret(secret) => match parse_seed(secret)? {
+                        secret @ SeedOrSecretInput::Secret(_) => {
+                            seed_answer = Some(secret);
+                            Action::Import
+                        }
+                        SeedOrSecretInput::Seed(_) => return Err(invalid_kind("secret key").into()),
+                    },
+                };
+
+                let index = items
+                    .iter()
+                    .position(|item| *item == action)
+                    .ok_or_else(|| {
+                        AnswersError::InvalidAnswer(
+                            self.name,
+                            match action {
+                                Action::Existing => format!("no keys found at {}", path.display()),
+                                _ => "new keys are not allowed here".to_owned(),
+                            },
+                        )
+                    })?;
+                Some(index)
+            }
+            None => None,
+        };
 
         // Try asking user until he selects a correct variant
-        let (is_new, stored_keys) = loop {
+        let (is_new, public) = loop {
             // Determine input action
             let action = if items.len() > 1 {
-                items[select.interact()?]
+                items[prompt.select(self.title, &items, 0, answer, self.name)?]
             } else {
                 items[0]
             };
 
             // Do action
             match action {
-                Action::Existing => match StoredKeys::load(path) {
-                    Ok(keys) => break (false, keys),
-                    Err(e) => {
+                // NOTE: only public key is required, so keys held
+                // in the node keystore or by the external signer are also allowed
+                Action::Existing => match StoredKeys::load_public(path) {
+                    Ok(public) => break (false, public),
+                    Err(e) if prompt.is_interactive() => {
                         print_error(format!("failed to load existing keys: {e:?}"));
                         continue;
                     }
+                    Err(e) => return Err(e.context("failed to load existing keys")),
                 },
                 Action::Generate => {
                     let keys = StoredKeys::generate()?;
                     if !store_keys(&keys)? {
                         continue;
                     }
-                    break (true, keys);
+                    break (true, keys.as_keypair().public);
                 }
                 Action::Import => {
-                    let seed: SeedOrSecretInput = Input::with_theme(self.theme)
-                        .with_prompt(format!("{} seed phrase or secret", self.prompt))
-                        .interact_text()?;
-                    let keys = seed.try_into_stored_keys()?;
+                    let seed: SeedOrSecretInput = prompt
+                        .input(format!("{} seed phrase or secret", self.title), self.name)
+                        .answer(seed_answer.clone())
+                        .interact()?;
+                    let (path, passphrase) = self.derivation_params(&seed)?;
+                    let keys = seed.try_into_stored_keys(&path, &passphrase)?;
                     if !store_keys(&keys)? {
                         continue;
                     }
-                    break (false, keys);
+                    break (false, keys.as_keypair().public);
                 }
             }
         };
 
-        Ok((is_new, stored_keys.as_keypair()))
+        Ok((is_new, public))
+    }
+
+    /// Asks for the custom account index and bip39 passphrase of the imported seed
+    /// (answers file always uses the default derivation)
+    fn derivation_params(&self, seed: &SeedOrSecretInput) -> Result<(String, String)> {
+        let default = (crypto::DEFAULT_PATH.to_owned(), String::new());
+        let theme = match self.prompt {
+            Prompt::Interactive(theme) if matches!(seed, SeedOrSecretInput::Seed(_)) => theme,
+            _ => return Ok(default),
+        };
+
+        if !confirm(
+            theme,
+            false,
+            "Use custom derivation (account index or passphrase)?",
+        )? {
+            return Ok(default);
+        }
+
+        let account: u32 = Input::with_theme(theme)
+            .with_prompt("Account index")
+            .default(0)
+            .interact_text()?;
+
+        let passphrase = Password::with_theme(theme)
+            .with_prompt("Seed passphrase (empty for none)")
+            .allow_empty_password(true)
+            .interact()?;
+
+        Ok((crypto::account_path(account), passphrase))
     }
 }
 
@@ -601,9 +846,14 @@ pub enum SeedOrSecretInput {
 }
 
 impl SeedOrSecretInput {
-    fn try_into_stored_keys(self) -> Result<StoredKeys> {
+    fn try_into_stored_keys(self, path: &str, passphrase: &str) -> Result<StoredKeys> {
         match self {
-            Self::Seed(seed) => StoredKeys::from_seed(seed),
+            Self::Seed(seed) => StoredKeys::from_seed_with_path(
+                seed,
+                StoredKeys::DEFAULT_MNEMONIC_TYPE,
+                path,
+                passphrase,
+            ),
             Self::Secret(secret) => StoredKeys::from_secret(secret),
         }
     }
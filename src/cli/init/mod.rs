@@ -1,12 +1,17 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use argh::FromArgs;
 
+use self::prompt::Prompt;
 use crate::config::{AppConfig, NodeConfig};
 
 use super::{CliContext, ProjectDirs};
 
+mod answers;
 mod contracts;
 mod node;
+mod prompt;
 mod systemd;
 
 #[derive(FromArgs)]
@@ -18,6 +23,9 @@ pub struct Cmd {
     /// force download and build the latest node
     #[argh(switch)]
     rebuild: bool,
+    /// path to the answers file for the non-interactive mode
+    #[argh(option)]
+    answers: Option<PathBuf>,
 }
 
 impl Cmd {
@@ -25,13 +33,15 @@ impl Cmd {
         let theme = &dialoguer::theme::ColorfulTheme::default();
         match self.subcommand {
             None => {
+                let (prompt, answers) = Prompt::with_answers(theme, self.answers)?;
                 node::Cmd {
                     rebuild: self.rebuild,
+                    answers: None,
                 }
-                .run(theme, &ctx)
+                .run_with(prompt, &answers, &ctx)
                 .await?;
                 println!();
-                contracts::Cmd {}.run(theme, &ctx).await
+                contracts::Cmd { answers: None }.run_with(prompt, &answers, &ctx)
             }
             Some(SubCmd::Node(cmd)) => cmd.run(theme, &ctx).await,
             Some(SubCmd::Systemd(cmd)) => cmd.run(theme, &ctx).await,
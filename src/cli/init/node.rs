@@ -6,10 +6,12 @@ use std::process::Stdio;
 use anyhow::{Context, Result};
 use argh::FromArgs;
 use dialoguer::theme::Theme;
-use dialoguer::{Completion, Input, Select};
+use dialoguer::Completion;
 use reqwest::Url;
 use tokio::process::Command;
 
+use super::answers::{Answers, AnswersError, ListenAddrAnswer, NodeAnswers};
+use super::prompt::Prompt;
 use super::systemd::{prepare_services, start_services, systemd_daemon_reload};
 use crate::cli::{CliContext, ProjectDirs};
 use crate::config::*;
@@ -20,6 +22,7 @@ const DEFAULT_LOCAL_ADNL_PORT: u16 = 0;
 const DEFAULT_ADNL_PORT: u16 = 30100;
 const DEFAULT_NODE_REPO: &str = "https://github.com/tonlabs/ton-labs-node.git";
 const DEFAULT_NODE_DB_PATH: &str = "/var/ever/rnode";
+const DB_PATH_FALLBACK: &str = "node_db";
 
 #[derive(FromArgs)]
 /// Prepares configs and binaries
@@ -28,12 +31,25 @@ pub struct Cmd {
     /// force download and build the latest node
     #[argh(switch)]
     pub rebuild: bool,
+    /// path to the answers file for the non-interactive mode
+    #[argh(option)]
+    pub answers: Option<PathBuf>,
 }
 
 impl Cmd {
-    pub async fn run(self, theme: &dyn Theme, ctx: &CliContext) -> Result<()> {
+    pub async fn run(mut self, theme: &dyn Theme, ctx: &CliContext) -> Result<()> {
+        let (prompt, answers) = Prompt::with_answers(theme, self.answers.take())?;
+        self.run_with(prompt, &answers, ctx).await
+    }
+
+    pub async fn run_with(self, prompt: Prompt, answers: &Answers, ctx: &CliContext) -> Result<()> {
         let dirs = ctx.dirs();
 
+        let default_answers = NodeAnswers::default();
+        let node_answers = prompt
+            .required(answers.node.as_ref(), "node")?
+            .unwrap_or(&default_answers);
+
         // Compute steps len
         let is_root = system::is_root();
         let mut steps = Steps::new(2 + 2 * (is_root as usize));
@@ -41,25 +57,34 @@ impl Cmd {
         steps.next("Preparing configs");
 
         // Ensure root dir exists
-        if !prepare_root_dir(theme, dirs)? {
+        if !prepare_root_dir(prompt, dirs)? {
             return Ok(());
         }
 
         // Ensure that global config exists
-        let global_config = load_global_config(theme, dirs).await?;
+        let global_config = load_global_config(prompt, node_answers, dirs).await?;
         // Ensure that node config exists
         let mut node_config = load_node_config(dirs)?;
         // Ensure that app config exists
         let mut app_config = load_app_config(dirs)?;
 
         // Configure control server
-        if !setup_control_server(theme, dirs, &mut app_config, &mut node_config)? {
+        if !setup_control_server(
+            prompt,
+            node_answers,
+            answers.overwrite,
+            dirs,
+            &mut app_config,
+            &mut node_config,
+        )? {
             return Ok(());
         }
 
         // Configure udp rpc
         if !setup_adnl(
-            theme,
+            prompt,
+            node_answers,
+            answers.overwrite,
             dirs,
             &mut app_config,
             &mut node_config,
@@ -71,19 +96,22 @@ impl Cmd {
         }
 
         // Configure node config
-        setup_node_config_paths(theme, dirs, &mut node_config)?;
+        setup_node_config_paths(prompt, node_answers, dirs, &mut node_config)?;
 
         // Clone and build the node
         steps.next("Preparing binary");
-        if !setup_binary(theme, dirs, self.rebuild).await? {
+        let force = self.rebuild || node_answers.rebuild;
+        if !setup_binary(prompt, node_answers, dirs, force).await? {
             return Ok(());
         }
 
         // Configure systemd services if running as root
         if is_root {
+            let systemd_answers = answers.systemd();
+
             // Ensure all services are created
             steps.next("Preparing services");
-            prepare_services(theme, dirs)?;
+            prepare_services(prompt, &systemd_answers, dirs)?;
 
             // Reload sysetmd
             steps.next("Reloading systemd configs");
@@ -91,7 +119,7 @@ impl Cmd {
 
             // Optionally start services
             steps.next("Node is configured now. Great!");
-            start_services(theme).await?;
+            start_services(prompt, &systemd_answers).await?;
         } else {
             // Ask user about creating systemd services
             steps.next("Node is configured now. Great!");
@@ -102,17 +130,17 @@ impl Cmd {
     }
 }
 
-fn prepare_root_dir(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<bool> {
+fn prepare_root_dir(prompt: Prompt, dirs: &ProjectDirs) -> Result<bool> {
     let root = &dirs.root;
     if root.exists() {
         // Do nothing if root directory exists
         return Ok(true);
     }
 
-    if !confirm(
-        theme,
-        root.is_absolute(),
+    if !prompt.confirm(
         format!("Create root directory? {}", note(root.display())),
+        root.is_absolute(),
+        true,
     )? {
         return Ok(false);
     }
@@ -122,7 +150,11 @@ fn prepare_root_dir(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<bool> {
     Ok(true)
 }
 
-async fn load_global_config(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<GlobalConfig> {
+async fn load_global_config(
+    prompt: Prompt,
+    answers: &NodeAnswers,
+    dirs: &ProjectDirs,
+) -> Result<GlobalConfig> {
     selector_variant!(Action, {
         Mainnet => "Everscale mainnet",
         Testnet => "Everscale testnet",
@@ -131,29 +163,32 @@ async fn load_global_config(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<Glo
 
     let global_config = &dirs.global_config;
     if !global_config.exists() {
+        // Map network answer to the selector variant
+        let (network, url) = match answers.network.as_deref() {
+            None => (None, None),
+            Some("mainnet") => (Some(0), None),
+            Some("testnet") => (Some(1), None),
+            Some(url) => {
+                let url = Url::parse(url)
+                    .map_err(|e| AnswersError::InvalidAnswer("node.network", e.to_string()))?;
+                (Some(2), Some(url))
+            }
+        };
+
         // Select network static nodes config
         let items = Action::all();
-        let data = match items[Select::with_theme(theme)
-            .with_prompt("Select network")
-            .items(&items)
-            .default(0)
-            .interact()?]
-        {
+        let network = prompt.select("Select network", &items, 0, network, "node.network")?;
+        let data = match items[network] {
             Action::Mainnet => Cow::Borrowed(GlobalConfig::MAINNET),
             Action::Testnet => Cow::Borrowed(GlobalConfig::TESTNET),
             // Try to download config
             Action::Other => {
-                let url: Url = Input::with_theme(theme)
-                    .with_prompt("Config URL")
-                    .interact_text()?;
-
-                reqwest::get(url)
-                    .await
-                    .context("failed to download global config")?
-                    .text()
-                    .await
-                    .context("failed to download global config")
-                    .map(Cow::Owned)?
+                let url: Url = prompt
+                    .input("Config URL", "node.network")
+                    .answer(url)
+                    .interact()?;
+
+                download_global_config(url).await?
             }
         };
 
@@ -165,6 +200,16 @@ async fn load_global_config(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<Glo
     GlobalConfig::load(global_config)
 }
 
+async fn download_global_config(url: Url) -> Result<Cow<'static, str>> {
+    reqwest::get(url)
+        .await
+        .context("failed to download global config")?
+        .text()
+        .await
+        .context("failed to download global config")
+        .map(Cow::Owned)
+}
+
 fn load_node_config(dirs: &ProjectDirs) -> Result<NodeConfig> {
     // Generate default log config if it doesn't exist
     let node_log_config = &dirs.node_log_config;
@@ -198,7 +243,9 @@ fn load_app_config(dirs: &ProjectDirs) -> Result<AppConfig> {
 }
 
 fn setup_control_server(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &NodeAnswers,
+    overwrite: bool,
     dirs: &ProjectDirs,
     app_config: &mut AppConfig,
     node_config: &mut NodeConfig,
@@ -212,6 +259,19 @@ fn setup_control_server(
         .get_suggested_control_port()
         .unwrap_or(DEFAULT_CONTROL_PORT);
 
+    // Select whether to append our client or replace existing ones
+    // (existing clients are kept in the non-interactive mode)
+    let append_client = |clients: &[ed25519::PublicKey]| -> Result<bool> {
+        Ok(clients.is_empty()
+            || prompt.select(
+                "Node config has some clients specified. What to do?",
+                &["append", "replace"],
+                0,
+                Some(0),
+                "node.control_clients",
+            )? == 0)
+    };
+
     // Check current configs state
     match (&mut app_config.control, node_config.get_control_server()?) {
         // App and node configs were already touched
@@ -219,31 +279,33 @@ fn setup_control_server(
             let mut server_changed = false;
             let mut client_changed = false;
 
-            // Ensure that control port is the same
+            // Ensure that control port is the same (or use the explicitly specified one)
             let server_port = existing_server.address.port();
             let client_port = existing_client.server_address.port();
-            if existing_client.server_address.port() != existing_server.address.port() {
-                let port = match Select::with_theme(theme)
-                    .with_prompt("App config has different control port. What to do?")
-                    .item(format!(
-                        "use control port from the node {}",
-                        note(server_port)
-                    ))
-                    .item(format!(
-                        "use control port from this app {}",
-                        note(client_port)
-                    ))
-                    .item("specify custom port")
-                    .default(0)
-                    .interact()?
-                {
+            if client_port != server_port
+                || matches!(answers.control_port, Some(port) if port != server_port)
+            {
+                let items = [
+                    format!("use control port from the node {}", note(server_port)),
+                    format!("use control port from this app {}", note(client_port)),
+                    "specify custom port".to_owned(),
+                ];
+                // Port from the node config is used if not specified explicitly
+                let port = match prompt.select(
+                    "App config has different control port. What to do?",
+                    &items,
+                    0,
+                    Some(answers.control_port.map_or(0, |_| 2)),
+                    "node.control_port",
+                )? {
                     // Use port from the node config
                     0 => server_port,
                     // Use port from the app config
                     1 => client_port,
-                    _ => Input::with_theme(theme)
-                        .with_prompt("Specify control port")
-                        .interact_text()?,
+                    _ => prompt
+                        .input("Specify control port", "node.control_port")
+                        .answer(answers.control_port)
+                        .interact()?,
                 };
 
                 client_changed |= port != client_port;
@@ -257,7 +319,12 @@ fn setup_control_server(
             // Ensure that control public key is the same
             let server_pubkey = ed25519::PublicKey::from(&existing_server.server_key);
             if server_pubkey != existing_client.server_pubkey {
-                if !confirm(theme, true, "Server pubkey mismatch. Update?")? {
+                if !prompt.confirm_overwrite(
+                    "Server pubkey mismatch. Update?",
+                    true,
+                    overwrite,
+                    "overwrite",
+                )? {
                     return Ok(false);
                 }
 
@@ -270,16 +337,7 @@ fn setup_control_server(
             if let Some(clients) = &mut existing_server.clients {
                 let client_pubkey = ed25519::PublicKey::from(&existing_client.client_secret);
                 if !clients.contains(&client_pubkey) {
-                    let append = clients.is_empty()
-                        || Select::with_theme(theme)
-                            .with_prompt("Node config has some clients specified. What to do?")
-                            .item("append")
-                            .item("replace")
-                            .default(0)
-                            .interact()?
-                            == 0;
-
-                    if !append {
+                    if !append_client(clients)? {
                         clients.clear();
                     }
 
@@ -300,10 +358,10 @@ fn setup_control_server(
         }
         // Only node config entry exists
         (None, Some(mut existing_server)) => {
-            if !confirm(
-                theme,
-                true,
+            if !prompt.confirm(
                 "App config doesn't have control server entry. Create?",
+                true,
+                true,
             )? {
                 return Ok(false);
             }
@@ -314,24 +372,15 @@ fn setup_control_server(
             // Update node config clients entry
             let node_config_changed = match &mut existing_server.clients {
                 // Explicitly ask about allowing any client to connect
-                None if !confirm(theme, false, "Allow any clients?")? => {
+                None if !prompt.confirm("Allow any clients?", false, false)? => {
                     existing_server.clients = Some(vec![ed25519::PublicKey::from(&client_key)]);
                     println!("Generated new client keys");
                     true
                 }
                 None => false,
                 Some(clients) => {
-                    let append = clients.is_empty()
-                        || Select::with_theme(theme)
-                            .with_prompt("Node config has some clients specified. What to do?")
-                            .item("append")
-                            .item("replace")
-                            .default(0)
-                            .interact()?
-                            == 0;
-
                     // Add or replace clients config
-                    if !append {
+                    if !append_client(clients)? {
                         clients.clear();
                     }
 
@@ -357,16 +406,21 @@ fn setup_control_server(
         }
         // Server config entry doesn't exist
         (existing_client, None) => {
-            if !confirm(
-                theme,
-                true,
+            if !prompt.confirm(
                 "Node config doesn't have control server entry. Create?",
+                true,
+                true,
             )? {
                 return Ok(false);
             }
 
             if existing_client.is_some()
-                && !confirm(theme, false, "Overwrite app control server config?")?
+                && !prompt.confirm_overwrite(
+                    "Overwrite app control server config?",
+                    false,
+                    overwrite,
+                    "overwrite",
+                )?
             {
                 return Ok(false);
             }
@@ -378,18 +432,23 @@ fn setup_control_server(
             ];
 
             // Select listen address
-            let listen_addr = Select::with_theme(theme)
-                .with_prompt("Control server listen address")
-                .item(LISTEN_ADDR_ITEMS[0].0)
-                .item(LISTEN_ADDR_ITEMS[1].0)
-                .default(0)
-                .interact()?;
+            let listen_addr = prompt.select(
+                "Control server listen address",
+                &LISTEN_ADDR_ITEMS.map(|(name, _)| name),
+                0,
+                Some(match answers.control_listen {
+                    ListenAddrAnswer::Localhost => 0,
+                    ListenAddrAnswer::Any => 1,
+                }),
+                "node.control_listen",
+            )?;
             let listen_addr = LISTEN_ADDR_ITEMS[listen_addr].1;
 
             // Select control port
-            let control_port = Input::with_theme(theme)
-                .with_prompt("Specify control port")
-                .with_initial_text(control_port.to_string())
+            let control_port = prompt
+                .input("Specify control port", "node.control_port")
+                .initial(control_port)
+                .answer(answers.control_port)
                 .interact()?;
 
             let addr = SocketAddrV4::new(listen_addr, control_port);
@@ -420,15 +479,14 @@ fn setup_control_server(
 }
 
 async fn setup_adnl(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &NodeAnswers,
+    overwrite: bool,
     dirs: &ProjectDirs,
     app_config: &mut AppConfig,
     node_config: &mut NodeConfig,
     global_config: &GlobalConfig,
 ) -> Result<bool> {
-    const DHT_TAG: usize = 1;
-    const OVERLAY_TAG: usize = 2;
-
     // Compute default adnl port
     let adnl_port = node_config
         .get_suggested_adnl_port()
@@ -440,87 +498,99 @@ async fn setup_adnl(
     // Get zerostate file hash from global config
     let zerostate_file_hash = *global_config.zero_state.file_hash.as_array();
 
-    // Check current configs state
-    match (&mut app_config.adnl, node_config.get_adnl_node()?) {
-        // App and node configs were already touched
-        (Some(adnl_client), Some(mut adnl_node)) => {
-            if let Some(public_ip) = public_ip {
-                // Update node ip address if it differs from the public ip
-                if adnl_node.ip_address.ip() != &public_ip
-                    && confirm(
-                        theme,
-                        false,
+    // Check current node config state
+    let (adnl_node, node_created) = match node_config.get_adnl_node()? {
+        Some(mut adnl_node) => {
+            let mut ip_address = adnl_node.ip_address;
+
+            // Update node ip address if it differs from the public ip
+            if let Some(public_ip) = answers.public_ip.or(public_ip) {
+                if ip_address.ip() != &public_ip
+                    && prompt.confirm(
                         "Your public IP is different from the configured one. Update?",
+                        false,
+                        answers.public_ip.is_some(),
                     )?
                 {
-                    adnl_node.ip_address.set_ip(public_ip);
-                    node_config.set_adnl_node(&adnl_node)?;
-                    dirs.store_node_config(node_config)?;
+                    ip_address.set_ip(public_ip);
                 }
             }
 
-            // Update client config if it differes from the node config
-            let server_pubkey = adnl_node.overlay_pubkey()?;
-            if adnl_client.server_address != adnl_node.ip_address
-                || adnl_client.server_pubkey != server_pubkey
-                || adnl_client.zerostate_file_hash != zerostate_file_hash
-            {
-                if !confirm(theme, false, "ADNL node configuration mismatch. Update?")? {
-                    return Ok(false);
-                }
-
-                adnl_client.server_address = adnl_node.ip_address;
-                adnl_client.server_pubkey = server_pubkey;
-                adnl_client.zerostate_file_hash = zerostate_file_hash;
-
-                dirs.store_app_config(app_config)?;
+            // Update node port if it was explicitly specified
+            if let Some(adnl_port) = answers.adnl_port {
+                ip_address.set_port(adnl_port);
             }
-        }
-        // Only node config entry exists
-        (None, Some(adnl_node)) => {
-            // Create client config
-            app_config.adnl = Some(AppConfigAdnl {
-                client_port: DEFAULT_LOCAL_ADNL_PORT,
-                server_address: adnl_node.ip_address,
-                server_pubkey: adnl_node.overlay_pubkey()?,
-                zerostate_file_hash,
-            });
 
-            app_config.store(&dirs.app_config)?;
+            if ip_address != adnl_node.ip_address {
+                adnl_node.ip_address = ip_address;
+                node_config.set_adnl_node(&adnl_node)?;
+                dirs.store_node_config(node_config)?;
+            }
+            (adnl_node, false)
         }
-        // Node config entry doesn't exist
-        (_, None) => {
-            // Ask for the public ip
-            let addr: Ipv4Addr = {
-                let mut input = Input::with_theme(theme);
-                if let Some(public_ip) = public_ip {
-                    input.with_initial_text(public_ip.to_string());
-                }
-                input.with_prompt("Enter public ip").interact_text()?
-            };
+        None => {
+            // Ask for the public ip (resolved automatically if not specified)
+            let mut input = prompt
+                .input::<Ipv4Addr, _>("Enter public ip", "node.public_ip")
+                .answer(answers.public_ip.or(public_ip));
+            if let Some(public_ip) = public_ip {
+                input = input.initial(public_ip);
+            }
+            let addr = input.interact()?;
 
             // Ask for the adnl port
-            let adnl_port = Input::with_theme(theme)
-                .with_prompt("Specify server ADNL port")
-                .with_initial_text(adnl_port.to_string())
+            let adnl_port = prompt
+                .input("Specify server ADNL port", "node.adnl_port")
+                .initial(adnl_port)
+                .answer(answers.adnl_port)
                 .interact()?;
 
-            // Update and save configs
+            // Update and save node config
             let adnl_node = NodeConfigAdnl::from_addr_and_keys(
                 SocketAddrV4::new(addr, adnl_port),
                 NodeConfigAdnl::generate_keys(),
             );
             node_config.set_adnl_node(&adnl_node)?;
+            dirs.store_node_config(node_config)?;
+            (adnl_node, true)
+        }
+    };
+
+    // Update client config if it differes from the node config
+    let server_pubkey = adnl_node.overlay_pubkey()?;
+    match &mut app_config.adnl {
+        Some(adnl_client)
+            if adnl_client.server_address == adnl_node.ip_address
+                && adnl_client.server_pubkey == server_pubkey
+                && adnl_client.zerostate_file_hash == zerostate_file_hash => {}
+        Some(adnl_client) => {
+            if !node_created
+                && !prompt.confirm_overwrite(
+                    "ADNL node configuration mismatch. Update?",
+                    false,
+                    overwrite,
+                    "overwrite",
+                )?
+            {
+                return Ok(false);
+            }
 
+            adnl_client.server_address = adnl_node.ip_address;
+            adnl_client.server_pubkey = server_pubkey;
+            adnl_client.zerostate_file_hash = zerostate_file_hash;
+
+            dirs.store_app_config(app_config)?;
+        }
+        None => {
+            // Create client config
             app_config.adnl = Some(AppConfigAdnl {
                 client_port: DEFAULT_LOCAL_ADNL_PORT,
                 server_address: adnl_node.ip_address,
-                server_pubkey: adnl_node.overlay_pubkey()?,
+                server_pubkey,
                 zerostate_file_hash,
             });
 
             dirs.store_app_config(app_config)?;
-            dirs.store_node_config(node_config)?;
         }
     }
 
@@ -528,30 +598,40 @@ async fn setup_adnl(
 }
 
 fn setup_node_config_paths(
-    theme: &dyn Theme,
+    prompt: Prompt,
+    answers: &NodeAnswers,
     dirs: &ProjectDirs,
     node_config: &mut NodeConfig,
 ) -> Result<()> {
-    const DB_PATH_FALLBACK: &str = "node_db";
-
     // Update global config path
     node_config.set_global_config_path(&dirs.global_config)?;
 
     // Check if internal db path was already configured
     if let Some(db_path) = node_config.get_internal_db_path()? {
-        if db_path != PathBuf::from(DB_PATH_FALLBACK) {
+        if db_path != PathBuf::from(DB_PATH_FALLBACK) && answers.db_path.is_none() {
             dirs.store_node_config(node_config)?;
             return Ok(());
         }
     }
 
+    let answer = answers
+        .db_path
+        .as_ref()
+        .map(|path| {
+            path.to_str()
+                .map(str::to_owned)
+                .context("invalid node DB path")
+        })
+        .transpose()?;
+
     // Ask for the internal db path
     let completion = &PathCompletion;
-    let path: String = Input::with_theme(theme)
-        .with_prompt("Specify node DB path")
+    let path: String = prompt
+        .input("Specify node DB path", "node.db_path")
         .default(DEFAULT_NODE_DB_PATH.to_owned())
-        .completion_with(completion)
-        .validate_with(|input: &String| {
+        .answer(answer)
+        .completion(completion)
+        .validate(|input: &String| {
             let path = PathBuf::from(input);
             if path.is_absolute() {
                 Ok(())
@@ -559,14 +639,19 @@ fn setup_node_config_paths(
                 Err("Node DB path must be an absolute")
             }
         })
-        .interact_text()?;
+        .interact()?;
 
     // Update and save node config
     node_config.set_internal_db_path(&path)?;
     dirs.store_node_config(node_config)
 }
 
-async fn setup_binary(theme: &dyn Theme, dirs: &ProjectDirs, force: bool) -> Result<bool> {
+async fn setup_binary(
+    prompt: Prompt,
+    answers: &NodeAnswers,
+    dirs: &ProjectDirs,
+    force: bool,
+) -> Result<bool> {
     if !force && dirs.node_binary.exists() {
         // Do nothing if binary exists
         // TODO: print version and ask for update?
@@ -576,11 +661,20 @@ async fn setup_binary(theme: &dyn Theme, dirs: &ProjectDirs, force: bool) -> Res
     // Ensure that binaries directory exists
     dirs.prepare_binaries_dir()?;
 
-    // Ask for the node repo
-    let repo: Url = Input::with_theme(theme)
-        .with_prompt("Node repo URL")
-        .with_initial_text(DEFAULT_NODE_REPO)
-        .interact_text()?;
+    let answer = answers
+        .node_repo
+        .as_deref()
+        .map(Url::parse)
+        .transpose()
+        .map_err(|e| AnswersError::InvalidAnswer("node.node_repo", e.to_string()))?;
+
+    // Ask for the node repo (the default one is used if not specified)
+    let default_repo = Url::parse(DEFAULT_NODE_REPO).unwrap();
+    let repo: Url = prompt
+        .input("Node repo URL", "node.node_repo")
+        .initial(default_repo.clone())
+        .answer(Some(answer.unwrap_or(default_repo)))
+        .interact()?;
 
     dirs.install_node_from_repo(&repo).await?;
     Ok(true)
@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::Result;
+use dialoguer::theme::Theme;
+use dialoguer::{Completion, Input, Select};
+
+use super::answers::{Answers, AnswersError};
+
+/// Source of the values for the init flows.
+///
+/// The same flow is used for both modes: the interactive mode asks the user,
+/// the non-interactive mode takes values from the answers file (or uses defaults)
+#[derive(Clone, Copy)]
+pub enum Prompt<'a> {
+    Interactive(&'a dyn Theme),
+    Answers,
+}
+
+impl<'a> Prompt<'a> {
+    /// Loads the answers file if specified, falling back to the interactive mode otherwise
+    pub fn with_answers(theme: &'a dyn Theme, answers: Option<PathBuf>) -> Result<(Self, Answers)> {
+        Ok(match answers {
+            Some(answers) => (Self::Answers, Answers::load(answers)?),
+            None => (Self::Interactive(theme), Answers::default()),
+        })
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Self::Interactive(_))
+    }
+
+    /// Ensures that the value is specified in the non-interactive mode
+    pub fn required<T>(self, value: Option<T>, name: &str) -> Result<Option<T>> {
+        match (self, value) {
+            (Self::Answers, None) => Err(AnswersError::MissingAnswer(name.to_owned()).into()),
+            (_, value) => Ok(value),
+        }
+    }
+
+    /// Asks for the confirmation, uses `answer` in the non-interactive mode
+    pub fn confirm<T>(self, text: T, default: bool, answer: bool) -> Result<bool>
+    where
+        T: Into<String>,
+    {
+        match self {
+            Self::Interactive(theme) => Ok(crate::util::confirm(theme, default, text)?),
+            Self::Answers => Ok(answer),
+        }
+    }
+
+    /// Asks whether to overwrite the existing config.
+    ///
+    /// Fails in the non-interactive mode unless the overwrite is explicitly allowed
+    pub fn confirm_overwrite<T>(
+        self,
+        text: T,
+        default: bool,
+        allowed: bool,
+        name: &'static str,
+    ) -> Result<bool>
+    where
+        T: Into<String>,
+    {
+        match self {
+            Self::Interactive(theme) => Ok(crate::util::confirm(theme, default, text)?),
+            Self::Answers if allowed => Ok(true),
+            Self::Answers => Err(AnswersError::OverwriteNotAllowed(text.into(), name).into()),
+        }
+    }
+
+    /// Asks to select an item, requires `answer` in the non-interactive mode
+    pub fn select<T: ToString>(
+        self,
+        text: &str,
+        items: &[T],
+        default: usize,
+        answer: Option<usize>,
+        name: &str,
+    ) -> Result<usize> {
+        match self {
+            Self::Interactive(theme) => Ok(Select::with_theme(theme)
+                .with_prompt(text)
+                .items(items)
+                .default(default)
+                .interact()?),
+            Self::Answers => {
+                answer.ok_or_else(|| AnswersError::MissingAnswer(name.to_owned()).into())
+            }
+        }
+    }
+
+    /// Creates a text input for the answer with the specified name
+    pub fn input<T, S>(self, text: S, name: &'static str) -> PromptInput<'a, T>
+    where
+        S: Into<String>,
+    {
+        PromptInput {
+            prompt: self,
+            text: text.into(),
+            name,
+            initial: None,
+            default: None,
+            answer: None,
+            completion: None,
+            validator: None,
+        }
+    }
+}
+
+pub struct PromptInput<'a, T> {
+    prompt: Prompt<'a>,
+    text: String,
+    name: &'static str,
+    initial: Option<T>,
+    default: Option<T>,
+    answer: Option<T>,
+    completion: Option<&'a dyn Completion>,
+    validator: Option<Box<dyn Fn(&T) -> Result<(), String> + 'a>>,
+}
+
+impl<'a, T> PromptInput<'a, T>
+where
+    T: Clone + ToString + FromStr + 'a,
+    <T as FromStr>::Err: std::fmt::Debug + ToString,
+{
+    /// Prefills the input with the value
+    pub fn initial(mut self, value: T) -> Self {
+        self.initial = Some(value);
+        self
+    }
+
+    /// Sets the value for the empty input
+    pub fn default(mut self, value: T) -> Self {
+        self.default = Some(value);
+        self
+    }
+
+    /// Sets the value for the non-interactive mode (required there)
+    pub fn answer(mut self, value: Option<T>) -> Self {
+        self.answer = value;
+        self
+    }
+
+    pub fn completion(mut self, completion: &'a dyn Completion) -> Self {
+        self.completion = Some(completion);
+        self
+    }
+
+    pub fn validate<F, E>(mut self, validator: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), E> + 'a,
+        E: ToString,
+    {
+        self.validator = Some(Box::new(move |value: &T| {
+            validator(value).map_err(|e| e.to_string())
+        }));
+        self
+    }
+
+    pub fn interact(self) -> Result<T> {
+        match self.prompt {
+            Prompt::Interactive(theme) => {
+                let mut input = Input::<T>::with_theme(theme);
+                input.with_prompt(self.text);
+                if let Some(initial) = self.initial {
+                    input.with_initial_text(initial.to_string());
+                }
+                if let Some(default) = self.default {
+                    input.default(default);
+                }
+                if let Some(completion) = self.completion {
+                    input.completion_with(completion);
+                }
+                if let Some(validator) = self.validator {
+                    input.validate_with(move |value: &T| validator(value));
+                }
+                Ok(input.interact_text()?)
+            }
+            Prompt::Answers => {
+                let value = self
+                    .answer
+                    .ok_or_else(|| AnswersError::MissingAnswer(self.name.to_owned()))?;
+                if let Some(validator) = &self.validator {
+                    validator(&value).map_err(|e| AnswersError::InvalidAnswer(self.name, e))?;
+                }
+                Ok(value)
+            }
+        }
+    }
+}
@@ -1,30 +1,38 @@
 use std::borrow::Cow;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 
 use anyhow::{Context, Result};
 use argh::FromArgs;
 use console::style;
 use dialoguer::theme::Theme;
-use dialoguer::Select;
 use tokio::process::Command;
 
+use super::answers::SystemdAnswers;
+use super::prompt::Prompt;
 use crate::cli::{CliContext, ProjectDirs, VALIDATOR_MANAGER_SERVICE, VALIDATOR_SERVICE};
 use crate::util::*;
 
 #[derive(FromArgs)]
 /// Creates systemd services
 #[argh(subcommand, name = "systemd")]
-pub struct Cmd {}
+pub struct Cmd {
+    /// path to the answers file for the non-interactive mode
+    #[argh(option)]
+    answers: Option<PathBuf>,
+}
 
 impl Cmd {
-    pub async fn run(self, theme: &dyn Theme, ctx: &CliContext) -> Result<()> {
+    pub async fn run(mut self, theme: &dyn Theme, ctx: &CliContext) -> Result<()> {
+        let (prompt, answers) = Prompt::with_answers(theme, self.answers.take())?;
+        let answers = answers.systemd();
+
         let dirs = ctx.dirs();
         let mut steps = Steps::new(2);
 
         // Ensure all services are created
         steps.next("Preparing services");
-        prepare_services(theme, dirs)?;
+        prepare_services(prompt, &answers, dirs)?;
 
         // Reload sysetmd
         steps.next("Reloading systemd configs");
@@ -32,14 +40,23 @@ impl Cmd {
 
         // Optionally start services
         steps.next("Systemd services are configured now. Great!");
-        start_services(theme).await?;
+        start_services(prompt, &answers).await?;
 
         Ok(())
     }
 }
 
-pub fn prepare_services(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<()> {
-    const ROOT_USER: &str = "root";
+const ROOT_USER: &str = "root";
+
+pub fn prepare_services(
+    prompt: Prompt,
+    answers: &SystemdAnswers,
+    dirs: &ProjectDirs,
+) -> Result<()> {
+    // Use explicitly specified user
+    if let Some(user) = &answers.user {
+        return create_services(dirs, user);
+    }
 
     // Determine current user id
     let uid = system::user_id();
@@ -60,13 +77,14 @@ pub fn prepare_services(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<()> {
         // If there is an option of running services under non-root user,
         // ask user about it
         let other_user = system::user_name(uid).context("failed to get user name")?;
-        match Select::with_theme(theme)
-            .with_prompt("Select the user from which the service will work")
-            .item(&other_user)
-            .item("root")
-            .default(0)
-            .interact()?
-        {
+        let selected = prompt.select(
+            "Select the user from which the service will work",
+            &[other_user.as_str(), ROOT_USER],
+            0,
+            None,
+            "systemd.user",
+        )?;
+        match selected {
             // Running as non-root user
             0 => Cow::Owned(other_user),
             // Running as root
@@ -79,6 +97,10 @@ pub fn prepare_services(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<()> {
             .unwrap_or(Cow::Borrowed(ROOT_USER))
     };
 
+    create_services(dirs, &user)
+}
+
+fn create_services(dirs: &ProjectDirs, user: &str) -> Result<()> {
     let print_service = |path: &Path| {
         println!(
             "{}",
@@ -87,25 +109,28 @@ pub fn prepare_services(theme: &dyn Theme, dirs: &ProjectDirs) -> Result<()> {
     };
 
     // Create validator node service
-    dirs.create_systemd_validator_service(&user)?;
+    dirs.create_systemd_validator_service(user)?;
     print_service(&dirs.validator_service);
 
     // Create validator manager service
-    dirs.create_systemd_validator_manager_service(&user)?;
+    dirs.create_systemd_validator_manager_service(user)?;
     print_service(&dirs.validator_manager_service);
 
     Ok(())
 }
 
-pub async fn start_services(theme: &dyn Theme) -> Result<()> {
+pub async fn start_services(prompt: Prompt, answers: &SystemdAnswers) -> Result<()> {
+    let enable = prompt.confirm(
+        "Enable autostart services at system startup?",
+        true,
+        answers.enable,
+    )?;
+    let restart = prompt.confirm("Restart systemd services?", true, answers.restart)?;
+
     let services = [VALIDATOR_SERVICE, VALIDATOR_MANAGER_SERVICE];
-    systemd_set_sercices_enabled(
-        services,
-        confirm(theme, true, "Enable autostart services at system startup?")?,
-    )
-    .await?;
+    systemd_set_sercices_enabled(services, enable).await?;
 
-    if confirm(theme, true, "Restart systemd services?")? {
+    if restart {
         for service in services {
             systemd_restart_service(service).await?;
         }
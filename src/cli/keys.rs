@@ -0,0 +1,650 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use broxus_util::now;
+use dialoguer::theme::Theme;
+
+use super::{CliContext, ProjectDirs};
+use crate::config::*;
+use crate::contracts::signer::ExternalSignerEndpoint;
+use crate::contracts::*;
+use crate::crypto::{self, MnemonicType};
+use crate::network::{NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// Keys management
+#[argh(subcommand, name = "keys")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        match self.subcommand {
+            SubCmd::List(cmd) => cmd.run(ctx),
+            SubCmd::Show(cmd) => cmd.run(ctx),
+            SubCmd::Import(cmd) => cmd.run(ctx).await,
+            SubCmd::Export(cmd) => cmd.run(ctx),
+            SubCmd::Rotate(cmd) => cmd.run(ctx).await,
+            SubCmd::Encrypt(cmd) => cmd.run(ctx),
+            SubCmd::Decrypt(cmd) => cmd.run(ctx),
+        }
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    List(CmdList),
+    Show(CmdShow),
+    Import(CmdImport),
+    Export(CmdExport),
+    Rotate(CmdRotate),
+    Encrypt(CmdEncrypt),
+    Decrypt(CmdDecrypt),
+}
+
+#[derive(FromArgs)]
+/// Lists stored keys with derived addresses
+#[argh(subcommand, name = "list")]
+struct CmdList {}
+
+impl CmdList {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let dirs = ctx.dirs();
+
+        let mut items = Vec::new();
+        for name in KeysName::all() {
+            let path = dirs.keys_path(name);
+            if path.exists() {
+                items.push(describe_keys(name, path)?);
+            }
+        }
+
+        print_output(serde_json::Value::Array(items));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows stored keys info
+#[argh(subcommand, name = "show")]
+struct CmdShow {
+    /// keys name (`validator` or `depool`)
+    #[argh(positional)]
+    keys: KeysName,
+}
+
+impl CmdShow {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let path = ctx.dirs().resolve_keys_path(self.keys)?;
+        print_output(describe_keys(self.keys, path)?);
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Imports keys from the seed phrase or secret key
+#[argh(subcommand, name = "import")]
+struct CmdImport {
+    /// keys name (`validator` or `depool`)
+    #[argh(positional)]
+    keys: KeysName,
+
+    /// seed phrase, secret key in hex/base64 or empty for input from stdin.
+    /// Public key when `--external-signer` is specified, key hash (or empty
+    /// to generate a new key) when `--node-keystore` is specified
+    #[argh(positional)]
+    input: Option<String>,
+
+    /// mnemonic type
+    #[argh(option, long = "type", short = 't', default = "MnemonicType::Bip39")]
+    ty: MnemonicType,
+
+    /// derivation path for bip39 mnemonic
+    #[argh(option, short = 'p')]
+    path: Option<String>,
+
+    /// account index for bip39 mnemonic (`m/44'/396'/0'/0/<account>`)
+    #[argh(option, short = 'a')]
+    account: Option<u32>,
+
+    /// ask for the bip39 passphrase (or read it from `STEVER_SEED_PASSPHRASE`)
+    #[argh(switch)]
+    passphrase: bool,
+
+    /// external signer endpoint (`unix:<path>` or `exec:<command>`)
+    #[argh(option)]
+    external_signer: Option<ExternalSignerEndpoint>,
+
+    /// use the key from the node keystore
+    #[argh(switch)]
+    node_keystore: bool,
+
+    /// overwrite existing keys
+    #[argh(switch)]
+    overwrite: bool,
+}
+
+impl CmdImport {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let path = ctx.dirs().keys_path(self.keys);
+
+        anyhow::ensure!(
+            !(self.node_keystore && self.external_signer.is_some()),
+            "`--node-keystore` and `--external-signer` are exclusive"
+        );
+
+        // Preserve encryption of the existing keys
+        let mut encrypted = false;
+        if path.exists() {
+            anyhow::ensure!(
+                self.overwrite,
+                "keys file already exists at {}, use `--overwrite` to replace it",
+                path.display()
+            );
+            encrypted = StoredKeys::is_encrypted(path)?;
+        }
+
+        if self.node_keystore {
+            let node_tcp_rpc = NodeTcpRpc::new(ctx.load_config()?.control()?).await?;
+            let key_hash = match &self.input {
+                Some(key_hash) => parse_key_hash(key_hash)?,
+                None => node_tcp_rpc.generate_key_pair().await?,
+            };
+
+            let signer = Signer::node_keystore(node_tcp_rpc, key_hash).await?;
+            NodeKeystoreKeys {
+                key_hash,
+                public: signer.public_key().to_bytes(),
+            }
+            .store(path)?;
+
+            print_output(describe_keys(self.keys, path)?);
+            return Ok(());
+        }
+
+        let input = parse_optional_input(self.input, true)?;
+        let input = String::from_utf8(input).context("invalid input")?;
+        let input = input.trim();
+
+        if let Some(endpoint) = self.external_signer {
+            let public = parse_optional_pubkey(Some(input.to_owned()))?
+                .context("public key not specified")?;
+            ExternalSignerKeys {
+                public: public.to_bytes(),
+                external_signer: endpoint.to_string(),
+            }
+            .store(path)?;
+
+            print_output(describe_keys(self.keys, path)?);
+            return Ok(());
+        }
+
+        let keys = if input.contains(char::is_whitespace) {
+            let path = crypto::derivation_path(self.path, self.account)?;
+            let passphrase = match self.passphrase {
+                true => seed_passphrase()?,
+                false => String::new(),
+            };
+            StoredKeys::from_seed_with_path(input, self.ty, &path, &passphrase)?
+        } else {
+            let secret = parse_hex_or_base64(input).context("invalid secret key")?;
+            StoredKeys::from_secret(secret)?
+        };
+
+        if encrypted {
+            keys.store_encrypted(path, keys_passphrase()?)?;
+        } else {
+            keys.store(path)?;
+        }
+
+        print_output(describe_keys(self.keys, path)?);
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Exports stored keys
+#[argh(subcommand, name = "export")]
+struct CmdExport {
+    /// keys name (`validator` or `depool`)
+    #[argh(positional)]
+    keys: KeysName,
+
+    /// export secret key
+    #[argh(switch)]
+    secret: bool,
+
+    /// export seed phrase (if any)
+    #[argh(switch)]
+    seed: bool,
+}
+
+impl CmdExport {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let path = ctx.dirs().resolve_keys_path(self.keys)?;
+
+        let mut result = serde_json::Map::new();
+        if !self.secret && !self.seed {
+            let public = StoredKeys::load_public(path)?;
+            result.insert("public".to_owned(), hex::encode(public.as_bytes()).into());
+        } else {
+            let keys = StoredKeys::load(path)?;
+            let keypair = keys.as_keypair();
+            result.insert(
+                "public".to_owned(),
+                hex::encode(keypair.public.as_bytes()).into(),
+            );
+            if self.secret {
+                result.insert("secret".to_owned(), hex::encode(keys.secret).into());
+            }
+            if self.seed {
+                result.insert("seed".to_owned(), keys.seed.into());
+                if let Some(path) = keys.path {
+                    result.insert("path".to_owned(), path.into());
+                }
+                if keys.passphrase {
+                    print_error("Seed alone is not enough, keys were derived with a passphrase");
+                    result.insert("passphrase".to_owned(), true.into());
+                }
+            }
+        }
+
+        print_output(serde_json::Value::Object(result));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Generates new validator wallet keys and moves funds to the new wallet
+#[argh(subcommand, name = "rotate")]
+struct CmdRotate {
+    /// generate new keys in the node keystore instead of the keys file
+    #[argh(switch)]
+    node_keystore: bool,
+}
+
+impl CmdRotate {
+    async fn run(self, ctx: CliContext) -> Result<()> {
+        let theme = &dialoguer::theme::ColorfulTheme::default();
+        let dirs = ctx.dirs();
+
+        let mut config = ctx.load_config()?;
+        let configured_address = match &config.validator {
+            Some(AppConfigValidator::Single(single)) => single.address.clone(),
+            Some(AppConfigValidator::DePool(_)) => {
+                return Err(KeysError::DePoolOwnerRotation.into())
+            }
+            None => return Err(KeysError::ValidatorNotConfigured.into()),
+        };
+
+        let mut steps = Steps::new(4);
+
+        // Check old wallet
+        steps.next("Checking current validator wallet");
+
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+
+        let old_signer = Signer::load(&dirs.validator_keys, &node_tcp_rpc)?;
+        let old_wallet_address = wallet::compute_wallet_address(-1, old_signer.public_key());
+        anyhow::ensure!(
+            old_wallet_address == configured_address,
+            "validator keys don't match the validator wallet address from the config"
+        );
+
+        let subscription = Subscription::new(node_tcp_rpc.clone(), node_udp_rpc);
+        subscription.ensure_ready().await?;
+
+        if !check_elector_stake(theme, &subscription, &old_wallet_address).await? {
+            return Ok(());
+        }
+
+        // Generate new keys
+        steps.next("Generating new validator wallet keys");
+
+        let new_keys = if self.node_keystore {
+            let key_hash = node_tcp_rpc.generate_key_pair().await?;
+            let signer = Signer::node_keystore(node_tcp_rpc, key_hash).await?;
+            NewKeys::NodeKeystore(NodeKeystoreKeys {
+                key_hash,
+                public: signer.public_key().to_bytes(),
+            })
+        } else {
+            NewKeys::Stored(StoredKeys::generate()?)
+        };
+        let new_wallet_address = wallet::compute_wallet_address(-1, &new_keys.public_key()?);
+
+        println!(
+            "{}\n{}\n{}\n{}\n",
+            console::style("Current validator wallet address:")
+                .green()
+                .bold(),
+            console::style(&old_wallet_address).bold(),
+            console::style("New validator wallet address:")
+                .green()
+                .bold(),
+            console::style(&new_wallet_address).bold(),
+        );
+
+        println!(
+            "{}\n",
+            note(format!(
+                "make sure the `{}` service is stopped during the rotation",
+                super::VALIDATOR_MANAGER_SERVICE
+            ))
+        );
+
+        if !confirm(theme, false, "Move all funds to the new wallet?")? {
+            return Ok(());
+        }
+
+        // Keep new keys next to the current ones until the funds are moved
+        let new_keys_path = keys_path_with_suffix(&dirs.validator_keys, "new");
+        match &new_keys {
+            NewKeys::Stored(keys) if StoredKeys::is_encrypted(&dirs.validator_keys)? => {
+                keys.store_encrypted(&new_keys_path, keys_passphrase()?)?;
+            }
+            NewKeys::Stored(keys) => keys.store(&new_keys_path)?,
+            NewKeys::NodeKeystore(keys) => keys.store(&new_keys_path)?,
+        }
+
+        // Move funds
+        steps.next("Moving funds to the new wallet");
+
+        let old_wallet = Wallet::new(-1, old_signer, subscription.clone());
+        match old_wallet.get_balance().await? {
+            Some(balance) if balance > 0 => {
+                old_wallet
+                    .call_transfer_all(new_wallet_address.clone())
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "failed to move funds, new keys were saved to {}",
+                            new_keys_path.display()
+                        )
+                    })?;
+
+                let new_balance = subscription
+                    .get_account_state(&new_wallet_address)
+                    .await?
+                    .map(|state| state.storage.balance.grams.0)
+                    .unwrap_or_default();
+                anyhow::ensure!(
+                    new_balance > 0,
+                    "new wallet didn't receive funds, new keys were saved to {}",
+                    new_keys_path.display()
+                );
+
+                println!("Moved {} EVER to the new wallet", Ever(new_balance));
+            }
+            _ => println!("Old wallet is empty, nothing to move"),
+        }
+
+        // Replace keys
+        steps.next("Replacing validator wallet keys");
+
+        let backup_path = keys_path_with_suffix(&dirs.validator_keys, "bak");
+        std::fs::copy(&dirs.validator_keys, &backup_path)
+            .context("failed to backup validator keys")?;
+        std::fs::rename(&new_keys_path, &dirs.validator_keys)
+            .context("failed to replace validator keys")?;
+
+        if let Some(AppConfigValidator::Single(single)) = &mut config.validator {
+            single.address = new_wallet_address.clone();
+        }
+        config.store(&dirs.app_config)?;
+
+        println!(
+            "\n{}\n{}\n{}",
+            console::style("Old keys were saved to:").yellow().bold(),
+            console::style(backup_path.display()).bold(),
+            console::style("Make sure you back up the new keys!")
+                .yellow()
+                .bold(),
+        );
+        if self.node_keystore {
+            println!(
+                "{}",
+                note("new secret key is held in the node keystore, back up the node config")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `<path>.<timestamp>.<suffix>`
+fn keys_path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.to_owned().into_os_string();
+    path.push(format!(".{}.{suffix}", now()));
+    PathBuf::from(path)
+}
+
+enum NewKeys {
+    Stored(StoredKeys),
+    NodeKeystore(NodeKeystoreKeys),
+}
+
+impl NewKeys {
+    fn public_key(&self) -> Result<ed25519_dalek::PublicKey> {
+        match self {
+            Self::Stored(keys) => Ok(keys.as_keypair().public),
+            Self::NodeKeystore(keys) => {
+                ed25519_dalek::PublicKey::from_bytes(&keys.public).context("invalid public key")
+            }
+        }
+    }
+}
+
+/// Returns `false` if user decided to wait for the stake to return
+async fn check_elector_stake(
+    theme: &dyn Theme,
+    subscription: &std::sync::Arc<Subscription>,
+    address: &ton_block::MsgAddressInt,
+) -> Result<bool> {
+    let config = subscription.tcp_rpc().get_config_all().await?.config;
+    let elector_address = config
+        .elector_address()
+        .context("invalid elector address")?;
+
+    let elector = Elector::new(elector_address, subscription.clone());
+    let elector_data = elector
+        .get_data()
+        .await
+        .context("failed to get elector data")?;
+
+    let mut warnings = Vec::new();
+    if elector_data.elected(address) {
+        warnings.push("the current wallet participates in the current elections");
+    }
+    if elector_data.has_unfrozen_stake(address).is_some() {
+        warnings.push("the elector has an unclaimed stake for the current wallet");
+    }
+
+    if warnings.is_empty() {
+        return Ok(true);
+    }
+
+    for warning in warnings {
+        print_error(warning);
+    }
+    println!(
+        "{}",
+        note("stakes are always returned to the old wallet, rotate keys after they are recovered")
+    );
+    Ok(confirm(theme, false, "Rotate keys anyway?")?)
+}
+
+#[derive(FromArgs)]
+/// Encrypts keys files with a passphrase
+#[argh(subcommand, name = "encrypt")]
+struct CmdEncrypt {
+    /// keys to encrypt (`validator` or `depool`). All existing keys by default
+    #[argh(positional)]
+    keys: Vec<KeysName>,
+}
+
+impl CmdEncrypt {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let paths = ctx.dirs().resolve_keys_paths(&self.keys)?;
+
+        let mut plain = Vec::with_capacity(paths.len());
+        for path in paths {
+            if StoredKeys::is_encrypted(path)? {
+                println!("{} is already encrypted", path.display());
+            } else if StoredKeys::load_node_keystore(path)?.is_some() {
+                println!("{} is held in the node keystore", path.display());
+            } else if StoredKeys::load_external_signer(path)?.is_some() {
+                println!("{} is held by the external signer", path.display());
+            } else {
+                plain.push((path, StoredKeys::load(path)?));
+            }
+        }
+
+        if !plain.is_empty() {
+            let passphrase = new_keys_passphrase()?;
+            for (path, keys) in plain {
+                keys.store_encrypted(path, &passphrase)?;
+            }
+        }
+
+        print_output(serde_json::json!({}));
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+/// Decrypts keys files and stores them as a plain JSON
+#[argh(subcommand, name = "decrypt")]
+struct CmdDecrypt {
+    /// keys to decrypt (`validator` or `depool`). All existing keys by default
+    #[argh(positional)]
+    keys: Vec<KeysName>,
+}
+
+impl CmdDecrypt {
+    fn run(self, ctx: CliContext) -> Result<()> {
+        let paths = ctx.dirs().resolve_keys_paths(&self.keys)?;
+
+        for path in &paths {
+            if !StoredKeys::is_encrypted(path)? {
+                println!("{} is not encrypted", path.display());
+                continue;
+            }
+
+            let keys = StoredKeys::load(path)?;
+            keys.store(path)?;
+        }
+
+        print_output(serde_json::json!({}));
+        Ok(())
+    }
+}
+
+fn describe_keys(name: KeysName, path: &Path) -> Result<serde_json::Value> {
+    let public = StoredKeys::load_public(path)?;
+
+    let addresses = match name {
+        KeysName::Validator => serde_json::json!({
+            "masterchain_wallet": wallet::compute_wallet_address(-1, &public).to_string(),
+            "basechain_wallet": wallet::compute_wallet_address(0, &public).to_string(),
+        }),
+        KeysName::DePool => serde_json::json!({
+            "depool_v3": DePoolType::DefaultV3.compute_depool_address(&public)?.to_string(),
+            "stever_depool": DePoolType::StEver.compute_depool_address(&public)?.to_string(),
+        }),
+    };
+
+    let node_keystore_key =
+        StoredKeys::load_node_keystore(path)?.map(|keys| hex::encode(keys.key_hash));
+    let external_signer = StoredKeys::load_external_signer(path)?.map(|keys| keys.external_signer);
+
+    Ok(serde_json::json!({
+        "name": name.to_string(),
+        "path": path.display().to_string(),
+        "encrypted": StoredKeys::is_encrypted(path)?,
+        "node_keystore_key": node_keystore_key,
+        "external_signer": external_signer,
+        "public": hex::encode(public.as_bytes()),
+        "addresses": addresses,
+    }))
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum KeysName {
+    Validator,
+    DePool,
+}
+
+impl KeysName {
+    fn all() -> [Self; 2] {
+        [Self::Validator, Self::DePool]
+    }
+}
+
+impl std::fmt::Display for KeysName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Validator => "validator",
+            Self::DePool => "depool",
+        })
+    }
+}
+
+impl FromStr for KeysName {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "validator" => Ok(Self::Validator),
+            "depool" => Ok(Self::DePool),
+            _ => Err(anyhow::anyhow!(
+                "unknown keys (neither `validator` nor `depool`)"
+            )),
+        }
+    }
+}
+
+impl ProjectDirs {
+    fn keys_path(&self, name: KeysName) -> &PathBuf {
+        match name {
+            KeysName::Validator => &self.validator_keys,
+            KeysName::DePool => &self.depool_keys,
+        }
+    }
+
+    fn resolve_keys_path(&self, name: KeysName) -> Result<&PathBuf> {
+        let path = self.keys_path(name);
+        anyhow::ensure!(path.exists(), "{} not found", path.display());
+        Ok(path)
+    }
+
+    fn resolve_keys_paths(&self, names: &[KeysName]) -> Result<Vec<&PathBuf>> {
+        if names.is_empty() {
+            return Ok(KeysName::all()
+                .into_iter()
+                .map(|name| self.keys_path(name))
+                .filter(|path| path.exists())
+                .collect());
+        }
+
+        names
+            .iter()
+            .map(|&name| self.resolve_keys_path(name))
+            .collect()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum KeysError {
+    #[error("validator is not configured")]
+    ValidatorNotConfigured,
+    #[error("DePool validator wallet can't be changed")]
+    DePoolOwnerRotation,
+}
@@ -5,14 +5,20 @@ use argh::FromArgs;
 use home::home_dir;
 
 use crate::config::*;
+use crate::contracts::depool::{DePoolRegistry, RoundsHistory};
 use crate::util::*;
 
+pub mod config;
 pub mod contract;
+pub mod depool;
+pub mod doctor;
 pub mod exporter;
 pub mod init;
+pub mod keys;
 pub mod node;
 pub mod seed;
 pub mod validator;
+pub mod wallet;
 
 /// All-in-one node management tool with support for the upcoming stEVER
 #[derive(FromArgs)]
@@ -33,6 +39,11 @@ impl App {
             dirs: ProjectDirs::new(self.root),
         };
 
+        // Load custom DePool versions
+        if let Err(e) = DePoolRegistry::init(&ctx.dirs.depools_dir) {
+            tracing::warn!("failed to load DePool registry: {e:?}");
+        }
+
         match self.command {
             Command::Init(cmd) => cmd.run(ctx).await,
             Command::Validator(cmd) => cmd.run(ctx).await,
@@ -40,6 +51,11 @@ impl App {
             Command::Exporter(cmd) => cmd.run(ctx).await,
             Command::Node(cmd) => cmd.run(ctx).await,
             Command::Seed(cmd) => cmd.run(),
+            Command::Config(cmd) => cmd.run(ctx),
+            Command::Doctor(cmd) => cmd.run(ctx).await,
+            Command::Keys(cmd) => cmd.run(ctx).await,
+            Command::Wallet(cmd) => cmd.run(ctx).await,
+            Command::DePool(cmd) => cmd.run(ctx).await,
         }
     }
 }
@@ -53,6 +69,11 @@ enum Command {
     Exporter(exporter::Cmd),
     Node(node::Cmd),
     Seed(seed::Cmd),
+    Config(config::Cmd),
+    Doctor(doctor::Cmd),
+    Keys(keys::Cmd),
+    Wallet(wallet::Cmd),
+    DePool(depool::Cmd),
 }
 
 pub struct CliContext {
@@ -84,6 +105,8 @@ pub struct ProjectDirs {
     keys_dir: PathBuf,
     validator_keys: PathBuf,
     depool_keys: PathBuf,
+    depool_rounds_dir: PathBuf,
+    depools_dir: PathBuf,
     root: PathBuf,
     validator_service: PathBuf,
     validator_manager_service: PathBuf,
@@ -119,11 +142,18 @@ impl ProjectDirs {
             keys_dir,
             validator_keys,
             depool_keys,
+            depool_rounds_dir: root.join("depool_rounds"),
+            depools_dir: root.join("depools"),
             root,
             validator_service,
             validator_manager_service,
         }
     }
+
+    /// Returns the rounds history file of the specified DePool
+    fn depool_rounds(&self, depool: &ton_block::MsgAddressInt) -> PathBuf {
+        RoundsHistory::path(&self.depool_rounds_dir, depool)
+    }
 }
 
 fn default_root_dir() -> &'static PathBuf {
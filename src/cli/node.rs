@@ -1,10 +1,16 @@
-use anyhow::Result;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
 use argh::FromArgs;
+use nekoton_abi::FunctionExt;
 use serde::Serialize;
 use ton_block::Serializable;
 
 use super::CliContext;
-use crate::network::{ConfigParamWithId, ConfigWithId, NodeTcpRpc};
+use crate::network::{
+    AccountStateWithId, BlockHeaderWithId, BlockLookup, ConfigParamWithId, ConfigWithId,
+    NodeTcpRpc, NodeUdpRpc, RunMethodOutput, SendMessageStatus, Subscription, TransactionsCursor,
+};
 use crate::util::*;
 
 #[derive(FromArgs)]
@@ -100,17 +106,285 @@ impl Cmd {
                 };
                 serde_json::to_value(Response { block_id, value })?
             }
+            SubCmd::GetMasterchainInfo(_) => {
+                #[derive(Serialize)]
+                struct Response {
+                    #[serde(with = "serde_block_id")]
+                    last: ton_block::BlockIdExt,
+                    mc_time: u32,
+                }
+
+                let stats = rpc_node.get_stats().await?.try_into_running()?;
+                serde_json::to_value(Response {
+                    last: stats.last_mc_block,
+                    mc_time: stats.mc_time,
+                })?
+            }
             SubCmd::GetAccount(cmd) => {
+                #[derive(Serialize)]
+                struct Response {
+                    #[serde(with = "serde_block_id")]
+                    block_id: ton_block::BlockIdExt,
+                    #[serde(with = "serde_block_id")]
+                    shard_block_id: ton_block::BlockIdExt,
+                    state: String,
+                }
+
+                let address = parse_address(&cmd.address)?;
+                match cmd.block {
+                    None => {
+                        let param = rpc_node.get_shard_account_state(&address).await?;
+                        serde_json::json!({
+                            "state": base64::encode(ton_types::serialize_toc(&param.serialize()?)?)
+                        })
+                    }
+                    Some(block_id) => {
+                        let block_id = parse_block_id(&block_id)?;
+                        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                        let subscription = Subscription::new(rpc_node.clone(), node_udp_rpc);
+                        let AccountStateWithId {
+                            block_id,
+                            shard_block_id,
+                            account,
+                        } = subscription
+                            .get_account_state_at(&address, &block_id)
+                            .await?;
+                        serde_json::to_value(Response {
+                            block_id,
+                            shard_block_id,
+                            state: base64::encode(ton_types::serialize_toc(&account.serialize()?)?),
+                        })?
+                    }
+                }
+            }
+            SubCmd::RunMethod(cmd) => {
+                #[derive(Serialize)]
+                struct Response {
+                    #[serde(with = "serde_block_id")]
+                    block_id: ton_block::BlockIdExt,
+                    #[serde(with = "serde_block_id")]
+                    shard_block_id: ton_block::BlockIdExt,
+                    exit_code: i32,
+                    stack: String,
+                }
+
                 let address = parse_address(&cmd.address)?;
-                let param = rpc_node.get_shard_account_state(&address).await?;
+                let block_id = cmd.block.as_deref().map(parse_block_id).transpose()?;
+
+                match cmd.abi {
+                    // Run the ABI getter locally on the fetched account state
+                    Some(abi) => {
+                        anyhow::ensure!(
+                            cmd.params.is_none(),
+                            "`--params` can't be used with `--abi`, use `--args` instead"
+                        );
+
+                        let method = parse_contract_abi(abi)?
+                            .functions
+                            .remove(&cmd.method)
+                            .with_context(|| format!("method `{}` not found", cmd.method))?;
+                        let args = cmd
+                            .args
+                            .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+                        let input = nekoton_abi::parse_abi_tokens(&method.inputs, args)?;
+
+                        let (state_block_id, account) = match block_id {
+                            Some(block_id) => {
+                                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                                let subscription =
+                                    Subscription::new(rpc_node.clone(), node_udp_rpc);
+                                let state = subscription
+                                    .get_account_state_at(&address, &block_id)
+                                    .await?;
+                                (Some(state.block_id), state.account)
+                            }
+                            None => {
+                                let state = rpc_node.get_shard_account_state(&address).await?;
+                                (None, state.read_account()?)
+                            }
+                        };
+                        let account = match account {
+                            ton_block::Account::Account(account) => account,
+                            ton_block::Account::AccountNone => {
+                                anyhow::bail!("account not deployed")
+                            }
+                        };
+
+                        let nekoton_abi::ExecutionOutput {
+                            result_code,
+                            tokens,
+                        } = method.run_local(&nekoton_utils::SimpleClock, account, &input)?;
+                        let output = tokens
+                            .as_deref()
+                            .map(nekoton_abi::make_abi_tokens)
+                            .transpose()?;
+
+                        #[derive(Serialize)]
+                        struct BlockId(#[serde(with = "serde_block_id")] ton_block::BlockIdExt);
+
+                        serde_json::json!({
+                            "block_id": state_block_id.map(BlockId),
+                            "exit_code": result_code,
+                            "output": output,
+                        })
+                    }
+                    // Run TVM get-method on the node
+                    None => {
+                        anyhow::ensure!(cmd.args.is_none(), "`--args` requires `--abi`");
+
+                        let method_id = match cmd.method.parse::<u64>() {
+                            Ok(method_id) => method_id,
+                            Err(_) => compute_method_id(&cmd.method),
+                        };
+                        let params = match cmd.params {
+                            Some(params) => {
+                                parse_hex_or_base64(&params).context("invalid params")?
+                            }
+                            None => empty_vm_stack()?,
+                        };
+                        let block_id = match block_id {
+                            Some(block_id) => block_id,
+                            None => {
+                                let stats = rpc_node.get_stats().await?.try_into_running()?;
+                                stats.last_mc_block
+                            }
+                        };
+
+                        let RunMethodOutput {
+                            block_id,
+                            shard_block_id,
+                            exit_code,
+                            stack,
+                        } = rpc_node
+                            .run_get_method(&address, &block_id, method_id, &params)
+                            .await
+                            .context(
+                                "failed to run get-method on the node \
+                                (use `--abi` to run the getter locally)",
+                            )?;
+                        serde_json::to_value(Response {
+                            block_id,
+                            shard_block_id,
+                            exit_code,
+                            stack: base64::encode(stack),
+                        })?
+                    }
+                }
+            }
+            SubCmd::LookupBlock(cmd) => {
+                let lookup = match (cmd.seqno, cmd.lt, cmd.utime) {
+                    (Some(seqno), None, None) => BlockLookup::Seqno(seqno),
+                    (None, Some(lt), None) => BlockLookup::Lt(lt),
+                    (None, None, Some(utime)) => BlockLookup::Utime(utime),
+                    _ => anyhow::bail!("exactly one of `--seqno`, `--lt` or `--utime` is required"),
+                };
+                let shard = u64::from_str_radix(&cmd.shard, 16).context("invalid shard")?;
+                let shard = ton_block::ShardIdent::with_tagged_prefix(cmd.workchain, shard)?;
+
+                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                let subscription = Subscription::new(rpc_node.clone(), node_udp_rpc);
+                let BlockHeaderWithId { block_id, info } =
+                    subscription.lookup_block(&shard, lookup).await?;
+                describe_block_header(block_id, &info)?
+            }
+            SubCmd::GetBlockHeader(cmd) => {
+                let block_id = parse_block_id(&cmd.block_id)?;
+                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                let block = node_udp_rpc.get_block(&block_id).await?;
+                describe_block_header(block_id, &block.block().read_info()?)?
+            }
+            SubCmd::GetShards(cmd) => {
+                #[derive(Serialize)]
+                struct BlockId(#[serde(with = "serde_block_id")] ton_block::BlockIdExt);
+
+                #[derive(Serialize)]
+                struct Response {
+                    #[serde(with = "serde_block_id")]
+                    block_id: ton_block::BlockIdExt,
+                    shards: Vec<BlockId>,
+                }
+
+                let block_id = match cmd.block {
+                    Some(block_id) => parse_block_id(&block_id)?,
+                    None => {
+                        let stats = rpc_node.get_stats().await?.try_into_running()?;
+                        stats.last_mc_block
+                    }
+                };
+                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                let block = node_udp_rpc.get_block(&block_id).await?;
+
+                let mut shards = block.shard_blocks()?.into_values().collect::<Vec<_>>();
+                shards.sort_by_key(|id| {
+                    (
+                        id.shard_id.workchain_id(),
+                        id.shard_id.shard_prefix_with_tag(),
+                    )
+                });
+                serde_json::to_value(Response {
+                    block_id,
+                    shards: shards.into_iter().map(BlockId).collect(),
+                })?
+            }
+            SubCmd::GetTransactions(cmd) => {
+                let address = parse_address(&cmd.address)?;
+                let from = match (cmd.from_lt, cmd.from_hash) {
+                    (Some(lt), Some(hash)) => {
+                        let hash = parse_key_hash(&hash).context("invalid transaction hash")?;
+                        let block_id = cmd.from_block.as_deref().map(parse_block_id).transpose()?;
+                        Some(TransactionsCursor {
+                            lt,
+                            hash: hash.into(),
+                            block_id,
+                        })
+                    }
+                    (None, None) if cmd.from_block.is_some() => {
+                        anyhow::bail!("`--from-block` requires `--from-lt` and `--from-hash`")
+                    }
+                    (None, None) => None,
+                    _ => anyhow::bail!("`--from-lt` and `--from-hash` must be specified together"),
+                };
+                let abi = cmd.abi.map(parse_contract_abi).transpose()?;
+
+                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                let subscription = Subscription::new(rpc_node.clone(), node_udp_rpc);
+                let page = subscription
+                    .get_account_transactions(&address, from, cmd.limit)
+                    .await?;
+
+                #[derive(Serialize)]
+                struct BlockId(#[serde(with = "serde_block_id")] ton_block::BlockIdExt);
+
+                // Cursor for the next page
+                let next = page.next.map(|next| {
+                    serde_json::json!({
+                        "lt": next.lt,
+                        "hash": next.hash.to_hex_string(),
+                        "block": next.block_id.map(BlockId),
+                    })
+                });
+
+                let transactions = page
+                    .transactions
+                    .iter()
+                    .map(|tx| describe_transaction(tx, abi.as_ref()))
+                    .collect::<Result<Vec<_>>>()?;
+
                 serde_json::json!({
-                    "state": base64::encode(ton_types::serialize_toc(&param.serialize()?)?)
+                    "transactions": transactions,
+                    "next": next,
                 })
             }
             SubCmd::SendMessage(cmd) => {
                 let data = parse_optional_input(cmd.data, false)?;
-                rpc_node.send_message(&data).await?;
-                serde_json::json!({})
+                match rpc_node.send_message(&data).await? {
+                    SendMessageStatus::Status(status) => serde_json::json!({
+                        "status": status,
+                    }),
+                    SendMessageStatus::Success => serde_json::json!({
+                        "status": null,
+                    }),
+                }
             }
         };
 
@@ -131,7 +405,13 @@ enum SubCmd {
     SetStatesGcInterval(CmdSetStatesGcInterval),
     GetConfig(CmdGetConfig),
     GetConfigParam(CmdGetConfigParam),
+    GetMasterchainInfo(CmdGetMasterchainInfo),
     GetAccount(CmdGetAccount),
+    RunMethod(CmdRunMethod),
+    LookupBlock(CmdLookupBlock),
+    GetBlockHeader(CmdGetBlockHeader),
+    GetShards(CmdGetShards),
+    GetTransactions(CmdGetTransactions),
     SendMessage(CmdSendMessage),
 }
 
@@ -224,6 +504,11 @@ struct CmdGetConfigParam {
     param: u32,
 }
 
+#[derive(FromArgs)]
+/// Gets the latest masterchain block known to the node
+#[argh(subcommand, name = "getmcinfo")]
+struct CmdGetMasterchainInfo {}
+
 #[derive(FromArgs)]
 /// Gets account state
 #[argh(subcommand, name = "getaccount")]
@@ -231,6 +516,111 @@ struct CmdGetAccount {
     /// address (raw format)
     #[argh(positional)]
     address: String,
+
+    /// masterchain block id (`workchain:shard:seqno:root_hash:file_hash`), latest state by default
+    #[argh(option, short = 'b')]
+    block: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Runs TVM get-method of the account, or the ABI getter locally
+#[argh(subcommand, name = "runmethod")]
+struct CmdRunMethod {
+    /// address (raw format)
+    #[argh(positional)]
+    address: String,
+
+    /// method name or id
+    #[argh(positional)]
+    method: String,
+
+    /// base64 encoded `VmStack` BOC with method arguments, no arguments by default
+    #[argh(option, short = 'p')]
+    params: Option<String>,
+
+    /// path to the JSON ABI file to run the getter locally
+    #[argh(option, short = 'a')]
+    abi: Option<PathBuf>,
+
+    /// ABI getter arguments (JSON object), used with `--abi`
+    #[argh(option)]
+    args: Option<serde_json::Value>,
+
+    /// masterchain block id (`workchain:shard:seqno:root_hash:file_hash`), latest block by default
+    #[argh(option, short = 'b')]
+    block: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Finds the block by seqno, logical time or unix time
+#[argh(subcommand, name = "lookupblock")]
+struct CmdLookupBlock {
+    /// workchain. -1 default
+    #[argh(option, short = 'w', default = "-1")]
+    workchain: i32,
+
+    /// shard prefix with tag (hex encoded). 8000000000000000 default
+    #[argh(option, short = 's', default = "String::from(\"8000000000000000\")")]
+    shard: String,
+
+    /// block seqno
+    #[argh(option)]
+    seqno: Option<u32>,
+
+    /// logical time within the block
+    #[argh(option)]
+    lt: Option<u64>,
+
+    /// unix time within the block
+    #[argh(option)]
+    utime: Option<u32>,
+}
+
+#[derive(FromArgs)]
+/// Downloads the block and prints its header
+#[argh(subcommand, name = "getblockheader")]
+struct CmdGetBlockHeader {
+    /// block id (`workchain:shard:seqno:root_hash:file_hash`)
+    #[argh(positional)]
+    block_id: String,
+}
+
+#[derive(FromArgs)]
+/// Gets the latest shard blocks
+#[argh(subcommand, name = "getshards")]
+struct CmdGetShards {
+    /// masterchain block id (`workchain:shard:seqno:root_hash:file_hash`), latest block by default
+    #[argh(option, short = 'b')]
+    block: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Gets account transactions, starting from the latest one
+#[argh(subcommand, name = "transactions")]
+struct CmdGetTransactions {
+    /// address (raw format)
+    #[argh(positional)]
+    address: String,
+
+    /// max number of transactions
+    #[argh(option, short = 'l', default = "10")]
+    limit: usize,
+
+    /// logical time of the transaction to start from
+    #[argh(option)]
+    from_lt: Option<u64>,
+
+    /// hash of the transaction to start from (hex encoded string)
+    #[argh(option)]
+    from_hash: Option<String>,
+
+    /// block to continue the blocks walk from (`next.block` of the previous page)
+    #[argh(option)]
+    from_block: Option<String>,
+
+    /// path to the JSON ABI file to decode message bodies
+    #[argh(option, short = 'a')]
+    abi: Option<PathBuf>,
 }
 
 #[derive(FromArgs)]
@@ -241,3 +631,57 @@ struct CmdSendMessage {
     #[argh(positional)]
     data: Option<String>,
 }
+
+fn describe_block_header(
+    block_id: ton_block::BlockIdExt,
+    info: &ton_block::BlockInfo,
+) -> Result<serde_json::Value> {
+    #[derive(Serialize)]
+    struct Response {
+        #[serde(with = "serde_block_id")]
+        block_id: ton_block::BlockIdExt,
+        gen_utime: u32,
+        start_lt: u64,
+        end_lt: u64,
+        key_block: bool,
+        prev_key_block_seqno: u32,
+        min_ref_mc_seqno: u32,
+        gen_catchain_seqno: u32,
+    }
+
+    serde_json::to_value(Response {
+        block_id,
+        gen_utime: info.gen_utime().0,
+        start_lt: info.start_lt(),
+        end_lt: info.end_lt(),
+        key_block: info.key_block(),
+        prev_key_block_seqno: info.prev_key_block_seqno(),
+        min_ref_mc_seqno: info.min_ref_mc_seqno(),
+        gen_catchain_seqno: info.gen_catchain_seqno(),
+    })
+    .map_err(From::from)
+}
+
+/// Computes get-method id from its name (same as in FunC)
+fn compute_method_id(name: &str) -> u64 {
+    // CRC16/XMODEM
+    let mut crc: u16 = 0;
+    for byte in name.as_bytes() {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc as u64 | 0x10000
+}
+
+/// Serialized `VmStack` without items
+fn empty_vm_stack() -> Result<Vec<u8>> {
+    let mut builder = ton_types::BuilderData::new();
+    builder.append_bits(0, 24)?;
+    Ok(ton_types::serialize_toc(&builder.into_cell()?)?)
+}
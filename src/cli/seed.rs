@@ -1,6 +1,9 @@
-use anyhow::Result;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
 use argh::FromArgs;
 
+use crate::contracts::wallet;
 use crate::crypto::{self, MnemonicType};
 use crate::util::*;
 
@@ -17,7 +20,10 @@ impl Cmd {
         match self.subcommand {
             SubCmd::Generate(cmd) => cmd.run(),
             SubCmd::Derive(cmd) => cmd.run(),
+            SubCmd::Accounts(cmd) => cmd.run(),
             SubCmd::Pubkey(cmd) => cmd.run(),
+            SubCmd::Split(cmd) => cmd.run(),
+            SubCmd::Combine(cmd) => cmd.run(),
         }
     }
 }
@@ -27,7 +33,10 @@ impl Cmd {
 enum SubCmd {
     Generate(CmdGenerate),
     Derive(CmdDerive),
+    Accounts(CmdAccounts),
     Pubkey(CmdPubkey),
+    Split(CmdSplit),
+    Combine(CmdCombine),
 }
 
 #[derive(Debug, PartialEq, FromArgs)]
@@ -63,6 +72,14 @@ struct CmdDerive {
     #[argh(option, short = 'p')]
     path: Option<String>,
 
+    /// account index for bip39 mnemonic (`m/44'/396'/0'/0/<account>`)
+    #[argh(option, short = 'a')]
+    account: Option<u32>,
+
+    /// ask for the bip39 passphrase (or read it from `STEVER_SEED_PASSPHRASE`)
+    #[argh(switch)]
+    passphrase: bool,
+
     /// encode keys in base64 (hex by default)
     #[argh(switch)]
     base64: bool,
@@ -73,19 +90,69 @@ impl CmdDerive {
         let seed = parse_optional_input(self.seed, true)?;
         let seed = String::from_utf8(seed)?;
 
-        let path = if let Some(path) = &self.path {
-            path.as_str()
-        } else {
-            crypto::DEFAULT_PATH
+        let passphrase = match self.passphrase {
+            true => seed_passphrase()?,
+            false => String::new(),
         };
 
-        let keys = crypto::derive_from_phrase(seed.trim(), self.ty, path)?;
+        let path = crypto::derivation_path(self.path, self.account)?;
+        let keys = crypto::derive_from_phrase(seed.trim(), self.ty, &path, &passphrase)?;
 
         print_output(encode_key_pair(keys.secret, keys.public, self.base64));
         Ok(())
     }
 }
 
+#[derive(Debug, PartialEq, FromArgs)]
+/// Lists public keys and wallet addresses of the first accounts
+#[argh(subcommand, name = "accounts")]
+struct CmdAccounts {
+    /// seed phrase or empty for input from stdin
+    #[argh(positional)]
+    seed: Option<String>,
+
+    /// number of accounts to derive
+    #[argh(option, short = 'n', default = "10")]
+    count: u32,
+
+    /// ask for the bip39 passphrase (or read it from `STEVER_SEED_PASSPHRASE`)
+    #[argh(switch)]
+    passphrase: bool,
+}
+
+impl CmdAccounts {
+    fn run(self) -> Result<()> {
+        let seed = parse_optional_input(self.seed, true)?;
+        let seed = String::from_utf8(seed)?;
+        let seed = seed.trim();
+
+        let passphrase = match self.passphrase {
+            true => seed_passphrase()?,
+            false => String::new(),
+        };
+
+        let accounts = (0..self.count)
+            .map(|account| {
+                let path = crypto::account_path(account);
+                let public =
+                    crypto::derive_from_phrase(seed, MnemonicType::Bip39, &path, &passphrase)?
+                        .public;
+
+                Ok(serde_json::json!({
+                    "account": account,
+                    "path": path,
+                    "public": hex::encode(public.as_bytes()),
+                    "masterchain_wallet": wallet::compute_wallet_address(-1, &public).to_string(),
+                    "basechain_wallet": wallet::compute_wallet_address(0, &public).to_string(),
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        print_output(serde_json::Value::Array(accounts));
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, FromArgs)]
 /// Computes public key from secret key
 #[argh(subcommand, name = "pubkey")]
@@ -111,6 +178,152 @@ impl CmdPubkey {
     }
 }
 
+#[derive(Debug, PartialEq, FromArgs)]
+/// Splits seed or secret key into shares
+#[argh(subcommand, name = "split")]
+struct CmdSplit {
+    /// mnemonic type
+    #[argh(option, long = "type", short = 't', default = "MnemonicType::Bip39")]
+    ty: MnemonicType,
+
+    /// seed phrase, secret key in hex or empty for input from stdin
+    #[argh(positional)]
+    seed: Option<String>,
+
+    /// number of shares required to recover the seed
+    #[argh(option, short = 'k')]
+    threshold: u8,
+
+    /// total number of shares
+    #[argh(option, short = 'n')]
+    shares: u8,
+}
+
+impl CmdSplit {
+    fn run(self) -> Result<()> {
+        let input = parse_optional_input(self.seed, true)?;
+        let input = String::from_utf8(input)?;
+        let input = input.trim();
+
+        let secret = if input.contains(char::is_whitespace) {
+            SplitSecret::Seed(
+                self.ty,
+                input.split_whitespace().collect::<Vec<_>>().join(" "),
+            )
+        } else {
+            let secret = parse_hex_or_base64(input)?;
+            SplitSecret::Secret(ed25519_dalek::SecretKey::from_bytes(&secret)?)
+        };
+
+        let public = secret.derive_public()?;
+        let shares = crypto::split_secret(
+            &secret.to_bytes(),
+            public.as_bytes(),
+            self.threshold,
+            self.shares,
+        )?;
+
+        print_output(serde_json::json!({
+            "public": hex::encode(public.as_bytes()),
+            "shares": shares.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        }));
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, FromArgs)]
+/// Recovers seed or secret key from shares
+#[argh(subcommand, name = "combine")]
+struct CmdCombine {
+    /// shares or empty for input from stdin (one share per line)
+    #[argh(positional)]
+    shares: Vec<String>,
+}
+
+impl CmdCombine {
+    fn run(self) -> Result<()> {
+        let shares = if self.shares.is_empty() {
+            let input = String::from_utf8(parse_optional_input(None, true)?)?;
+            input
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(crypto::Share::from_str)
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            self.shares
+                .iter()
+                .map(|share| crypto::Share::from_str(share))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let expected_public = shares.first().map(|share| share.public);
+
+        let secret = SplitSecret::from_bytes(&crypto::combine_shares(&shares)?)?;
+        let public = secret.derive_public()?;
+        anyhow::ensure!(
+            Some(public.to_bytes()) == expected_public,
+            "recovered public key mismatch"
+        );
+
+        print_output(match secret {
+            SplitSecret::Seed(_, seed) => serde_json::json!({
+                "seed": seed,
+                "public": hex::encode(public.as_bytes()),
+            }),
+            SplitSecret::Secret(secret) => serde_json::json!({
+                "secret": hex::encode(secret.as_bytes()),
+                "public": hex::encode(public.as_bytes()),
+            }),
+        });
+        Ok(())
+    }
+}
+
+enum SplitSecret {
+    Seed(MnemonicType, String),
+    Secret(ed25519_dalek::SecretKey),
+}
+
+impl SplitSecret {
+    const BIP39_SEED: u8 = 0;
+    const LEGACY_SEED: u8 = 1;
+    const SECRET: u8 = 2;
+
+    fn derive_public(&self) -> Result<ed25519_dalek::PublicKey> {
+        Ok(match self {
+            Self::Seed(ty, seed) => {
+                crypto::derive_from_phrase(seed, *ty, crypto::DEFAULT_PATH, "")?.public
+            }
+            Self::Secret(secret) => ed25519_dalek::PublicKey::from(secret),
+        })
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let (tag, data) = match self {
+            Self::Seed(MnemonicType::Bip39, seed) => (Self::BIP39_SEED, seed.as_bytes()),
+            Self::Seed(MnemonicType::Legacy, seed) => (Self::LEGACY_SEED, seed.as_bytes()),
+            Self::Secret(secret) => (Self::SECRET, secret.as_bytes().as_slice()),
+        };
+
+        let mut result = Vec::with_capacity(1 + data.len());
+        result.push(tag);
+        result.extend_from_slice(data);
+        result
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (tag, data) = bytes.split_first().context("empty secret")?;
+        let seed = || String::from_utf8(data.to_vec()).context("invalid seed");
+        Ok(match *tag {
+            Self::BIP39_SEED => Self::Seed(MnemonicType::Bip39, seed()?),
+            Self::LEGACY_SEED => Self::Seed(MnemonicType::Legacy, seed()?),
+            Self::SECRET => Self::Secret(ed25519_dalek::SecretKey::from_bytes(data)?),
+            _ => anyhow::bail!("unknown secret type"),
+        })
+    }
+}
+
 fn encode_key_pair(
     secret: ed25519_dalek::SecretKey,
     public: ed25519_dalek::PublicKey,
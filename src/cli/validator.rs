@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -11,7 +12,7 @@ use tokio_util::sync::CancellationToken;
 use super::{CliContext, ProjectDirs};
 use crate::config::*;
 use crate::contracts::*;
-use crate::network::{ConfigWithId, NodeStats, NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::network::{ConfigWithId, NodeRpcError, NodeStats, NodeTcpRpc, NodeUdpRpc, Subscription};
 use crate::util::Ever;
 
 #[derive(FromArgs)]
@@ -49,6 +50,14 @@ pub struct Cmd {
     /// ignore contracts deployment
     #[argh(switch)]
     ignore_deploy: bool,
+
+    /// execute external messages locally before sending them
+    #[argh(switch)]
+    emulate_messages: bool,
+
+    /// DePool rounds check interval (in seconds). 300 seconds default
+    #[argh(option, default = "300")]
+    ticktock_check_interval: u64,
 }
 
 impl Cmd {
@@ -57,14 +66,16 @@ impl Cmd {
         let signal_rx = broxus_util::any_signal(broxus_util::TERMINATION_SIGNALS);
 
         // Create validation manager
-        let mut manager = ValidationManager {
+        let manager = ValidationManager {
             ctx,
             max_time_diff: std::cmp::max(self.max_time_diff as i32, 5),
             stake_unfreeze_offset: self.stake_unfreeze_offset,
             elections_start_offset: self.elections_start_offset,
             elections_end_offset: self.elections_end_offset,
             ignore_deploy: self.ignore_deploy,
+            emulate_messages: self.emulate_messages,
             last_params: Default::default(),
+            subscription: Default::default(),
             guard: Arc::new(Mutex::new(())),
         };
 
@@ -85,6 +96,11 @@ impl Cmd {
             }
         });
 
+        // Prepare DePool ticktock scheduler future
+        let ticktock_check_interval = std::cmp::max(self.ticktock_check_interval, 10);
+        let ticktock_fut =
+            manager.run_ticktock_scheduler(Duration::from_secs(ticktock_check_interval));
+
         // Prepare validation future
         let validation_fut = async {
             self.min_retry_interval = std::cmp::max(self.min_retry_interval, 1);
@@ -95,7 +111,12 @@ impl Cmd {
             let mut interval = self.min_retry_interval;
             loop {
                 if let Err(e) = manager.try_validate().await {
-                    tracing::error!("error occured: {e:?}");
+                    match e.downcast_ref::<NodeRpcError>() {
+                        Some(NodeRpcError::NodeError { code, .. }) => {
+                            tracing::error!(code, "node rejected the query: {e:?}");
+                        }
+                        _ => tracing::error!("error occured: {e:?}"),
+                    }
                 }
 
                 tracing::info!("retrying in {interval} seconds");
@@ -111,6 +132,7 @@ impl Cmd {
         // Cancellable main loop
         tokio::select! {
             _ = validation_fut => {},
+            _ = ticktock_fut => {},
             _ = cancelled => {},
         };
 
@@ -125,12 +147,31 @@ struct ValidationManager {
     elections_start_offset: u32,
     elections_end_offset: u32,
     ignore_deploy: bool,
+    emulate_messages: bool,
     last_params: parking_lot::Mutex<Option<AppConfigValidator>>,
+    subscription: tokio::sync::OnceCell<Arc<Subscription>>,
     guard: Arc<Mutex<()>>,
 }
 
 impl ValidationManager {
-    async fn try_validate(&mut self) -> Result<()> {
+    /// Returns the subscription shared by the validation loop and the ticktock scheduler.
+    ///
+    /// It is created only once, because the node UDP client is bound to the fixed
+    /// ADNL client port and the TCP connection is restored on failures
+    async fn subscription(&self, config: &AppConfig) -> Result<Arc<Subscription>> {
+        self.subscription
+            .get_or_try_init(|| async {
+                let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
+                let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
+                let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc);
+                subscription.set_emulate_messages(self.emulate_messages);
+                Ok::<_, anyhow::Error>(subscription)
+            })
+            .await
+            .cloned()
+    }
+
+    async fn try_validate(&self) -> Result<()> {
         const SYNC_CHECK_INTERVAL: u32 = 10;
 
         tracing::info!("started validation loop");
@@ -155,16 +196,15 @@ impl ValidationManager {
                 }
             };
 
-            // Create tcp rpc and wait until node is synced
-            let node_tcp_rpc = NodeTcpRpc::new(config.control()?).await?;
-            if !self.is_synced(&node_tcp_rpc, validator.is_single()).await? {
+            // Wait until node is synced
+            let subscription = self.subscription(&config).await?;
+            if !self
+                .is_synced(subscription.tcp_rpc(), validator.is_single())
+                .await?
+            {
                 interval = SYNC_CHECK_INTERVAL;
                 continue;
             }
-            let node_udp_rpc = NodeUdpRpc::new(config.adnl()?).await?;
-
-            // Create subscription
-            let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc);
             subscription.ensure_ready().await?;
 
             // Get current network config params
@@ -173,6 +213,18 @@ impl ValidationManager {
                 config: blockchain_config,
             } = subscription.tcp_rpc().get_config_all().await?;
 
+            // Check DePool balance thresholds against the current gas prices
+            if let AppConfigValidator::DePool(depool) = &validator {
+                match depool::check_balance_thresholds(&depool.balances, &blockchain_config) {
+                    Ok(warnings) => {
+                        for warning in warnings {
+                            tracing::warn!("{warning}");
+                        }
+                    }
+                    Err(e) => tracing::warn!("failed to check DePool balance thresholds: {e:?}"),
+                }
+            }
+
             if !self.ignore_deploy && self.ensure_deployed(&validator, &subscription).await? {
                 // Proceed to the next iteration after contracts deployment
                 continue;
@@ -272,20 +324,21 @@ impl ValidationManager {
             }
 
             // Prepare context
-            let keypair = dirs.load_validator_keys()?;
+            let signer = dirs.load_validator_signer(subscription.tcp_rpc())?;
             let ctx = ElectionsContext {
                 subscription,
                 elector,
                 elector_data,
                 election_id,
                 timings,
+                dirs,
                 guard: &self.guard,
             };
 
             // Prepare election future
             let validation = match validator {
-                AppConfigValidator::Single(validation) => validation.elect(keypair, ctx).boxed(),
-                AppConfigValidator::DePool(validation) => validation.elect(keypair, ctx).boxed(),
+                AppConfigValidator::Single(validation) => validation.elect(signer, ctx).boxed(),
+                AppConfigValidator::DePool(validation) => validation.elect(signer, ctx).boxed(),
             };
 
             // Try elect
@@ -304,6 +357,160 @@ impl ValidationManager {
         }
     }
 
+    /// Sends ticktocks to the DePool when its rounds are stuck waiting for them.
+    /// Also reports unhealthy stEVER strategies.
+    ///
+    /// Elections flow only sends ticktocks to prepare the target round,
+    /// while rounds also need them after the validation start and stake unfreeze.
+    async fn run_ticktock_scheduler(&self, interval: Duration) {
+        let mut attempts = TickTockAttempts::default();
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = self.check_depool_rounds(&mut attempts).await {
+                tracing::error!("failed to check DePool rounds: {e:?}");
+            }
+        }
+    }
+
+    async fn check_depool_rounds(&self, attempts: &mut TickTockAttempts) -> Result<()> {
+        let config = self.ctx.load_config()?;
+        let validator = match &config.validator {
+            Some(AppConfigValidator::DePool(validator)) => validator,
+            _ => return Ok(()),
+        };
+
+        let subscription = self.subscription(&config).await?;
+
+        let depool = DePool::new(
+            validator.depool_type.clone(),
+            validator.depool.clone(),
+            subscription.clone(),
+        );
+        if !depool.is_deployed().await? {
+            return Ok(());
+        }
+
+        let depool_state = depool.get_state().await?;
+        let depool_info = depool
+            .get_info(&depool_state)
+            .context("failed to get DePool info")?;
+        let rounds = depool
+            .get_rounds(&depool_state)
+            .context("failed to get DePool rounds")?;
+        attempts.retain(&rounds);
+
+        // Report unhealthy stEVER strategies
+        if validator.depool_type.version()?.capabilities.stever {
+            if let Err(e) = self
+                .check_strategies(validator, &depool, &depool_state, &subscription)
+                .await
+            {
+                tracing::error!("failed to check stEVER strategies: {e:?}");
+            }
+        }
+
+        let now = now();
+        let stuck_rounds = rounds
+            .values()
+            .filter(|round| round.is_waiting_for_ticktock(now, self.stake_unfreeze_offset))
+            .filter(|round| attempts.is_due(round))
+            .map(|round| round.id)
+            .collect::<Vec<_>>();
+
+        let path = &self.ctx.dirs().depool_rounds(depool.address());
+        if stuck_rounds.is_empty() {
+            return depool::RoundsHistory::modify(path, depool.address(), |history| {
+                history.update(&rounds, &depool_info, now)
+            });
+        }
+
+        tracing::info!(?stuck_rounds, "DePool rounds are waiting for the ticktock");
+
+        let signer = self
+            .ctx
+            .dirs()
+            .load_validator_signer(subscription.tcp_rpc())?;
+        let wallet = Wallet::new(0, signer, subscription.clone());
+        anyhow::ensure!(
+            wallet.address() == &validator.owner,
+            "validator wallet address mismatch"
+        );
+
+        let balance = wallet.get_balance().await?.unwrap_or_default();
+        anyhow::ensure!(
+            balance >= 2 * ONE_EVER,
+            "validator wallet balance is not enough for the ticktock ({} EVER)",
+            Ever(balance)
+        );
+
+        subscription.ensure_ready().await?;
+
+        let result = {
+            // Prevent shutdown during sending ticktock
+            let _guard = self.guard.lock().await;
+
+            // Count only the ticktocks which were actually sent
+            attempts.register(&stuck_rounds, now);
+
+            tracing::info!("sending ticktock");
+            wallet.call(depool.ticktock()?).await
+        };
+
+        let attempt = depool::TickTockAttempt {
+            time: now,
+            rounds: stuck_rounds,
+            tx_hash: result.as_ref().ok().map(|tx| tx.hash.to_hex_string()),
+            error: result.as_ref().err().map(|e| format!("{e:?}")),
+        };
+        depool::RoundsHistory::modify(path, depool.address(), |history| {
+            history.update(&rounds, &depool_info, now);
+            history.add_ticktock(attempt);
+        })?;
+
+        result.context("failed to send ticktock")?;
+        Ok(())
+    }
+
+    async fn check_strategies(
+        &self,
+        validator: &AppConfigValidatorDePool,
+        depool: &DePool,
+        depool_state: &ton_block::AccountStuff,
+        subscription: &Arc<Subscription>,
+    ) -> Result<()> {
+        let strategies =
+            strategy::check_strategies(depool, depool_state, subscription.tcp_rpc()).await?;
+        if strategies.is_empty() {
+            tracing::warn!("stEVER DePool has no strategies");
+        }
+
+        if let Some(configured) = &validator.strategy {
+            if !strategies
+                .iter()
+                .any(|status| &status.address == configured)
+            {
+                tracing::warn!(
+                    strategy = %configured,
+                    "configured stEVER strategy is not an allowed participant"
+                );
+            }
+        }
+
+        for status in strategies {
+            if status.is_healthy() {
+                continue;
+            }
+            tracing::warn!(
+                strategy = %status.address,
+                issues = ?status.issues,
+                balance = %Ever(status.balance),
+                "stEVER strategy is unhealthy"
+            );
+        }
+
+        Ok(())
+    }
+
     async fn ensure_deployed(
         &self,
         validator: &AppConfigValidator,
@@ -367,6 +574,7 @@ struct ElectionsContext<'a> {
     elector_data: elector::ElectorData,
     election_id: u32,
     timings: ton_block::ConfigParam15,
+    dirs: &'a ProjectDirs,
     guard: &'a Mutex<()>,
 }
 
@@ -376,7 +584,7 @@ impl AppConfigValidatorSingle {
         Ok(())
     }
 
-    async fn elect(self, keypair: ed25519_dalek::Keypair, ctx: ElectionsContext<'_>) -> Result<()> {
+    async fn elect(self, signer: Signer, ctx: ElectionsContext<'_>) -> Result<()> {
         tracing::info!(
             election_id = ctx.election_id,
             address = %self.address,
@@ -385,7 +593,7 @@ impl AppConfigValidatorSingle {
             "election as single"
         );
 
-        let wallet = Wallet::new(-1, keypair, ctx.subscription);
+        let wallet = Wallet::new(-1, signer, ctx.subscription);
         anyhow::ensure!(
             wallet.address() == &self.address,
             "validator wallet address mismatch"
@@ -460,8 +668,11 @@ impl AppConfigValidatorDePool {
                 match &mut self.state {
                     Some(wallet) => Ok(wallet),
                     state @ None => {
-                        let keypair = self.ctx.dirs.load_validator_keys()?;
-                        let res = Wallet::new(0, keypair, self.ctx.subscription.clone());
+                        let signer = self
+                            .ctx
+                            .dirs
+                            .load_validator_signer(self.ctx.subscription.tcp_rpc())?;
+                        let res = Wallet::new(0, signer, self.ctx.subscription.clone());
                         anyhow::ensure!(
                             res.address() == self.target,
                             "validator wallet address mismatch"
@@ -479,7 +690,7 @@ impl AppConfigValidatorDePool {
         };
 
         let mut depool = DePool::new(
-            self.depool_type,
+            self.depool_type.clone(),
             self.depool.clone(),
             ctx.subscription.clone(),
         );
@@ -501,7 +712,7 @@ impl AppConfigValidatorDePool {
                 .context("deployment params not found")?;
 
             // Load and check depool keypair
-            depool.set_keypair(ctx.dirs.load_depool_keys()?)?;
+            depool.set_signer(ctx.dirs.load_depool_signer(ctx.subscription.tcp_rpc())?)?;
 
             // Prepare wallet
             let wallet = wallet.get_or_init()?;
@@ -514,7 +725,7 @@ impl AppConfigValidatorDePool {
                 .unwrap_or_default();
 
             // Compute remaining depool balance
-            let depool_initial_balance = DePool::INITIAL_BALANCE
+            let depool_initial_balance = (self.balances.initial_balance as u128)
                 .checked_sub(depool_balance)
                 .and_then(|diff| (diff > 0).then_some(std::cmp::max(diff, ONE_EVER)));
 
@@ -553,7 +764,7 @@ impl AppConfigValidatorDePool {
         }
 
         // Handle stEVER depool case
-        if let DePoolType::StEver = self.depool_type {
+        if self.depool_type.version()?.capabilities.stever {
             let depool_state = depool.get_state().await?;
 
             // Get allowed participants
@@ -633,7 +844,7 @@ impl AppConfigValidatorDePool {
         Ok(())
     }
 
-    async fn elect(self, keypair: ed25519_dalek::Keypair, ctx: ElectionsContext<'_>) -> Result<()> {
+    async fn elect(self, signer: Signer, ctx: ElectionsContext<'_>) -> Result<()> {
         tracing::info!(
             election_id = ctx.election_id,
             depool = %self.depool,
@@ -643,14 +854,14 @@ impl AppConfigValidatorDePool {
             "election as DePool"
         );
 
-        let wallet = Wallet::new(0, keypair, ctx.subscription.clone());
+        let wallet = Wallet::new(0, signer, ctx.subscription.clone());
         anyhow::ensure!(
             wallet.address() == &self.owner,
             "validator wallet address mismatch"
         );
 
         let depool = DePool::new(
-            self.depool_type,
+            self.depool_type.clone(),
             self.depool.clone(),
             ctx.subscription.clone(),
         );
@@ -668,6 +879,11 @@ impl AppConfigValidatorDePool {
         );
         anyhow::ensure!(depool_info.proxies.len() == 2, "invalid DePool proxies");
 
+        // Save rounds snapshot for the rewards report
+        if let Err(e) = self.update_rounds_history(&depool, &depool_state, &depool_info, &ctx) {
+            tracing::warn!("failed to update DePool rounds history: {e:?}");
+        }
+
         // Ensure that depool and proxy balances are enough
         self.maintain_balances(&wallet, &depool, &ctx)
             .await
@@ -738,7 +954,7 @@ impl AppConfigValidatorDePool {
         ctx: &ElectionsContext<'_>,
     ) -> Result<()> {
         // Check and refill depool and proxy balances
-        let refill_messages = depool.maintain_balances().await?;
+        let refill_messages = depool.maintain_balances(&self.balances).await?;
         for message in refill_messages {
             tracing::info!(
                 target = %message.dst,
@@ -761,6 +977,21 @@ impl AppConfigValidatorDePool {
         Ok(())
     }
 
+    fn update_rounds_history(
+        &self,
+        depool: &DePool,
+        depool_state: &ton_block::AccountStuff,
+        depool_info: &depool::DePoolInfo,
+        ctx: &ElectionsContext<'_>,
+    ) -> Result<()> {
+        let rounds = depool.get_rounds(depool_state)?;
+
+        let path = ctx.dirs.depool_rounds(depool.address());
+        depool::RoundsHistory::modify(path, depool.address(), |history| {
+            history.update(&rounds, depool_info, now())
+        })
+    }
+
     async fn update_depool(
         &self,
         wallet: &Wallet,
@@ -862,6 +1093,64 @@ impl AppConfigValidatorDePool {
     }
 }
 
+/// Ticktocks sent for each DePool round step
+#[derive(Default)]
+struct TickTockAttempts {
+    rounds: HashMap<u64, (depool::RoundStep, u32, u32)>,
+}
+
+impl TickTockAttempts {
+    /// Max number of ticktocks for the same round step
+    const MAX_ATTEMPTS: u32 = 5;
+    /// Min interval between ticktocks for the same round step (in seconds)
+    const RETRY_INTERVAL: u32 = 600;
+
+    /// Returns whether the ticktock can be sent for the round
+    /// (it is not too early and not all attempts failed)
+    fn is_due(&mut self, round: &depool::Round) -> bool {
+        let now = now();
+        let (step, count, last_attempt) = self.rounds.entry(round.id).or_insert((round.step, 0, 0));
+
+        if *step != round.step {
+            *step = round.step;
+            *count = 0;
+        }
+
+        if *count >= Self::MAX_ATTEMPTS {
+            // Warn only once for each step
+            if *count == Self::MAX_ATTEMPTS {
+                tracing::warn!(
+                    round_id = round.id,
+                    step = ?round.step,
+                    "DePool round is still stuck after {} ticktocks",
+                    Self::MAX_ATTEMPTS
+                );
+                *count += 1;
+            }
+            return false;
+        }
+
+        now >= last_attempt.saturating_add(Self::RETRY_INTERVAL)
+    }
+
+    /// Forgets the rounds which are completed or no longer stored in the DePool
+    fn retain(&mut self, rounds: &depool::RoundsMap) {
+        self.rounds.retain(|id, _| {
+            matches!(rounds.get(id), Some(round) if round.step != depool::RoundStep::Completed)
+        });
+    }
+
+    /// Registers the ticktock sent for the rounds
+    fn register(&mut self, rounds: &[u64], now: u32) {
+        for id in rounds {
+            if let Some((_, count, last_attempt)) = self.rounds.get_mut(id) {
+                *count += 1;
+                *last_attempt = now;
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 enum Timeline {
     BeforeElections {
@@ -959,15 +1248,13 @@ impl Wallet {
 }
 
 impl ProjectDirs {
-    fn load_validator_keys(&self) -> Result<ed25519_dalek::Keypair> {
-        let keys = StoredKeys::load(&self.validator_keys)
-            .context("failed to load validator wallet keys")?;
-        Ok(keys.as_keypair())
+    fn load_validator_signer(&self, node_tcp_rpc: &NodeTcpRpc) -> Result<Signer> {
+        Signer::load(&self.validator_keys, node_tcp_rpc)
+            .context("failed to load validator wallet keys")
     }
 
-    fn load_depool_keys(&self) -> Result<ed25519_dalek::Keypair> {
-        let keys = StoredKeys::load(&self.depool_keys).context("failed to load DePool keys")?;
-        Ok(keys.as_keypair())
+    fn load_depool_signer(&self, node_tcp_rpc: &NodeTcpRpc) -> Result<Signer> {
+        Signer::load(&self.depool_keys, node_tcp_rpc).context("failed to load DePool keys")
     }
 }
 
@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use ton_types::IBitstring;
+
+use super::CliContext;
+use crate::config::*;
+use crate::contracts::*;
+use crate::network::{NodeTcpRpc, NodeUdpRpc, Subscription};
+use crate::util::*;
+
+#[derive(FromArgs)]
+/// Validator wallet operations
+#[argh(subcommand, name = "wallet")]
+pub struct Cmd {
+    #[argh(subcommand)]
+    subcommand: SubCmd,
+}
+
+impl Cmd {
+    pub async fn run(self, ctx: CliContext) -> Result<()> {
+        let config = ctx.load_config()?;
+        let (workchain_id, address) = match &config.validator {
+            Some(AppConfigValidator::Single(single)) => (-1, single.address.clone()),
+            Some(AppConfigValidator::DePool(depool)) => (0, depool.owner.clone()),
+            None => return Err(WalletError::ValidatorNotConfigured.into()),
+        };
+
+        let node_tcp_rpc = NodeTcpRpc::new(config.control()?)
+            .await
+            .context("failed to build node TCP client")?;
+        let node_udp_rpc = NodeUdpRpc::new(config.adnl()?)
+            .await
+            .context("failed to build node UDP client")?;
+        let subscription = Subscription::new(node_tcp_rpc, node_udp_rpc);
+
+        let wallet = ValidatorWallet {
+            ctx,
+            workchain_id,
+            address,
+            subscription,
+        };
+
+        let response = match self.subcommand {
+            SubCmd::Balance(cmd) => cmd.run(wallet).await?,
+            SubCmd::Transfer(cmd) => cmd.run(wallet).await?,
+            SubCmd::Deploy(cmd) => cmd.run(wallet).await?,
+            SubCmd::History(cmd) => cmd.run(wallet).await?,
+        };
+
+        print_output(response);
+        Ok(())
+    }
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum SubCmd {
+    Balance(CmdBalance),
+    Transfer(CmdTransfer),
+    Deploy(CmdDeploy),
+    History(CmdHistory),
+}
+
+#[derive(FromArgs)]
+/// Shows the validator wallet state and balance
+#[argh(subcommand, name = "balance")]
+struct CmdBalance {}
+
+impl CmdBalance {
+    async fn run(self, wallet: ValidatorWallet) -> Result<serde_json::Value> {
+        let account = wallet
+            .subscription
+            .get_account_state(&wallet.address)
+            .await?;
+
+        let (state, balance) = match account {
+            Some(account) => {
+                let state = match account.storage.state {
+                    ton_block::AccountState::AccountActive { .. } => "active",
+                    ton_block::AccountState::AccountUninit => "uninit",
+                    ton_block::AccountState::AccountFrozen { .. } => "frozen",
+                };
+                (state, account.storage.balance.grams.0)
+            }
+            None => ("not_exists", 0),
+        };
+
+        Ok(serde_json::json!({
+            "address": wallet.address.to_string(),
+            "state": state,
+            "balance": balance.to_string(),
+            "balance_ever": Ever(balance).to_string(),
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Sends funds from the validator wallet
+#[argh(subcommand, name = "transfer")]
+struct CmdTransfer {
+    /// destination address
+    #[argh(positional)]
+    dst: AddressInput,
+
+    /// amount in EVER (e.g. `1.5`)
+    #[argh(positional)]
+    amount: Ever<u128>,
+
+    /// whether the message should bounce back on error
+    #[argh(switch)]
+    bounce: bool,
+
+    /// text comment
+    #[argh(option, short = 'c')]
+    comment: Option<String>,
+
+    /// base64 encoded payload BOC
+    #[argh(option, short = 'p')]
+    payload: Option<String>,
+}
+
+impl CmdTransfer {
+    async fn run(self, wallet: ValidatorWallet) -> Result<serde_json::Value> {
+        let payload = match (self.comment, self.payload) {
+            (Some(_), Some(_)) => anyhow::bail!("`--comment` and `--payload` are exclusive"),
+            (Some(comment), None) => make_comment_payload(&comment)?,
+            (None, Some(payload)) => {
+                let payload = parse_hex_or_base64(&payload).context("invalid payload")?;
+                ton_types::deserialize_tree_of_cells(&mut payload.as_slice())
+                    .context("invalid payload BOC")?
+            }
+            (None, None) => Default::default(),
+        };
+
+        let wallet = wallet.load().await?;
+
+        let balance = wallet.get_balance().await?.unwrap_or_default();
+        anyhow::ensure!(
+            balance >= self.amount.0,
+            "insufficient wallet balance ({} EVER)",
+            Ever(balance)
+        );
+
+        let tx = wallet
+            .send(
+                InternalMessage {
+                    dst: self.dst.0,
+                    amount: self.amount.0,
+                    payload,
+                },
+                self.bounce,
+            )
+            .await?;
+
+        Ok(serde_json::json!({
+            "tx_hash": tx.hash.to_hex_string(),
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Deploys the validator wallet
+#[argh(subcommand, name = "deploy")]
+struct CmdDeploy {}
+
+impl CmdDeploy {
+    async fn run(self, wallet: ValidatorWallet) -> Result<serde_json::Value> {
+        let wallet = wallet.load().await?;
+        let tx = wallet.deploy().await?;
+
+        Ok(serde_json::json!({
+            "address": wallet.address().to_string(),
+            "tx_hash": tx.map(|tx| tx.hash.to_hex_string()),
+        }))
+    }
+}
+
+#[derive(FromArgs)]
+/// Shows the latest validator wallet transactions
+#[argh(subcommand, name = "history")]
+struct CmdHistory {
+    /// max number of transactions
+    #[argh(option, short = 'l', default = "10")]
+    limit: usize,
+}
+
+impl CmdHistory {
+    async fn run(self, wallet: ValidatorWallet) -> Result<serde_json::Value> {
+        let transactions = wallet
+            .subscription
+            .get_account_transactions(&wallet.address, None, self.limit)
+            .await
+            .context("failed to get transactions")?
+            .transactions
+            .iter()
+            .map(|tx| describe_transaction(tx, None))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(serde_json::json!({
+            "address": wallet.address.to_string(),
+            "transactions": transactions,
+        }))
+    }
+}
+
+struct ValidatorWallet {
+    ctx: CliContext,
+    workchain_id: i8,
+    address: ton_block::MsgAddressInt,
+    subscription: Arc<Subscription>,
+}
+
+impl ValidatorWallet {
+    /// Loads validator keys and prepares the wallet for sending messages
+    async fn load(self) -> Result<Wallet> {
+        let signer = Signer::load(&self.ctx.dirs().validator_keys, self.subscription.tcp_rpc())
+            .context("failed to load validator wallet keys")?;
+
+        let wallet = Wallet::new(self.workchain_id, signer, self.subscription.clone());
+        anyhow::ensure!(
+            wallet.address() == &self.address,
+            "validator keys don't match the validator wallet address from the config"
+        );
+
+        self.subscription.ensure_ready().await?;
+        Ok(wallet)
+    }
+}
+
+/// Builds a simple text comment payload (zero function id and chained UTF-8 chunks)
+fn make_comment_payload(comment: &str) -> Result<ton_types::Cell> {
+    const CELL_BYTES: usize = 127;
+    const FIRST_CHUNK_BYTES: usize = CELL_BYTES - 4;
+
+    let comment = comment.as_bytes();
+    let (first, rest) = comment.split_at(std::cmp::min(comment.len(), FIRST_CHUNK_BYTES));
+
+    let mut child = None;
+    for chunk in rest.chunks(CELL_BYTES).rev() {
+        let mut builder = ton_types::BuilderData::new();
+        builder.append_raw(chunk, chunk.len() * 8)?;
+        if let Some(child) = child.take() {
+            builder.checked_append_reference(child)?;
+        }
+        child = Some(builder.into_cell()?);
+    }
+
+    let mut builder = ton_types::BuilderData::new();
+    builder.append_u32(0)?.append_raw(first, first.len() * 8)?;
+    if let Some(child) = child {
+        builder.checked_append_reference(child)?;
+    }
+    builder.into_cell()
+}
+
+#[derive(thiserror::Error, Debug)]
+enum WalletError {
+    #[error("validator is not configured")]
+    ValidatorNotConfigured,
+}
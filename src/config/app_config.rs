@@ -65,6 +65,10 @@ pub struct AppConfigControl {
     /// Control server query timeout
     #[serde(with = "serde_duration_ms", default = "const_duration_ms::<10000>")]
     pub query_timeout: Duration,
+
+    /// Control server keepalive interval (zero to disable pings)
+    #[serde(with = "serde_duration_ms", default = "const_duration_ms::<10000>")]
+    pub ping_interval: Duration,
 }
 
 impl AppConfigControl {
@@ -79,6 +83,7 @@ impl AppConfigControl {
             client_secret: client_key,
             connection_timeout: Duration::from_millis(2000),
             query_timeout: Duration::from_millis(10000),
+            ping_interval: Duration::from_millis(10000),
         }
     }
 }
@@ -149,6 +154,8 @@ pub struct AppConfigValidatorDePool {
     pub strategy: Option<ton_block::MsgAddressInt>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub deploy: Option<AppConfigDePoolDeploymentParams>,
+    #[serde(default)]
+    pub balances: AppConfigDePoolBalances,
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -160,10 +167,85 @@ pub struct AppConfigDePoolDeploymentParams {
     pub participant_reward_fraction: u8,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+/// DePool and proxies balance thresholds (in nano EVER)
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfigDePoolBalances {
+    /// DePool balance after replenishment
+    #[serde(with = "serde_string_or_number")]
+    pub initial_balance: u64,
+    /// DePool is replenished when its balance drops below this value
+    #[serde(with = "serde_string_or_number")]
+    pub critical_balance: u64,
+    /// Proxy balance after replenishment
+    #[serde(with = "serde_string_or_number")]
+    pub initial_proxy_balance: u64,
+    /// Proxy is replenished when its balance drops below this value
+    #[serde(with = "serde_string_or_number")]
+    pub min_proxy_balance: u64,
+}
+
+impl Default for AppConfigDePoolBalances {
+    fn default() -> Self {
+        Self {
+            initial_balance: 30_000_000_000,
+            critical_balance: 20_000_000_000,
+            initial_proxy_balance: 3_000_000_000,
+            min_proxy_balance: 2_000_000_000,
+        }
+    }
+}
+
+/// DePool contract version, custom versions are described in the DePool registry
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum DePoolType {
-    #[serde(rename = "default_v3")]
     DefaultV3,
-    #[serde(rename = "stever_v1")]
     StEver,
+    Custom(String),
+}
+
+impl DePoolType {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::DefaultV3 => "default_v3",
+            Self::StEver => "stever_v1",
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// Parses the type name without checking the DePool registry
+    pub fn from_name(name: String) -> Self {
+        match name.as_str() {
+            "default_v3" => Self::DefaultV3,
+            "stever_v1" => Self::StEver,
+            _ => Self::Custom(name),
+        }
+    }
+}
+
+impl TryFrom<String> for DePoolType {
+    type Error = anyhow::Error;
+
+    /// Parses the type name, custom versions must be present in the DePool registry
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let ty = Self::from_name(name);
+        ty.version()?;
+        Ok(ty)
+    }
+}
+
+impl From<DePoolType> for String {
+    fn from(ty: DePoolType) -> Self {
+        match ty {
+            DePoolType::Custom(name) => name,
+            ty => ty.name().to_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for DePoolType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
@@ -1,10 +1,13 @@
 pub use self::app_config::{
-    AppConfig, AppConfigAdnl, AppConfigControl, AppConfigDePoolDeploymentParams,
-    AppConfigValidator, AppConfigValidatorDePool, AppConfigValidatorSingle, DePoolType,
+    AppConfig, AppConfigAdnl, AppConfigControl, AppConfigDePoolBalances,
+    AppConfigDePoolDeploymentParams, AppConfigValidator, AppConfigValidatorDePool,
+    AppConfigValidatorSingle, DePoolType,
 };
 pub use self::global_config::GlobalConfig;
 pub use self::node_config::{NodeConfig, NodeConfigAdnl, NodeConfigControlServer, NodeLogConfig};
-pub use self::stored_keys::StoredKeys;
+pub use self::stored_keys::{
+    keys_passphrase, new_keys_passphrase, ExternalSignerKeys, NodeKeystoreKeys, StoredKeys,
+};
 
 mod app_config;
 mod global_config;
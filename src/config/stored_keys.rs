@@ -1,7 +1,12 @@
+use std::io::Read;
 use std::path::Path;
 
 use anyhow::{Context, Result};
 use broxus_util::{serde_hex_array, serde_optional_hex_array};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use hmac::{Mac, NewMac};
+use once_cell::sync::OnceCell;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use crate::crypto::*;
@@ -17,6 +22,13 @@ pub struct StoredKeys {
     pub public: Option<[u8; 32]>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub seed: Option<String>,
+    /// Derivation path of the bip39 seed (if it differs from the default one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Whether the keys were derived with a bip39 passphrase
+    /// (the seed alone is not enough to restore them in that case)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub passphrase: bool,
 }
 
 impl StoredKeys {
@@ -27,18 +39,45 @@ impl StoredKeys {
     }
 
     pub fn from_seed<T: AsRef<str>>(seed: T) -> Result<Self> {
-        fn inner(seed: &str) -> Result<StoredKeys> {
+        Self::from_seed_with_type(seed, Self::DEFAULT_MNEMONIC_TYPE)
+    }
+
+    pub fn from_seed_with_type<T: AsRef<str>>(
+        seed: T,
+        mnemonic_type: MnemonicType,
+    ) -> Result<Self> {
+        Self::from_seed_with_path(seed, mnemonic_type, DEFAULT_PATH, "")
+    }
+
+    /// Derives keys from the seed with the specified path and bip39 passphrase.
+    ///
+    /// NOTE: passphrase is not stored (only the marker that it was used),
+    /// so the secret is the only way to restore keys from the file in that case.
+    pub fn from_seed_with_path<T: AsRef<str>>(
+        seed: T,
+        mnemonic_type: MnemonicType,
+        path: &str,
+        passphrase: &str,
+    ) -> Result<Self> {
+        fn inner(
+            seed: &str,
+            mnemonic_type: MnemonicType,
+            path: &str,
+            passphrase: &str,
+        ) -> Result<StoredKeys> {
             let seed = seed.trim().to_owned();
-            let keypair =
-                derive_from_phrase(&seed, StoredKeys::DEFAULT_MNEMONIC_TYPE, DEFAULT_PATH)?;
+            let keypair = derive_from_phrase(&seed, mnemonic_type, path, passphrase)?;
             Ok(StoredKeys {
                 secret: keypair.secret.to_bytes(),
                 public: Some(keypair.public.to_bytes()),
                 seed: Some(seed),
+                path: (mnemonic_type == MnemonicType::Bip39 && path != DEFAULT_PATH)
+                    .then(|| path.to_owned()),
+                passphrase: !passphrase.is_empty(),
             })
         }
 
-        inner(seed.as_ref())
+        inner(seed.as_ref(), mnemonic_type, path, passphrase)
     }
 
     pub fn from_secret<T: AsRef<[u8]>>(secret: T) -> Result<Self> {
@@ -49,6 +88,8 @@ impl StoredKeys {
                 secret: secret.to_bytes(),
                 public: Some(public.to_bytes()),
                 seed: None,
+                path: None,
+                passphrase: false,
             })
         }
 
@@ -59,44 +100,76 @@ impl StoredKeys {
         Ok(Self::load(path)?.as_keypair())
     }
 
+    /// Loads keys from the file, decrypting them if needed
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         fn inner(path: &Path) -> Result<StoredKeys> {
-            #[derive(Deserialize)]
-            #[serde(deny_unknown_fields)]
-            pub struct StoredKeysHelper {
-                #[serde(with = "serde_optional_hex_array")]
-                pub secret: Option<[u8; 32]>,
-                #[serde(default, with = "serde_optional_hex_array")]
-                pub public: Option<[u8; 32]>,
-                #[serde(default)]
-                pub seed: Option<String>,
+            match StoredKeysFile::load(path)? {
+                StoredKeysFile::Plain(data) => data.try_into_keys(),
+                StoredKeysFile::Encrypted(data) => data.decrypt(keys_passphrase()?),
+                StoredKeysFile::NodeKeystore(_) => {
+                    Err(StoredKeysError::SecretInNodeKeystore.into())
+                }
+                StoredKeysFile::ExternalSigner(_) => {
+                    Err(StoredKeysError::SecretInExternalSigner.into())
+                }
             }
+        }
 
-            let file = std::fs::File::open(path).context("failed to open keys file")?;
-            let mut deserializer =
-                serde_json::Deserializer::from_reader(std::io::BufReader::new(file));
-            let data: StoredKeysHelper = serde_path_to_error::deserialize(&mut deserializer)
-                .context("failed to parse keys")?;
-
-            if let Some(secret) = data.secret {
-                Ok(StoredKeys {
-                    secret,
-                    public: data.public,
-                    seed: data.seed,
-                })
-            } else if let Some(seed) = data.seed {
-                StoredKeys::from_seed(seed)
-            } else {
-                anyhow::bail!("invalid keys file")
+        inner(path.as_ref())
+    }
+
+    /// Loads only the public key, without decrypting keys if possible
+    pub fn load_public<P: AsRef<Path>>(path: P) -> Result<ed25519_dalek::PublicKey> {
+        let path = path.as_ref();
+        match StoredKeysFile::load(path)? {
+            StoredKeysFile::Plain(data) => Ok(data.try_into_keys()?.as_keypair().public),
+            StoredKeysFile::Encrypted(EncryptedStoredKeys {
+                public: Some(public),
+                ..
+            }) => ed25519_dalek::PublicKey::from_bytes(&public).context("invalid public key"),
+            StoredKeysFile::Encrypted(_) => Ok(Self::load(path)?.as_keypair().public),
+            StoredKeysFile::NodeKeystore(NodeKeystoreKeys { public, .. })
+            | StoredKeysFile::ExternalSigner(ExternalSignerKeys { public, .. }) => {
+                ed25519_dalek::PublicKey::from_bytes(&public).context("invalid public key")
             }
         }
+    }
 
-        inner(path.as_ref())
+    /// Loads a reference to the key from the node keystore (if the file contains it)
+    pub fn load_node_keystore<P: AsRef<Path>>(path: P) -> Result<Option<NodeKeystoreKeys>> {
+        Ok(match StoredKeysFile::load(path.as_ref())? {
+            StoredKeysFile::NodeKeystore(data) => Some(data),
+            _ => None,
+        })
+    }
+
+    /// Loads an external signer reference (if the file contains it)
+    pub fn load_external_signer<P: AsRef<Path>>(path: P) -> Result<Option<ExternalSignerKeys>> {
+        Ok(match StoredKeysFile::load(path.as_ref())? {
+            StoredKeysFile::ExternalSigner(data) => Some(data),
+            _ => None,
+        })
+    }
+
+    /// Returns whether the keys file is encrypted
+    pub fn is_encrypted<P: AsRef<Path>>(path: P) -> Result<bool> {
+        Ok(matches!(
+            StoredKeysFile::load(path.as_ref())?,
+            StoredKeysFile::Encrypted(_)
+        ))
     }
 
+    /// Stores keys as a plain JSON
     pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let data = serde_json::to_string_pretty(self).context("failed to serialize keys")?;
-        std::fs::write(path, data).context("failed to save keys")
+        write_keys_file(path.as_ref(), &data).context("failed to save keys")
+    }
+
+    /// Stores keys encrypted with the passphrase
+    pub fn store_encrypted<P: AsRef<Path>>(&self, path: P, passphrase: &str) -> Result<()> {
+        let data = EncryptedStoredKeys::encrypt(self, passphrase)?;
+        let data = serde_json::to_string_pretty(&data).context("failed to serialize keys")?;
+        write_keys_file(path.as_ref(), &data).context("failed to save keys")
     }
 
     pub fn as_secret(&self) -> ed25519_dalek::SecretKey {
@@ -109,3 +182,378 @@ impl StoredKeys {
         ed25519_dalek::Keypair { secret, public }
     }
 }
+
+/// Reference to the key which is held in the node keystore.
+///
+/// Only the key hash and the public key are stored on disk,
+/// the secret never leaves the node.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeKeystoreKeys {
+    #[serde(with = "serde_hex_array")]
+    pub key_hash: [u8; 32],
+    #[serde(with = "serde_hex_array")]
+    pub public: [u8; 32],
+}
+
+impl NodeKeystoreKeys {
+    const KEY_HASH_FIELD: &str = "key_hash";
+
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let data = serde_json::to_string_pretty(self).context("failed to serialize keys")?;
+        write_keys_file(path.as_ref(), &data).context("failed to save keys")
+    }
+}
+
+/// Reference to the key which is held by the external signer.
+///
+/// Signer endpoint is either `unix:<path>` or `exec:<command>`.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalSignerKeys {
+    #[serde(with = "serde_hex_array")]
+    pub public: [u8; 32],
+    pub external_signer: String,
+}
+
+impl ExternalSignerKeys {
+    const EXTERNAL_SIGNER_FIELD: &str = "external_signer";
+
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let data = serde_json::to_string_pretty(self).context("failed to serialize keys")?;
+        write_keys_file(path.as_ref(), &data).context("failed to save keys")
+    }
+}
+
+/// Writes the keys file which is readable only by the owner
+fn write_keys_file(path: &Path, data: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .mode(0o600)
+        .open(path)?;
+    // Mode is only applied to the new files
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(data.as_bytes())
+}
+
+enum StoredKeysFile {
+    Plain(PlainStoredKeys),
+    Encrypted(EncryptedStoredKeys),
+    NodeKeystore(NodeKeystoreKeys),
+    ExternalSigner(ExternalSignerKeys),
+}
+
+impl StoredKeysFile {
+    fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).context("failed to open keys file")?;
+        let data: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))
+            .context("failed to parse keys")?;
+
+        // NOTE: parse as encrypted only when the field is present to keep errors readable
+        Ok(if data.get(EncryptedStoredKeys::CRYPTO_FIELD).is_some() {
+            Self::Encrypted(
+                serde_path_to_error::deserialize(data).context("failed to parse encrypted keys")?,
+            )
+        } else if data.get(NodeKeystoreKeys::KEY_HASH_FIELD).is_some() {
+            Self::NodeKeystore(
+                serde_path_to_error::deserialize(data)
+                    .context("failed to parse node keystore keys")?,
+            )
+        } else if data
+            .get(ExternalSignerKeys::EXTERNAL_SIGNER_FIELD)
+            .is_some()
+        {
+            Self::ExternalSigner(
+                serde_path_to_error::deserialize(data)
+                    .context("failed to parse external signer keys")?,
+            )
+        } else {
+            Self::Plain(serde_path_to_error::deserialize(data).context("failed to parse keys")?)
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlainStoredKeys {
+    #[serde(with = "serde_optional_hex_array")]
+    secret: Option<[u8; 32]>,
+    #[serde(default, with = "serde_optional_hex_array")]
+    public: Option<[u8; 32]>,
+    #[serde(default)]
+    seed: Option<String>,
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    passphrase: bool,
+}
+
+impl PlainStoredKeys {
+    fn try_into_keys(self) -> Result<StoredKeys> {
+        if let Some(secret) = self.secret {
+            Ok(StoredKeys {
+                secret,
+                public: self.public,
+                seed: self.seed,
+                path: self.path,
+                passphrase: self.passphrase,
+            })
+        } else if self.passphrase {
+            Err(StoredKeysError::SeedWithPassphrase.into())
+        } else if let Some(seed) = self.seed {
+            let path = self.path.as_deref().unwrap_or(DEFAULT_PATH);
+            StoredKeys::from_seed_with_path(seed, StoredKeys::DEFAULT_MNEMONIC_TYPE, path, "")
+        } else {
+            anyhow::bail!("invalid keys file")
+        }
+    }
+}
+
+/// Keys file encrypted with a passphrase.
+///
+/// Plain JSON of the keys is encrypted with AES-256-CTR. Cipher and MAC keys
+/// are derived from the passphrase with PBKDF2-HMAC-SHA512, MAC is
+/// HMAC-SHA256 over the IV and the ciphertext.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncryptedStoredKeys {
+    #[serde(
+        default,
+        with = "serde_optional_hex_array",
+        skip_serializing_if = "Option::is_none"
+    )]
+    public: Option<[u8; 32]>,
+    crypto: KeysCrypto,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeysCrypto {
+    version: u8,
+    iterations: u32,
+    #[serde(with = "serde_hex_array")]
+    salt: [u8; 32],
+    #[serde(with = "serde_hex_array")]
+    iv: [u8; 16],
+    ciphertext: String,
+    #[serde(with = "serde_hex_array")]
+    mac: [u8; 32],
+}
+
+impl EncryptedStoredKeys {
+    const CRYPTO_FIELD: &str = "crypto";
+    const VERSION: u8 = 1;
+    const PBKDF_ITERATIONS: u32 = 100_000;
+    /// Accepted range of the iterations count from the keys file
+    const PBKDF_ITERATIONS_RANGE: std::ops::RangeInclusive<u32> = 10_000..=10_000_000;
+
+    fn encrypt(keys: &StoredKeys, passphrase: &str) -> Result<Self> {
+        let rng = &mut rand::thread_rng();
+        let salt: [u8; 32] = rng.gen();
+        let iv: [u8; 16] = rng.gen();
+
+        let (cipher_key, mac_key) = derive_keys(passphrase, &salt, Self::PBKDF_ITERATIONS);
+
+        let mut data = serde_json::to_vec(keys).context("failed to serialize keys")?;
+        Aes256Ctr::new(
+            &generic_array::GenericArray::from(cipher_key),
+            &generic_array::GenericArray::from(iv),
+        )
+        .apply_keystream(&mut data);
+
+        let mac = compute_mac(&mac_key, &iv, &data)
+            .finalize()
+            .into_bytes()
+            .into();
+
+        Ok(Self {
+            public: keys.public,
+            crypto: KeysCrypto {
+                version: Self::VERSION,
+                iterations: Self::PBKDF_ITERATIONS,
+                salt,
+                iv,
+                ciphertext: hex::encode(data),
+                mac,
+            },
+        })
+    }
+
+    fn decrypt(self, passphrase: &str) -> Result<StoredKeys> {
+        let crypto = self.crypto;
+        if crypto.version != Self::VERSION {
+            return Err(StoredKeysError::UnsupportedVersion(crypto.version).into());
+        }
+        if !Self::PBKDF_ITERATIONS_RANGE.contains(&crypto.iterations) {
+            return Err(StoredKeysError::InvalidIterations(crypto.iterations).into());
+        }
+
+        let mut data = hex::decode(&crypto.ciphertext).context("invalid ciphertext")?;
+
+        let (cipher_key, mac_key) = derive_keys(passphrase, &crypto.salt, crypto.iterations);
+        compute_mac(&mac_key, &crypto.iv, &data)
+            .verify(&crypto.mac)
+            .map_err(|_| StoredKeysError::InvalidPassphrase)?;
+
+        Aes256Ctr::new(
+            &generic_array::GenericArray::from(cipher_key),
+            &generic_array::GenericArray::from(crypto.iv),
+        )
+        .apply_keystream(&mut data);
+
+        let keys: PlainStoredKeys =
+            serde_json::from_slice(&data).context("failed to parse decrypted keys")?;
+        keys.try_into_keys()
+    }
+}
+
+fn derive_keys(passphrase: &str, salt: &[u8; 32], iterations: u32) -> ([u8; 32], [u8; 32]) {
+    let mut res = [0; 64];
+    pbkdf2::pbkdf2::<hmac::Hmac<sha2::Sha512>>(passphrase.as_bytes(), salt, iterations, &mut res);
+
+    let mut cipher_key = [0; 32];
+    let mut mac_key = [0; 32];
+    cipher_key.copy_from_slice(&res[..32]);
+    mac_key.copy_from_slice(&res[32..]);
+    (cipher_key, mac_key)
+}
+
+fn compute_mac(key: &[u8; 32], iv: &[u8; 16], data: &[u8]) -> hmac::Hmac<sha2::Sha256> {
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(key).unwrap();
+    mac.update(iv);
+    mac.update(data);
+    mac
+}
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+/// Env with the passphrase for the encrypted keys
+pub const KEYS_PASSPHRASE_ENV: &str = "STEVER_KEYS_PASSPHRASE";
+/// Env with the path to the file with the passphrase for the encrypted keys
+pub const KEYS_PASSPHRASE_FILE_ENV: &str = "STEVER_KEYS_PASSPHRASE_FILE";
+/// Env with the file descriptor to read the passphrase for the encrypted keys from
+pub const KEYS_PASSPHRASE_FD_ENV: &str = "STEVER_KEYS_PASSPHRASE_FD";
+/// Name of the systemd credential with the passphrase for the encrypted keys
+pub const KEYS_PASSPHRASE_CREDENTIAL: &str = "stever-keys-passphrase";
+
+/// Returns the passphrase for the encrypted keys.
+///
+/// Falls back to an interactive prompt if no passphrase was provided
+/// for the process (see [`keys_passphrase_from_env`]). The passphrase
+/// is resolved only once and reused for all keys.
+pub fn keys_passphrase() -> Result<&'static str> {
+    static PASSPHRASE: OnceCell<String> = OnceCell::new();
+
+    PASSPHRASE
+        .get_or_try_init(|| {
+            if let Some(passphrase) = keys_passphrase_from_env()? {
+                return Ok(passphrase.to_owned());
+            }
+
+            if console::user_attended() {
+                return dialoguer::Password::new()
+                    .with_prompt("Keys passphrase")
+                    .interact()
+                    .context("failed to read passphrase");
+            }
+
+            Err(StoredKeysError::PassphraseNotFound.into())
+        })
+        .map(String::as_str)
+}
+
+/// Returns the passphrase to encrypt the keys with.
+///
+/// Uses the passphrase provided for the process or asks for the new one
+/// with confirmation. Empty passphrases are rejected.
+pub fn new_keys_passphrase() -> Result<String> {
+    let passphrase = match keys_passphrase_from_env()? {
+        Some(passphrase) => passphrase.to_owned(),
+        None if console::user_attended() => dialoguer::Password::new()
+            .with_prompt("New keys passphrase")
+            .with_confirmation("Confirm passphrase", "Passphrases mismatch")
+            .interact()
+            .context("failed to read passphrase")?,
+        None => return Err(StoredKeysError::PassphraseNotFound.into()),
+    };
+
+    if passphrase.is_empty() {
+        return Err(StoredKeysError::EmptyPassphrase.into());
+    }
+    Ok(passphrase)
+}
+
+/// Reads the passphrase for the encrypted keys provided for the process.
+///
+/// Sources are checked in the following order: file descriptor, file,
+/// systemd credential and env variable. Sources are read only once
+/// (the file descriptor is closed after reading).
+fn keys_passphrase_from_env() -> Result<Option<&'static str>> {
+    fn read_passphrase(mut reader: impl Read) -> Result<Option<String>> {
+        let mut passphrase = String::new();
+        reader
+            .read_to_string(&mut passphrase)
+            .context("failed to read passphrase")?;
+        Ok(Some(
+            passphrase.trim_end_matches(&['\n', '\r'][..]).to_owned(),
+        ))
+    }
+
+    fn read_env() -> Result<Option<String>> {
+        if let Ok(fd) = std::env::var(KEYS_PASSPHRASE_FD_ENV) {
+            use std::os::unix::io::FromRawFd;
+
+            let fd = fd.parse().context("invalid passphrase fd")?;
+            // SAFETY: fd is explicitly passed by the user and is not used anywhere else
+            let file = unsafe { std::fs::File::from_raw_fd(fd) };
+            return read_passphrase(file);
+        }
+
+        if let Ok(path) = std::env::var(KEYS_PASSPHRASE_FILE_ENV) {
+            let file = std::fs::File::open(path).context("failed to open passphrase file")?;
+            return read_passphrase(file);
+        }
+
+        if let Ok(dir) = std::env::var("CREDENTIALS_DIRECTORY") {
+            let path = Path::new(&dir).join(KEYS_PASSPHRASE_CREDENTIAL);
+            if path.exists() {
+                let file =
+                    std::fs::File::open(path).context("failed to open passphrase credential")?;
+                return read_passphrase(file);
+            }
+        }
+
+        Ok(std::env::var(KEYS_PASSPHRASE_ENV).ok())
+    }
+
+    static ENV_PASSPHRASE: OnceCell<Option<String>> = OnceCell::new();
+
+    ENV_PASSPHRASE
+        .get_or_try_init(read_env)
+        .map(Option::as_deref)
+}
+
+#[derive(thiserror::Error, Debug)]
+enum StoredKeysError {
+    #[error("unsupported encrypted keys version {0}")]
+    UnsupportedVersion(u8),
+    #[error("invalid PBKDF2 iterations count {0}")]
+    InvalidIterations(u32),
+    #[error("invalid passphrase")]
+    InvalidPassphrase,
+    #[error("empty passphrase")]
+    EmptyPassphrase,
+    #[error("passphrase for the encrypted keys not found")]
+    PassphraseNotFound,
+    #[error("keys were derived with a bip39 passphrase, secret key is required")]
+    SeedWithPassphrase,
+    #[error("secret key is held in the node keystore")]
+    SecretInNodeKeystore,
+    #[error("secret key is held by the external signer")]
+    SecretInExternalSigner,
+}
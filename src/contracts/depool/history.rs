@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use broxus_util::serde_string;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{CompletionReason, DePoolInfo, Round, RoundStep, RoundsMap};
+
+/// DePool rounds snapshots, collected by the validator manager each election.
+///
+/// DePool only keeps the four latest rounds, so this history is the only way
+/// to account rewards of the already removed rounds.
+#[derive(Serialize, Deserialize)]
+pub struct RoundsHistory {
+    #[serde(with = "serde_string")]
+    pub depool: ton_block::MsgAddressInt,
+    pub rounds: BTreeMap<u64, RoundSnapshot>,
+    /// Latest ticktocks sent by the validator manager
+    #[serde(default)]
+    pub ticktocks: Vec<TickTockAttempt>,
+}
+
+impl RoundsHistory {
+    pub fn new(depool: ton_block::MsgAddressInt) -> Self {
+        Self {
+            depool,
+            rounds: Default::default(),
+            ticktocks: Default::default(),
+        }
+    }
+
+    /// Returns the history file of the specified DePool in the directory
+    pub fn path<P: AsRef<Path>>(dir: P, depool: &ton_block::MsgAddressInt) -> PathBuf {
+        let address = hex::encode(depool.address().get_bytestring(0));
+        dir.as_ref()
+            .join(format!("{}_{address}.json", depool.workchain_id()))
+    }
+
+    /// Loads history for the specified DePool.
+    ///
+    /// Returns an empty history if the file doesn't exist. Fails if the file
+    /// was collected for another DePool, so that it is never overwritten.
+    pub fn load<P: AsRef<Path>>(path: P, depool: &ton_block::MsgAddressInt) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::new(depool.clone()));
+        }
+
+        let content = std::fs::read_to_string(path).context("failed to read rounds history")?;
+        let history: Self =
+            serde_json::from_str(&content).context("failed to deserialize rounds history")?;
+
+        anyhow::ensure!(
+            &history.depool == depool,
+            "rounds history {} belongs to another DePool {}",
+            path.display(),
+            history.depool
+        );
+        Ok(history)
+    }
+
+    /// Loads, modifies and stores the history file.
+    ///
+    /// Concurrent modifications within the process are serialized.
+    pub fn modify<P, F>(path: P, depool: &ton_block::MsgAddressInt, f: F) -> Result<()>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Self),
+    {
+        static LOCK: Mutex<()> = parking_lot::const_mutex(());
+        let _guard = LOCK.lock();
+
+        let path = path.as_ref();
+        let mut history = Self::load(path, depool)?;
+        f(&mut history);
+        history.store(path)
+    }
+
+    /// Stores the history atomically (through the temp file)
+    pub fn store<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let data = serde_json::to_string_pretty(self).context("failed to serialize history")?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("failed to create rounds history dir")?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, data).context("failed to save rounds history")?;
+        std::fs::rename(&temp_path, path).context("failed to replace rounds history")
+    }
+
+    /// Replaces snapshots of the specified rounds with the new ones
+    pub fn update(&mut self, rounds: &RoundsMap, info: &DePoolInfo, now: u32) {
+        for round in rounds.values() {
+            self.rounds
+                .insert(round.id, RoundSnapshot::new(round, info, now));
+        }
+    }
+
+    /// Appends the ticktock attempt, keeping only the latest ones
+    pub fn add_ticktock(&mut self, attempt: TickTockAttempt) {
+        const MAX_TICKTOCKS: usize = 100;
+
+        self.ticktocks.push(attempt);
+        if let Some(excess) = self.ticktocks.len().checked_sub(MAX_TICKTOCKS) {
+            self.ticktocks.drain(..excess);
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TickTockAttempt {
+    pub time: u32,
+    /// Rounds which were waiting for the ticktock
+    pub rounds: Vec<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoundSnapshot {
+    pub id: u64,
+    pub supposed_elected_at: u32,
+    pub unfreeze: u32,
+    pub step: RoundStep,
+    pub completion_reason: CompletionReason,
+    #[serde(with = "serde_string")]
+    pub stake: u64,
+    #[serde(with = "serde_string")]
+    pub recovered_stake: u64,
+    #[serde(with = "serde_string")]
+    pub validator_stake: u64,
+    #[serde(with = "serde_string")]
+    pub participant_reward: u64,
+    pub participant_qty: u32,
+    /// Participant reward fraction (in percent) at the moment of the snapshot
+    pub participant_reward_fraction: u8,
+    /// Snapshot timestamp
+    pub updated_at: u32,
+}
+
+impl RoundSnapshot {
+    pub fn new(round: &Round, info: &DePoolInfo, now: u32) -> Self {
+        Self {
+            id: round.id,
+            supposed_elected_at: round.supposed_elected_at,
+            unfreeze: round.unfreeze,
+            step: round.step,
+            completion_reason: round.completion_reason,
+            stake: round.stake,
+            recovered_stake: round.recovered_stake,
+            validator_stake: round.validator_stake,
+            participant_reward: round.participant_reward,
+            participant_qty: round.participant_qty,
+            participant_reward_fraction: info.participant_reward_fraction,
+            updated_at: now,
+        }
+    }
+
+    /// Total reward received from the elector
+    pub fn reward(&self) -> u64 {
+        if self.completion_reason == CompletionReason::RewardIsReceived {
+            self.recovered_stake.saturating_sub(self.stake)
+        } else {
+            0
+        }
+    }
+
+    /// Part of the reward which was not distributed between participants
+    pub fn validator_reward(&self) -> u64 {
+        self.reward().saturating_sub(self.participant_reward)
+    }
+
+    /// Validator share of the participants reward (proportional to its stake)
+    pub fn validator_stake_reward(&self) -> u64 {
+        if self.stake == 0 {
+            return 0;
+        }
+        (self.participant_reward as u128 * self.validator_stake as u128 / self.stake as u128) as u64
+    }
+
+    /// Participant reward fraction (in percent) which was actually applied
+    pub fn applied_reward_fraction(&self) -> Option<f64> {
+        match self.reward() {
+            0 => None,
+            reward => Some(self.participant_reward as f64 * 100.0 / reward as f64),
+        }
+    }
+}
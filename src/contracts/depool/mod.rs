@@ -2,19 +2,26 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
+use broxus_util::serde_string;
 use nekoton_abi::{
     BuildTokenValue, FunctionBuilder, FunctionExt, KnownParamType, KnownParamTypePlain,
     PackAbiPlain, TokenValueExt, UnpackAbi, UnpackAbiPlain, UnpackFirst,
 };
 use nekoton_utils::SimpleClock;
 use num::ToPrimitive;
+use serde::{Deserialize, Serialize};
 use ton_abi::contract::ABI_VERSION_2_2;
-use ton_block::{Deserializable, Serializable};
 
-use super::{InternalMessage, ONE_EVER};
-use crate::config::DePoolType;
+pub use self::history::{RoundSnapshot, RoundsHistory, TickTockAttempt};
+pub use self::registry::{DePoolCapabilities, DePoolRegistry, DePoolVersion};
+use super::signer::{SigningAction, SigningContext};
+use super::{InternalMessage, Signer, ONE_EVER};
+use crate::config::{AppConfigDePoolBalances, DePoolType};
 use crate::network::Subscription;
-use crate::util::make_default_headers;
+use crate::util::{make_default_headers, serde_address_list, serde_uint256, Ever};
+
+mod history;
+mod registry;
 
 #[derive(Debug, Clone)]
 pub struct DePoolInitParams {
@@ -26,16 +33,15 @@ pub struct DePoolInitParams {
 
 pub struct DePool {
     ty: DePoolType,
-    keypair: Option<ed25519_dalek::Keypair>,
+    signer: Option<Signer>,
     address: ton_block::MsgAddressInt,
-    subscription: Arc<Subscription>,
+    /// Node connection (not required to parse the DePool state)
+    subscription: Option<Arc<Subscription>>,
 }
 
 impl DePool {
-    pub const INITIAL_BALANCE: u128 = 30 * ONE_EVER;
-    pub const CRITICAL_BALANCE: u128 = 20 * ONE_EVER;
-    pub const INITIAL_PROXY_BALANCE: u128 = 3 * ONE_EVER;
-    pub const MIN_PROXY_BALANCE: u128 = 2 * ONE_EVER;
+    /// Attached to each participant request, the change is returned
+    pub const STAKE_FEE: u128 = ONE_EVER / 2;
 
     pub fn new(
         ty: DePoolType,
@@ -44,9 +50,19 @@ impl DePool {
     ) -> Self {
         Self {
             ty,
-            keypair: None,
+            signer: None,
+            address,
+            subscription: Some(subscription),
+        }
+    }
+
+    /// Creates the DePool without the node connection, it can only parse the provided states
+    pub fn offline(ty: DePoolType, address: ton_block::MsgAddressInt) -> Self {
+        Self {
+            ty,
+            signer: None,
             address,
-            subscription,
+            subscription: None,
         }
     }
 
@@ -54,19 +70,24 @@ impl DePool {
         &self.address
     }
 
-    pub fn set_keypair(&mut self, keypair: ed25519_dalek::Keypair) -> Result<()> {
-        let computed_address = self.ty.compute_depool_address(&keypair.public)?;
+    pub fn ty(&self) -> &DePoolType {
+        &self.ty
+    }
+
+    pub fn set_signer<S: Into<Signer>>(&mut self, signer: S) -> Result<()> {
+        let signer = signer.into();
+        let computed_address = self.ty.compute_depool_address(signer.public_key())?;
         anyhow::ensure!(
             computed_address == self.address,
             "wrong DePool address or keys"
         );
-        self.keypair = Some(keypair);
+        self.signer = Some(signer);
         Ok(())
     }
 
     pub async fn is_deployed(&self) -> Result<bool> {
         let Some(account) = self
-            .subscription
+            .subscription()?
             .get_account_state(&self.address)
             .await
             .context("failed to get DePool state")? else {
@@ -81,41 +102,55 @@ impl DePool {
     }
 
     pub async fn get_balance(&self) -> Result<Option<u128>> {
-        let account = self.subscription.get_account_state(&self.address).await?;
+        let account = self
+            .subscription()?
+            .get_account_state(&self.address)
+            .await?;
         Ok(account.map(|state| state.storage.balance.grams.0))
     }
 
     pub async fn deploy(&self, params: DePoolInitParams) -> Result<()> {
-        let keypair = self.keypair.as_ref().context("DePool keypair not set")?;
+        let signer = self.signer.as_ref().context("DePool signer not set")?;
 
         let inputs = ConstructorInputs {
             min_stake: params.min_stake,
             validator_assurance: params.validator_assurance,
             validator_wallet: params.owner,
-            proxy_code: self.ty.proxy_code().clone(),
+            proxy_code: self.ty.version()?.proxy_code.clone(),
             participant_reward_fraction: params.participant_reward_fraction,
         }
         .pack();
 
-        self.subscription
-            .send_message_with_retires(move |timeout| {
-                let (expire_at, header) = make_default_headers(None, timeout);
-
-                let mut message = self.external_message_to_self(
-                    common::constructor()
-                        .encode_input(
-                            &header,
-                            &inputs,
-                            false,
-                            Some(keypair),
-                            Some(self.address.clone()),
-                        )
-                        .context("failed to encode constructor")?,
-                );
-
-                message.set_state_init(self.ty.compute_depool_state_init(&keypair.public)?);
-
-                Ok((message, expire_at))
+        let context = SigningContext::new(
+            SigningAction::DePoolDeploy,
+            &self.address,
+            common::constructor(),
+        );
+
+        self.subscription()?
+            .send_message_with_retires(|timeout| {
+                let (inputs, context) = (&inputs, &context);
+                async move {
+                    let (expire_at, header) = make_default_headers(None, timeout);
+
+                    let mut message = self.external_message_to_self(
+                        signer
+                            .encode_input(
+                                common::constructor(),
+                                &header,
+                                inputs,
+                                &self.address,
+                                context,
+                            )
+                            .await
+                            .context("failed to encode constructor")?,
+                    );
+
+                    let version = self.ty.version()?;
+                    message.set_state_init(version.compute_depool_state_init(signer.public_key())?);
+
+                    Ok((message, expire_at))
+                }
             })
             .await
             .context("failed to send constructor message")?;
@@ -127,9 +162,12 @@ impl DePool {
         Ok(self.internal_message_to_self(ONE_EVER, common::ticktock().encode_internal_input(&[])?))
     }
 
-    pub async fn maintain_balances(&self) -> Result<Vec<InternalMessage>> {
+    pub async fn maintain_balances(
+        &self,
+        balances: &AppConfigDePoolBalances,
+    ) -> Result<Vec<InternalMessage>> {
         let account = self
-            .subscription
+            .subscription()?
             .get_account_state(&self.address)
             .await?
             .context("DePool not deployed")?;
@@ -139,20 +177,11 @@ impl DePool {
         let depool_info = self.get_info(&account)?;
 
         // Check depool balance
-        let depool_balance = {
-            let tokens = self.run_local(&account, common::get_depool_balance(), &[])?;
-            match tokens.into_iter().next() {
-                Some(ton_abi::Token {
-                    value: ton_abi::TokenValue::Int(ton_abi::Int { number, .. }),
-                    ..
-                }) => number,
-                _ => return Err(nekoton_abi::UnpackerError::InvalidAbi.into()),
-            }
-        };
+        let depool_balance = self.get_depool_balance(&account)?;
 
-        let critical_balance = num::BigInt::from(Self::CRITICAL_BALANCE);
+        let critical_balance = num::BigInt::from(balances.critical_balance);
         if depool_balance <= critical_balance {
-            let remaining = num::BigInt::from(Self::INITIAL_BALANCE) - depool_balance;
+            let remaining = num::BigInt::from(balances.initial_balance) - depool_balance;
             if let Some(remaining) = remaining.to_u128() {
                 messages.push(self.internal_message_to_self(
                     remaining,
@@ -164,7 +193,7 @@ impl DePool {
         // Check proxies
         for proxy in depool_info.proxies {
             let account = self
-                .subscription
+                .subscription()?
                 .get_account_state(&proxy)
                 .await
                 .context("failed to get proxy state")?
@@ -180,9 +209,11 @@ impl DePool {
                 }
             };
 
-            if proxy_balance <= Self::MIN_PROXY_BALANCE {
+            let min_proxy_balance = balances.min_proxy_balance as u128;
+            let initial_proxy_balance = balances.initial_proxy_balance as u128;
+            if proxy_balance <= min_proxy_balance && proxy_balance < initial_proxy_balance {
                 messages.push(InternalMessage {
-                    amount: Self::INITIAL_PROXY_BALANCE - proxy_balance,
+                    amount: initial_proxy_balance - proxy_balance,
                     dst: proxy,
                     payload: Default::default(),
                 });
@@ -194,17 +225,116 @@ impl DePool {
 
     pub fn add_ordinary_stake(&self, amount: u64) -> Result<InternalMessage> {
         Ok(self.internal_message_to_self(
-            (amount as u128) + ONE_EVER / 2,
+            (amount as u128) + Self::STAKE_FEE,
             &common::add_ordinary_stake()
                 .encode_internal_input(&[amount.token_value().named("stake")])?,
         ))
     }
 
+    pub fn add_vesting_stake(&self, params: ComplexStakeParams) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            (params.stake as u128) + Self::STAKE_FEE,
+            &common::add_vesting_stake().encode_internal_input(&params.pack())?,
+        ))
+    }
+
+    pub fn add_lock_stake(&self, params: ComplexStakeParams) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            (params.stake as u128) + Self::STAKE_FEE,
+            &common::add_lock_stake().encode_internal_input(&params.pack())?,
+        ))
+    }
+
+    /// Withdraws the ordinary stake from the pooling round
+    pub fn withdraw_from_pooling_round(&self, amount: u64) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            Self::STAKE_FEE,
+            &common::withdraw_from_pooling_round()
+                .encode_internal_input(&[amount.token_value().named("withdrawValue")])?,
+        ))
+    }
+
+    /// Requests the part of the ordinary stake to be returned after the round completion
+    pub fn withdraw_part(&self, amount: u64) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            Self::STAKE_FEE,
+            &common::withdraw_part()
+                .encode_internal_input(&[amount.token_value().named("withdrawValue")])?,
+        ))
+    }
+
+    /// Disables reinvest, the whole ordinary stake will be returned after the round completion
+    pub fn withdraw_all(&self) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            Self::STAKE_FEE,
+            &common::withdraw_all().encode_internal_input(&[])?,
+        ))
+    }
+
+    /// Cancels all withdrawal requests and enables reinvest
+    pub fn cancel_withdrawal(&self) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            Self::STAKE_FEE,
+            &common::cancel_withdrawal().encode_internal_input(&[])?,
+        ))
+    }
+
+    pub fn transfer_stake(
+        &self,
+        dest: &ton_block::MsgAddressInt,
+        amount: u64,
+    ) -> Result<InternalMessage> {
+        Ok(self.internal_message_to_self(
+            Self::STAKE_FEE,
+            &common::transfer_stake().encode_internal_input(&[
+                dest.clone().token_value().named("dest"),
+                amount.token_value().named("amount"),
+            ])?,
+        ))
+    }
+
+    /// Finds the DePool answer to the participant request in the DePool transaction
+    pub fn parse_answer(
+        &self,
+        tx: &ton_block::Transaction,
+        participant: &ton_block::MsgAddressInt,
+    ) -> Result<Option<DePoolAnswer>> {
+        let function = common::receive_answer();
+
+        let mut answer = None;
+        tx.out_msgs.iterate(|ton_block::InRefValue(msg)| {
+            let Some(header) = msg.int_header() else { return Ok(true) };
+            if &header.dst != participant {
+                return Ok(true);
+            }
+
+            let Some(mut body) = msg.body() else { return Ok(true) };
+            if body.get_next_u32().ok() != Some(function.input_id) {
+                return Ok(true);
+            }
+
+            let tokens = ton_abi::TokenValue::decode_params(
+                &function.inputs,
+                body,
+                &function.abi_version,
+                false,
+            )?;
+            answer = Some(tokens.unpack()?);
+            Ok(false)
+        })?;
+
+        Ok(answer)
+    }
+
+    /// Adds the participant to the stEVER DePool whitelist.
+    ///
+    /// NOTE: the contract accepts it only as an internal message from the validator wallet,
+    /// and there is no method to remove the participant.
     pub fn set_allowed_participant(
         &self,
         address: &ton_block::MsgAddressInt,
     ) -> Result<InternalMessage> {
-        self.ensure_stever()?;
+        self.ensure_allowed_participants()?;
         Ok(self.internal_message_to_self(
             ONE_EVER,
             stever_v1::set_allowed_participant()
@@ -219,11 +349,13 @@ impl DePool {
     ) -> Result<Option<ParticipantInfo>> {
         const ERR_NOT_PARTICIPANT: i32 = 116;
 
-        let result = match self.ty {
-            DePoolType::DefaultV3 => common::get_participant_info(),
-            DePoolType::StEver => stever_v1::get_participant_info(),
-        }
-        .run_local(
+        let function = if self.ty.version()?.capabilities.stever {
+            stever_v1::get_participant_info()
+        } else {
+            common::get_participant_info()
+        };
+
+        let result = function.run_local(
             &SimpleClock,
             state.clone(),
             &[addr.clone().token_value().named("addr")],
@@ -243,6 +375,28 @@ impl DePool {
         Ok(info)
     }
 
+    /// Returns the DePool balance without stakes (can be negative)
+    pub fn get_depool_balance(&self, state: &ton_block::AccountStuff) -> Result<num::BigInt> {
+        let tokens = self.run_local(state, common::get_depool_balance(), &[])?;
+        match tokens.into_iter().next() {
+            Some(ton_abi::Token {
+                value: ton_abi::TokenValue::Int(ton_abi::Int { number, .. }),
+                ..
+            }) => Ok(number),
+            _ => Err(nekoton_abi::UnpackerError::InvalidAbi.into()),
+        }
+    }
+
+    pub fn get_participants(
+        &self,
+        state: &ton_block::AccountStuff,
+    ) -> Result<Vec<ton_block::MsgAddressInt>> {
+        let participants = self
+            .run_local(state, common::get_participants(), &[])?
+            .unpack_first()?;
+        Ok(participants)
+    }
+
     pub fn get_rounds(&self, state: &ton_block::AccountStuff) -> Result<RoundsMap> {
         let rounds = self
             .run_local(state, common::get_rounds(), &[])?
@@ -254,7 +408,7 @@ impl DePool {
         &self,
         state: &ton_block::AccountStuff,
     ) -> Result<Vec<ton_block::MsgAddressInt>> {
-        self.ensure_stever()?;
+        self.ensure_allowed_participants()?;
         let addresses: stever_v1::ParticipantsMap = self
             .run_local(state, stever_v1::allowed_participants(), &[])?
             .unpack_first()?;
@@ -274,17 +428,24 @@ impl DePool {
     }
 
     pub async fn get_state(&self) -> Result<ton_block::AccountStuff> {
-        self.subscription
+        self.subscription()?
             .get_account_state(&self.address)
             .await?
             .context("DePool not deployed")
     }
 
-    fn ensure_stever(&self) -> Result<()> {
-        match self.ty {
-            DePoolType::DefaultV3 => anyhow::bail!("expected StEver depool"),
-            DePoolType::StEver => Ok(()),
-        }
+    fn subscription(&self) -> Result<&Arc<Subscription>> {
+        self.subscription
+            .as_ref()
+            .context("DePool is not connected to the node")
+    }
+
+    fn ensure_allowed_participants(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.ty.version()?.capabilities.allowed_participants,
+            "DePool doesn't support allowed participants"
+        );
+        Ok(())
     }
 
     fn external_message_to_self<T>(&self, body: T) -> ton_block::Message
@@ -312,62 +473,104 @@ impl DePool {
     }
 }
 
-impl DePoolType {
-    pub fn compute_depool_address(
-        &self,
-        pubkey: &ed25519_dalek::PublicKey,
-    ) -> Result<ton_block::MsgAddressInt> {
-        let state_init = self
-            .compute_depool_state_init(pubkey)
-            .context("failed to compute state init")?
-            .serialize()
-            .context("failed to serialize DePool state")?;
-        ton_block::MsgAddressInt::with_standart(None, 0, state_init.repr_hash().into())
-            .context("failed to compute DePool address")
-    }
-
-    fn compute_depool_state_init(
-        &self,
-        pubkey: &ed25519_dalek::PublicKey,
-    ) -> Result<ton_block::StateInit> {
-        let mut state_init = self.depool_tvc().clone();
-        if let Some(data) = state_init.data.take() {
-            let data = ton_abi::Contract::insert_pubkey(data.into(), pubkey.as_bytes())
-                .context("failed to insert pubkey")?;
-            state_init.data = Some(data.into_cell());
-        }
-        Ok(state_init)
-    }
+/// Returns warnings if balance thresholds are too low for the current gas prices
+pub fn check_balance_thresholds(
+    balances: &AppConfigDePoolBalances,
+    config: &ton_block::ConfigParams,
+) -> Result<Vec<String>> {
+    /// Max number of ticktocks sent to DePool during the round
+    const TICKTOCKS_PER_ROUND: u64 = 4;
+    /// Estimated gas usage of the single DePool ticktock (with all participants processing)
+    const TICKTOCK_GAS: u64 = 500_000;
+    /// Estimated gas usage of all proxy transactions during the round
+    const PROXY_GAS_PER_ROUND: u64 = 100_000;
+
+    let mut warnings = Vec::new();
+
+    // DePool is in the basechain
+    let depool_fee = compute_gas_fee(config, false, TICKTOCKS_PER_ROUND * TICKTOCK_GAS)?;
+    if (balances.critical_balance as u128) < depool_fee {
+        warnings.push(format!(
+            "DePool critical balance is too low to cover round ticktocks ({} EVER required)",
+            Ever(depool_fee)
+        ));
+    }
+
+    // Proxies are in the masterchain
+    let proxy_fee = compute_gas_fee(config, true, PROXY_GAS_PER_ROUND)?;
+    if (balances.min_proxy_balance as u128) < proxy_fee {
+        warnings.push(format!(
+            "min proxy balance is too low to cover round fees ({} EVER required)",
+            Ever(proxy_fee)
+        ));
+    }
+
+    Ok(warnings)
 }
 
-macro_rules! impl_getters(
-    ($t:ident, $tvc:ident, $proxy:ident, { $($var:ident => ($tvc_path:literal, $proxy_path:literal)),*$(,)? }) => {
-        impl $t {
-            pub fn $tvc(&self) -> &'static ton_block::StateInit {
-                match self {
-                    $(Self::$var => once!(ton_block::StateInit, || {
-                        let bytes = include_bytes!($tvc_path);
-                        ton_block::StateInit::construct_from_bytes(bytes).unwrap()
-                    })),*,
-                }
-            }
+fn compute_gas_fee(config: &ton_block::ConfigParams, masterchain: bool, gas: u64) -> Result<u128> {
+    let param = if masterchain { 20 } else { 21 };
+    let prices = match config.config(param)? {
+        Some(ton_block::ConfigParamEnum::ConfigParam20(prices)) if masterchain => prices,
+        Some(ton_block::ConfigParamEnum::ConfigParam21(prices)) if !masterchain => prices,
+        _ => anyhow::bail!("gas prices config param {param} not found"),
+    };
 
-            pub fn $proxy(&self) -> &'static ton_types::Cell {
-                match self {
-                    $(Self::$var => once!(ton_types::Cell, || {
-                        let mut code = include_bytes!($proxy_path).as_ref();
-                        ton_types::deserialize_tree_of_cells(&mut code).unwrap()
-                    })),*,
-                }
-            }
-        }
+    let flat_fee = prices.flat_gas_price as u128;
+    let fee = match gas.checked_sub(prices.flat_gas_limit) {
+        Some(gas) if gas > 0 => flat_fee + ((gas as u128 * prices.gas_price as u128) >> 16),
+        _ => flat_fee,
     };
-);
+    Ok(fee)
+}
 
-impl_getters!(DePoolType, depool_tvc, proxy_code, {
-    DefaultV3 => ("./v3/DePool.tvc", "./v3/DePoolProxy.code"),
-    StEver => ("./stever/DePool.tvc", "./stever/DePoolProxy.code"),
-});
+/// Checks that the contract ABI is compatible with all methods used for the specified capabilities.
+///
+/// The ABI itself is not used to encode calls, so custom versions must keep
+/// the built-in DePool interface.
+fn check_abi(abi: &ton_abi::Contract, capabilities: &DePoolCapabilities) -> Result<()> {
+    let mut functions = vec![
+        common::constructor(),
+        common::add_ordinary_stake(),
+        common::add_vesting_stake(),
+        common::add_lock_stake(),
+        common::withdraw_from_pooling_round(),
+        common::withdraw_part(),
+        common::withdraw_all(),
+        common::cancel_withdrawal(),
+        common::transfer_stake(),
+        common::ticktock(),
+        common::receive_funds(),
+        common::get_depool_balance(),
+        common::get_depool_info(),
+        common::get_participants(),
+        common::get_rounds(),
+    ];
+    if capabilities.stever {
+        functions.push(stever_v1::get_participant_info());
+    } else {
+        functions.push(common::get_participant_info());
+    }
+    if capabilities.allowed_participants {
+        functions.extend([
+            stever_v1::set_allowed_participant(),
+            stever_v1::allowed_participants(),
+        ]);
+    }
+
+    for function in functions {
+        let name = &function.name;
+        let abi_function = abi
+            .function(name)
+            .map_err(|_| anyhow::anyhow!("function `{name}` not found in ABI"))?;
+        anyhow::ensure!(
+            abi_function.input_id == function.input_id,
+            "function `{name}` signature mismatch"
+        );
+    }
+
+    Ok(())
+}
 
 #[derive(Clone, PackAbiPlain, KnownParamTypePlain)]
 struct ConstructorInputs {
@@ -383,7 +586,30 @@ struct ConstructorInputs {
     participant_reward_fraction: u8,
 }
 
-#[derive(Debug, Clone, UnpackAbiPlain, KnownParamTypePlain)]
+#[derive(Debug, Clone, PackAbiPlain, KnownParamTypePlain)]
+pub struct ComplexStakeParams {
+    #[abi(uint64)]
+    pub stake: u64,
+    #[abi(address)]
+    pub beneficiary: ton_block::MsgAddressInt,
+    #[abi(uint32)]
+    pub withdrawal_period: u32,
+    #[abi(uint32)]
+    pub total_period: u32,
+}
+
+/// Result of the participant request
+#[derive(Debug, Copy, Clone, Serialize, UnpackAbiPlain, KnownParamTypePlain)]
+pub struct DePoolAnswer {
+    /// Zero on success
+    #[abi(uint32)]
+    pub errcode: u32,
+    /// Additional value (e.g. min stake for `STAKE_TOO_SMALL`)
+    #[abi(uint64)]
+    pub comment: u64,
+}
+
+#[derive(Debug, Clone, Serialize, UnpackAbiPlain, KnownParamTypePlain)]
 pub struct ParticipantInfo {
     #[abi(uint64)]
     pub total: u64,
@@ -400,8 +626,10 @@ pub struct ParticipantInfo {
     #[abi]
     pub locks: BTreeMap<u64, ComplexStake>,
     #[abi(address)]
+    #[serde(with = "serde_string")]
     pub vesting_donor: ton_block::MsgAddressInt,
     #[abi(address)]
+    #[serde(with = "serde_string")]
     pub lock_donor: ton_block::MsgAddressInt,
 }
 
@@ -421,7 +649,7 @@ impl ParticipantInfo {
     }
 }
 
-#[derive(Debug, Clone, UnpackAbi, KnownParamType)]
+#[derive(Debug, Clone, Serialize, UnpackAbi, KnownParamType)]
 pub struct ComplexStake {
     #[abi(uint64)]
     pub remaining_amount: u64,
@@ -432,10 +660,11 @@ pub struct ComplexStake {
     #[abi(uint64)]
     pub withdrawal_value: u64,
     #[abi(address)]
+    #[serde(with = "serde_string")]
     pub owner: ton_block::MsgAddressInt,
 }
 
-#[derive(Clone, UnpackAbiPlain, KnownParamTypePlain)]
+#[derive(Clone, Serialize, UnpackAbiPlain, KnownParamTypePlain)]
 pub struct DePoolInfo {
     #[abi(bool)]
     pub pool_closed: bool,
@@ -450,8 +679,10 @@ pub struct DePoolInfo {
     #[abi(uint64)]
     pub balance_threshold: u64,
     #[abi(address)]
+    #[serde(with = "serde_string")]
     pub validator_wallet: ton_block::MsgAddressInt,
     #[abi(array)]
+    #[serde(with = "serde_address_list")]
     pub proxies: Vec<ton_block::MsgAddressInt>,
     #[abi(uint64)]
     pub stake_fee: u64,
@@ -461,7 +692,7 @@ pub struct DePoolInfo {
     pub proxy_fee: u64,
 }
 
-#[derive(Debug, UnpackAbi, KnownParamType)]
+#[derive(Debug, Serialize, UnpackAbi, KnownParamType)]
 pub struct Round {
     #[abi(uint64)]
     pub id: u64,
@@ -472,6 +703,7 @@ pub struct Round {
     #[abi(uint32)]
     pub stake_held_for: u32,
     #[abi(uint256)]
+    #[serde(with = "serde_uint256")]
     pub vset_hash_in_election_phase: ton_types::UInt256,
     #[abi]
     pub step: RoundStep,
@@ -497,9 +729,32 @@ pub struct Round {
     pub handled_stakes_and_rewards: u64,
 }
 
+impl Round {
+    /// Whether the round can only proceed after the next ticktock
+    pub fn is_waiting_for_ticktock(&self, now: u32, offset: u32) -> bool {
+        match self.step {
+            // Validation has started, DePool should check whether it won the elections
+            RoundStep::WaitingValidationStart => {
+                self.supposed_elected_at != 0
+                    && now > self.supposed_elected_at.saturating_add(offset)
+            }
+            // Stake is unfrozen, DePool should recover it from the elector
+            RoundStep::WaitingUnfreeze => {
+                self.completion_reason == CompletionReason::Undefined
+                    && self.unfreeze != 0
+                    && now > self.unfreeze.saturating_add(offset)
+            }
+            // Participant stakes are returned in batches on each ticktock
+            RoundStep::Completing => true,
+            _ => false,
+        }
+    }
+}
+
 pub type RoundsMap = BTreeMap<u64, Round>;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, UnpackAbi, KnownParamType)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, UnpackAbi, KnownParamType)]
+#[serde(rename_all = "snake_case")]
 pub enum RoundStep {
     /// Receiving a half of vesting/lock stake from participants
     PrePooling = 0,
@@ -528,7 +783,8 @@ pub enum RoundStep {
     Completed = 9,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, UnpackAbi, KnownParamType)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, UnpackAbi, KnownParamType)]
+#[serde(rename_all = "snake_case")]
 pub enum CompletionReason {
     Undefined = 0,
     PoolClosed = 1,
@@ -562,6 +818,69 @@ mod common {
         })
     }
 
+    pub fn add_vesting_stake() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("addVestingStake")
+                .inputs(ComplexStakeParams::param_type())
+                .build()
+        })
+    }
+
+    pub fn add_lock_stake() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("addLockStake")
+                .inputs(ComplexStakeParams::param_type())
+                .build()
+        })
+    }
+
+    pub fn withdraw_from_pooling_round() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("withdrawFromPoolingRound")
+                .input("withdrawValue", u64::param_type())
+                .build()
+        })
+    }
+
+    pub fn withdraw_part() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("withdrawPart")
+                .input("withdrawValue", u64::param_type())
+                .build()
+        })
+    }
+
+    pub fn withdraw_all() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("withdrawAll").build()
+        })
+    }
+
+    pub fn cancel_withdrawal() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("cancelWithdrawal").build()
+        })
+    }
+
+    pub fn transfer_stake() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("transferStake")
+                .input("dest", ton_block::MsgAddressInt::param_type())
+                .input("amount", u64::param_type())
+                .build()
+        })
+    }
+
+    /// Sent by DePool to the participant as a response to each request
+    pub fn receive_answer() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("receiveAnswer")
+                .input("errcode", u32::param_type())
+                .input("comment", u64::param_type())
+                .build()
+        })
+    }
+
     pub fn ticktock() -> &'static ton_abi::Function {
         once!(ton_abi::Function, || {
             FunctionBuilder::new("ticktock").build()
@@ -607,6 +926,19 @@ mod common {
         })
     }
 
+    pub fn get_participants() -> &'static ton_abi::Function {
+        once!(ton_abi::Function, || {
+            FunctionBuilder::new("getParticipants")
+                .time_header()
+                .expire_header()
+                .output(
+                    "participants",
+                    ton_abi::ParamType::Array(Box::new(ton_abi::ParamType::Address)),
+                )
+                .build()
+        })
+    }
+
     pub fn get_rounds() -> &'static ton_abi::Function {
         once!(ton_abi::Function, || {
             FunctionBuilder::new("getRounds")
@@ -0,0 +1,241 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use ton_block::{Deserializable, Serializable};
+
+use crate::config::DePoolType;
+use crate::util::parse_contract_abi;
+
+/// Known DePool versions.
+///
+/// Built-in versions are always available, custom versions are loaded
+/// from the registry directory (see [`DePoolRegistry::load`]).
+pub struct DePoolRegistry {
+    versions: Vec<DePoolVersion>,
+}
+
+impl DePoolRegistry {
+    pub const TVC_FILE: &'static str = "DePool.tvc";
+    pub const PROXY_CODE_FILE: &'static str = "DePoolProxy.code";
+    pub const ABI_FILE: &'static str = "DePool.abi.json";
+    pub const CAPABILITIES_FILE: &'static str = "capabilities.toml";
+
+    /// Loads the global registry from the specified directory.
+    ///
+    /// Does nothing if the registry was already initialized.
+    pub fn init<P: AsRef<Path>>(dir: P) -> Result<()> {
+        let registry = Self::load(dir)?;
+        REGISTRY.get_or_init(|| registry);
+        Ok(())
+    }
+
+    /// Returns the global registry (with only built-in versions if it was not initialized)
+    pub fn get() -> &'static Self {
+        REGISTRY.get_or_init(Self::builtin)
+    }
+
+    pub fn builtin() -> Self {
+        Self {
+            versions: vec![
+                DePoolVersion::builtin(
+                    DePoolType::StEver,
+                    include_bytes!("./stever/DePool.tvc"),
+                    include_bytes!("./stever/DePoolProxy.code"),
+                    DePoolCapabilities {
+                        stever: true,
+                        allowed_participants: true,
+                    },
+                ),
+                DePoolVersion::builtin(
+                    DePoolType::DefaultV3,
+                    include_bytes!("./v3/DePool.tvc"),
+                    include_bytes!("./v3/DePoolProxy.code"),
+                    DePoolCapabilities::default(),
+                ),
+            ],
+        }
+    }
+
+    /// Loads built-in versions and all custom versions from the directory.
+    ///
+    /// Each custom version is a subdirectory (its name is used as the DePool type)
+    /// with `DePool.tvc`, `DePoolProxy.code`, `DePool.abi.json` and `capabilities.toml`.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+
+        let mut registry = Self::builtin();
+        if !dir.exists() {
+            return Ok(registry);
+        }
+
+        let mut entries = std::fs::read_dir(dir)
+            .context("failed to read DePool registry directory")?
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| anyhow::anyhow!("invalid DePool version name"))?;
+
+            let ty = DePoolType::from_name(name.clone());
+            anyhow::ensure!(
+                matches!(ty, DePoolType::Custom(_)),
+                "built-in DePool version `{name}` can't be overwritten"
+            );
+
+            let version = DePoolVersion::load(ty, entry.path())
+                .with_context(|| format!("failed to load DePool version `{name}`"))?;
+            registry.versions.push(version);
+        }
+
+        Ok(registry)
+    }
+
+    pub fn versions(&self) -> impl Iterator<Item = &DePoolVersion> {
+        self.versions.iter()
+    }
+
+    pub fn version(&self, ty: &DePoolType) -> Result<&DePoolVersion> {
+        self.versions
+            .iter()
+            .find(|version| &version.ty == ty)
+            .ok_or_else(|| DePoolRegistryError::UnknownVersion(ty.to_string()).into())
+    }
+
+    /// Finds the version of the deployed DePool by its code hash
+    pub fn version_by_code_hash(&self, code_hash: &ton_types::UInt256) -> Option<&DePoolVersion> {
+        self.versions
+            .iter()
+            .find(|version| match &version.tvc.code {
+                Some(code) => &code.repr_hash() == code_hash,
+                None => false,
+            })
+    }
+}
+
+static REGISTRY: OnceCell<DePoolRegistry> = OnceCell::new();
+
+/// DePool contract description
+pub struct DePoolVersion {
+    pub ty: DePoolType,
+    pub tvc: ton_block::StateInit,
+    pub proxy_code: ton_types::Cell,
+    /// Contract ABI (only for the custom versions).
+    ///
+    /// It is only checked for compatibility on load, all calls are encoded
+    /// with the built-in interface selected by the capabilities. So custom versions
+    /// must keep the built-in signatures of all used methods.
+    pub abi: Option<ton_abi::Contract>,
+    pub capabilities: DePoolCapabilities,
+}
+
+impl DePoolVersion {
+    fn builtin(
+        ty: DePoolType,
+        tvc: &[u8],
+        mut proxy_code: &[u8],
+        capabilities: DePoolCapabilities,
+    ) -> Self {
+        Self {
+            ty,
+            tvc: ton_block::StateInit::construct_from_bytes(tvc).unwrap(),
+            proxy_code: ton_types::deserialize_tree_of_cells(&mut proxy_code).unwrap(),
+            abi: None,
+            capabilities,
+        }
+    }
+
+    fn load<P: AsRef<Path>>(ty: DePoolType, dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+
+        let tvc =
+            std::fs::read(dir.join(DePoolRegistry::TVC_FILE)).context("failed to read TVC")?;
+        let tvc = ton_block::StateInit::construct_from_bytes(&tvc).context("invalid TVC")?;
+
+        let proxy_code = std::fs::read(dir.join(DePoolRegistry::PROXY_CODE_FILE))
+            .context("failed to read proxy code")?;
+        let proxy_code = ton_types::deserialize_tree_of_cells(&mut proxy_code.as_slice())
+            .context("invalid proxy code")?;
+
+        let abi = parse_contract_abi(dir.join(DePoolRegistry::ABI_FILE))?;
+
+        let capabilities = std::fs::read_to_string(dir.join(DePoolRegistry::CAPABILITIES_FILE))
+            .context("failed to read capabilities")?;
+        let capabilities: DePoolCapabilities =
+            toml::from_str(&capabilities).context("failed to parse capabilities")?;
+
+        // Make sure that all used methods are compatible with the specified ABI
+        super::check_abi(&abi, &capabilities)?;
+
+        Ok(Self {
+            ty,
+            tvc,
+            proxy_code,
+            abi: Some(abi),
+            capabilities,
+        })
+    }
+
+    pub fn compute_depool_address(
+        &self,
+        pubkey: &ed25519_dalek::PublicKey,
+    ) -> Result<ton_block::MsgAddressInt> {
+        let state_init = self
+            .compute_depool_state_init(pubkey)
+            .context("failed to compute state init")?
+            .serialize()
+            .context("failed to serialize DePool state")?;
+        ton_block::MsgAddressInt::with_standart(None, 0, state_init.repr_hash().into())
+            .context("failed to compute DePool address")
+    }
+
+    pub fn compute_depool_state_init(
+        &self,
+        pubkey: &ed25519_dalek::PublicKey,
+    ) -> Result<ton_block::StateInit> {
+        let mut state_init = self.tvc.clone();
+        if let Some(data) = state_init.data.take() {
+            let data = ton_abi::Contract::insert_pubkey(data.into(), pubkey.as_bytes())
+                .context("failed to insert pubkey")?;
+            state_init.data = Some(data.into_cell());
+        }
+        Ok(state_init)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DePoolCapabilities {
+    /// Uses stEVER DePool interface (extended participant info, strategies)
+    pub stever: bool,
+    /// Supports allowed participants list
+    pub allowed_participants: bool,
+}
+
+impl DePoolType {
+    /// Finds the DePool version description in the global registry
+    pub fn version(&self) -> Result<&'static DePoolVersion> {
+        DePoolRegistry::get().version(self)
+    }
+
+    pub fn compute_depool_address(
+        &self,
+        pubkey: &ed25519_dalek::PublicKey,
+    ) -> Result<ton_block::MsgAddressInt> {
+        self.version()?.compute_depool_address(pubkey)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum DePoolRegistryError {
+    #[error("unknown DePool version `{0}` (see `stever depool versions`)")]
+    UnknownVersion(String),
+}
@@ -1,11 +1,13 @@
 pub use depool::DePool;
 pub use elector::Elector;
+pub use signer::Signer;
 pub use strategy::Strategy;
 pub use strategy_factory::StrategyFactory;
 pub use wallet::Wallet;
 
 pub mod depool;
 pub mod elector;
+pub mod signer;
 pub mod strategy;
 pub mod strategy_factory;
 pub mod wallet;
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::process::Command;
+
+use super::SigningContext;
+
+/// Signer which delegates signing to the external process.
+///
+/// Each request is a single JSON line with the data to sign and its context,
+/// the response is a single JSON line with either a signature or an error.
+pub struct ExternalSigner {
+    endpoint: ExternalSignerEndpoint,
+    public: ed25519_dalek::PublicKey,
+}
+
+impl ExternalSigner {
+    pub const PROTOCOL_VERSION: u8 = 1;
+    /// Max time to wait for the signer response (including the manual approval)
+    pub const TIMEOUT: Duration = Duration::from_secs(120);
+    /// Max signer response length in bytes
+    pub const MAX_RESPONSE_LEN: usize = 64 << 10;
+
+    pub fn new(endpoint: ExternalSignerEndpoint, public: ed25519_dalek::PublicKey) -> Self {
+        Self { endpoint, public }
+    }
+
+    pub fn endpoint(&self) -> &ExternalSignerEndpoint {
+        &self.endpoint
+    }
+
+    pub fn public_key(&self) -> &ed25519_dalek::PublicKey {
+        &self.public
+    }
+
+    pub async fn sign(&self, data: &[u8], context: &SigningContext) -> Result<[u8; 64]> {
+        let mut request = serde_json::to_vec(&Request {
+            version: Self::PROTOCOL_VERSION,
+            public: hex::encode(self.public.as_bytes()),
+            data: hex::encode(data),
+            context,
+        })
+        .context("failed to serialize signer request")?;
+        request.push(b'\n');
+
+        let response = tokio::time::timeout(Self::TIMEOUT, self.exchange(&request))
+            .await
+            .map_err(|_| ExternalSignerError::Timeout)??;
+        if response.len() > Self::MAX_RESPONSE_LEN {
+            return Err(ExternalSignerError::ResponseTooLong.into());
+        }
+
+        let response: Response =
+            serde_json::from_slice(&response).context("invalid signer response")?;
+        match response {
+            Response {
+                signature: Some(signature),
+                ..
+            } => {
+                let mut result = [0; 64];
+                hex::decode_to_slice(signature, &mut result).context("invalid signature")?;
+                Ok(result)
+            }
+            Response {
+                error: Some(error), ..
+            } => Err(ExternalSignerError::Rejected(error).into()),
+            _ => Err(ExternalSignerError::EmptyResponse.into()),
+        }
+    }
+
+    /// Sends the request line and reads the response line
+    async fn exchange(&self, request: &[u8]) -> Result<Vec<u8>> {
+        Ok(match &self.endpoint {
+            ExternalSignerEndpoint::Unix(path) => {
+                let mut stream = UnixStream::connect(path)
+                    .await
+                    .with_context(|| format!("failed to connect to {}", path.display()))?;
+
+                stream.write_all(request).await?;
+                stream.flush().await?;
+
+                // Read at most one byte more than allowed to detect too long responses
+                let mut stream = BufReader::new(stream).take(Self::MAX_RESPONSE_LEN as u64 + 1);
+                let mut response = Vec::new();
+                stream.read_until(b'\n', &mut response).await?;
+                response
+            }
+            ExternalSignerEndpoint::Exec(command) => {
+                let (program, args) = command.split_first().context("empty signer command")?;
+                let mut child = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .with_context(|| format!("failed to spawn `{program}`"))?;
+
+                let mut stdin = child.stdin.take().context("signer stdin is not piped")?;
+                stdin.write_all(request).await?;
+                drop(stdin);
+
+                let output = child
+                    .wait_with_output()
+                    .await
+                    .context("signer process encountered an error")?;
+
+                // Signer may exit with an error code after rejecting the request
+                let rejected = matches!(
+                    serde_json::from_slice::<Response>(&output.stdout),
+                    Ok(Response { error: Some(_), .. })
+                );
+                anyhow::ensure!(
+                    output.status.success() || rejected,
+                    "signer process failed with exit code {}",
+                    output.status
+                );
+                output.stdout
+            }
+        })
+    }
+}
+
+/// External signer address
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExternalSignerEndpoint {
+    /// Signer is listening on the Unix socket (`unix:/path/to/socket`)
+    Unix(PathBuf),
+    /// Signer is spawned for each request and uses stdin/stdout (`exec:command args...`).
+    ///
+    /// Arguments are separated by whitespace, quoting is not supported
+    Exec(Vec<String>),
+}
+
+impl std::fmt::Display for ExternalSignerEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+            Self::Exec(command) => write!(f, "exec:{}", command.join(" ")),
+        }
+    }
+}
+
+impl FromStr for ExternalSignerEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            anyhow::ensure!(!path.is_empty(), "empty signer socket path");
+            Ok(Self::Unix(PathBuf::from(path)))
+        } else if let Some(command) = s.strip_prefix("exec:") {
+            let command = command
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            anyhow::ensure!(!command.is_empty(), "empty signer command");
+            Ok(Self::Exec(command))
+        } else {
+            Err(ExternalSignerError::UnknownEndpoint.into())
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    version: u8,
+    public: String,
+    data: String,
+    context: &'a SigningContext,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    signature: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum ExternalSignerError {
+    #[error("unknown signer endpoint (expected `unix:<path>` or `exec:<command>`)")]
+    UnknownEndpoint,
+    #[error("signing rejected: {0}")]
+    Rejected(String),
+    #[error("signer response contains neither signature nor error")]
+    EmptyResponse,
+    #[error("signer response timeout")]
+    Timeout,
+    #[error("signer response is too long")]
+    ResponseTooLong,
+}
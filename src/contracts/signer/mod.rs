@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use broxus_util::{serde_optional_string, serde_string};
+use ed25519_dalek::Signer as _;
+use serde::Serialize;
+
+pub use self::external::{ExternalSigner, ExternalSignerEndpoint};
+use crate::config::StoredKeys;
+use crate::network::NodeTcpRpc;
+
+mod external;
+
+/// Signs external messages
+pub enum Signer {
+    /// Keypair loaded into memory
+    Keypair(ed25519_dalek::Keypair),
+    /// Key held in the node keystore, messages are signed via the control server
+    NodeKeystore {
+        node_tcp_rpc: NodeTcpRpc,
+        key_hash: [u8; 32],
+        public: ed25519_dalek::PublicKey,
+    },
+    /// Key held by the external process, which can approve or reject each message
+    External(ExternalSigner),
+}
+
+impl Signer {
+    /// Loads signer from the keys file.
+    ///
+    /// Files which only reference the node keystore key are signed
+    /// through the specified node control client, files with the
+    /// external signer endpoint are signed by that signer.
+    pub fn load<P: AsRef<Path>>(path: P, node_tcp_rpc: &NodeTcpRpc) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(keys) = StoredKeys::load_node_keystore(path)? {
+            return Ok(Self::NodeKeystore {
+                node_tcp_rpc: node_tcp_rpc.clone(),
+                key_hash: keys.key_hash,
+                public: ed25519_dalek::PublicKey::from_bytes(&keys.public)
+                    .context("invalid public key")?,
+            });
+        }
+
+        if let Some(keys) = StoredKeys::load_external_signer(path)? {
+            return Ok(Self::External(ExternalSigner::new(
+                keys.external_signer.parse()?,
+                ed25519_dalek::PublicKey::from_bytes(&keys.public).context("invalid public key")?,
+            )));
+        }
+
+        Ok(Self::Keypair(StoredKeys::load_as_keypair(path)?))
+    }
+
+    /// Creates signer for the existing key from the node keystore
+    pub async fn node_keystore(node_tcp_rpc: NodeTcpRpc, key_hash: [u8; 32]) -> Result<Self> {
+        let public = node_tcp_rpc
+            .export_public_key(&key_hash)
+            .await
+            .context("failed to export public key")?;
+        let public = ed25519_dalek::PublicKey::from_bytes(public.as_bytes())
+            .context("invalid public key")?;
+
+        Ok(Self::NodeKeystore {
+            node_tcp_rpc,
+            key_hash,
+            public,
+        })
+    }
+
+    pub fn public_key(&self) -> &ed25519_dalek::PublicKey {
+        match self {
+            Self::Keypair(keypair) => &keypair.public,
+            Self::NodeKeystore { public, .. } => public,
+            Self::External(signer) => signer.public_key(),
+        }
+    }
+
+    pub async fn sign(&self, data: &[u8], context: &SigningContext) -> Result<[u8; 64]> {
+        let signature = match self {
+            Self::Keypair(keypair) => return Ok(keypair.sign(data).to_bytes()),
+            Self::NodeKeystore {
+                node_tcp_rpc,
+                key_hash,
+                ..
+            } => node_tcp_rpc
+                .sign(key_hash, data)
+                .await
+                .context("failed to sign data with the node keystore key")?,
+            Self::External(signer) => signer
+                .sign(data, context)
+                .await
+                .with_context(|| format!("failed to sign data with {}", signer.endpoint()))?,
+        };
+
+        // Check remote signature to fail early on the wrong key
+        let parsed =
+            ed25519_dalek::Signature::from_bytes(&signature).context("invalid signature")?;
+        self.public_key()
+            .verify_strict(data, &parsed)
+            .map_err(|_| SignerError::KeyMismatch)?;
+
+        Ok(signature)
+    }
+
+    /// Encodes and signs the external message body
+    pub async fn encode_input(
+        &self,
+        function: &ton_abi::Function,
+        header: &HashMap<String, ton_abi::TokenValue>,
+        input: &[ton_abi::Token],
+        address: &ton_block::MsgAddressInt,
+        context: &SigningContext,
+    ) -> Result<ton_types::BuilderData> {
+        let (data, hash) =
+            function.create_unsigned_call(header, input, false, true, Some(address.clone()))?;
+        let signature = self.sign(&hash, context).await?;
+        ton_abi::Function::fill_sign(
+            &function.abi_version,
+            Some(&signature),
+            Some(self.public_key().as_bytes()),
+            data,
+        )
+    }
+}
+
+impl From<ed25519_dalek::Keypair> for Signer {
+    fn from(keypair: ed25519_dalek::Keypair) -> Self {
+        Self::Keypair(keypair)
+    }
+}
+
+/// Description of the external message which is being signed
+#[derive(Debug, Clone, Serialize)]
+pub struct SigningContext {
+    pub action: SigningAction,
+    /// Address of the contract which receives the external message
+    #[serde(with = "serde_string")]
+    pub address: ton_block::MsgAddressInt,
+    /// Called contract method
+    pub method: String,
+    /// Recipient of the outgoing internal message
+    #[serde(
+        with = "serde_optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub destination: Option<ton_block::MsgAddressInt>,
+    /// Attached amount in nano EVER
+    #[serde(
+        with = "serde_optional_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub amount: Option<u128>,
+    /// Outgoing message flags (e.g. 3 for the regular transfer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<u8>,
+    /// Whether the whole contract balance is sent (`amount` is ignored)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub all_balance: bool,
+    /// Internal message payload
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<PayloadSummary>,
+}
+
+impl SigningContext {
+    pub fn new(
+        action: SigningAction,
+        address: &ton_block::MsgAddressInt,
+        function: &ton_abi::Function,
+    ) -> Self {
+        Self {
+            action,
+            address: address.clone(),
+            method: function.name.clone(),
+            destination: None,
+            amount: None,
+            flags: None,
+            all_balance: false,
+            payload: None,
+        }
+    }
+
+    pub fn with_transfer(
+        mut self,
+        destination: &ton_block::MsgAddressInt,
+        amount: u128,
+        flags: u8,
+        payload: &ton_types::Cell,
+    ) -> Self {
+        self.destination = Some(destination.clone());
+        self.amount = Some(amount);
+        self.flags = Some(flags);
+        self.all_balance = flags & 128 != 0;
+        self.payload = Some(PayloadSummary::new(payload));
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningAction {
+    WalletTransfer,
+    DePoolDeploy,
+    ContractSend,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadSummary {
+    /// Hex encoded payload cell hash
+    pub hash: String,
+    /// Hex encoded function id (if the payload is large enough)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function_id: Option<String>,
+    pub bits: usize,
+    pub refs: usize,
+}
+
+impl PayloadSummary {
+    pub fn new(payload: &ton_types::Cell) -> Self {
+        let bits = payload.bit_length();
+        let function_id = (bits >= 32).then(|| hex::encode(&payload.data()[..4]));
+
+        Self {
+            hash: hex::encode(payload.repr_hash().as_slice()),
+            function_id,
+            bits,
+            refs: payload.references_count(),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum SignerError {
+    #[error("signer key doesn't match the stored public key")]
+    KeyMismatch,
+}
@@ -1,11 +1,17 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use broxus_util::serde_string;
 use nekoton_abi::{
-    BuildTokenValue, FunctionBuilder, KnownParamType, TokenValueExt, UnpackAbi, UnpackFirst,
+    BuildTokenValue, FunctionBuilder, FunctionExt, KnownParamType, TokenValueExt, UnpackAbi,
+    UnpackFirst,
 };
+use nekoton_utils::SimpleClock;
+use serde::Serialize;
 
-use crate::network::Subscription;
+use super::depool::{DePool, RoundStep, RoundsMap};
+use super::ONE_EVER;
+use crate::network::{NodeTcpRpc, Subscription};
 
 pub struct Strategy {
     pub address: ton_block::MsgAddressInt,
@@ -34,11 +40,249 @@ impl Strategy {
     }
 }
 
-#[derive(Clone, UnpackAbi, KnownParamType)]
+/// Checks all strategies of the stEVER DePool (allowed participants except the validator)
+pub async fn check_strategies(
+    depool: &DePool,
+    depool_state: &ton_block::AccountStuff,
+    node_tcp_rpc: &NodeTcpRpc,
+) -> Result<Vec<StrategyStatus>> {
+    let info = depool
+        .get_info(depool_state)
+        .context("failed to get DePool info")?;
+    let rounds = depool
+        .get_rounds(depool_state)
+        .context("failed to get DePool rounds")?;
+    let allowed_participants = depool
+        .get_allowed_participants(depool_state)
+        .context("failed to get allowed participants")?;
+
+    let mut result = Vec::new();
+    for address in allowed_participants {
+        if address == info.validator_wallet {
+            continue;
+        }
+
+        let status = get_strategy_status(&address, depool, depool_state, &rounds, node_tcp_rpc);
+        let status = match status.await {
+            Ok(status) => status,
+            Err(e) => {
+                // Other strategies are still reported
+                tracing::warn!(strategy = %address, "failed to get strategy status: {e:?}");
+                let mut status = StrategyStatus::new(address);
+                status.issues.push(StrategyIssue::CheckFailed);
+                status
+            }
+        };
+        result.push(status);
+    }
+
+    Ok(result)
+}
+
+/// Collects the strategy state and checks it against the DePool
+async fn get_strategy_status(
+    address: &ton_block::MsgAddressInt,
+    depool: &DePool,
+    depool_state: &ton_block::AccountStuff,
+    rounds: &RoundsMap,
+    node_tcp_rpc: &NodeTcpRpc,
+) -> Result<StrategyStatus> {
+    /// Strategy pays for its storage and for processing DePool answers
+    const MIN_BALANCE: u128 = ONE_EVER;
+
+    let mut status = StrategyStatus::new(address.clone());
+
+    // Strategy is an ordinary DePool participant
+    let participant = depool
+        .get_participant_info(depool_state, address)
+        .context("failed to get strategy participant info")?;
+    if let Some(participant) = participant {
+        status.total_stake = participant.total;
+        status.pending_withdrawal = participant.withdraw_value;
+        status.pending_deposit = rounds
+            .values()
+            .filter(|round| round.step == RoundStep::Pooling)
+            .map(|round| participant.compute_total_stake(round.id))
+            .sum();
+    }
+
+    let shard_account = node_tcp_rpc
+        .get_shard_account_state(address)
+        .await
+        .context("failed to get shard account state")?;
+    let account = shard_account
+        .read_account()
+        .context("failed to read account state")?;
+    let account = match account {
+        ton_block::Account::Account(account) => account,
+        ton_block::Account::AccountNone => {
+            status.issues.push(StrategyIssue::NotDeployed);
+            return Ok(status);
+        }
+    };
+
+    status.balance = account.storage.balance.grams.0;
+    status.last_transaction_at =
+        match get_last_transaction_at(address, &shard_account, node_tcp_rpc).await {
+            Ok(time) => time,
+            Err(e) => {
+                tracing::warn!(strategy = %address, "failed to get last transaction: {e:?}");
+                None
+            }
+        };
+
+    match &account.storage.state {
+        ton_block::AccountState::AccountActive { .. } => {}
+        ton_block::AccountState::AccountFrozen { .. } => {
+            status.issues.push(StrategyIssue::Frozen);
+            return Ok(status);
+        }
+        ton_block::AccountState::AccountUninit => {
+            status.issues.push(StrategyIssue::NotDeployed);
+            return Ok(status);
+        }
+    }
+
+    if status.balance < MIN_BALANCE {
+        status.issues.push(StrategyIssue::OutOfGas);
+    }
+
+    let output = methods::get_details().run_local(
+        &SimpleClock,
+        account,
+        &[0u32.token_value().named("answerId")],
+    )?;
+    match output.tokens {
+        Some(tokens) => {
+            let details: Details = tokens.unpack_first()?;
+            if &details.depool != depool.address() {
+                status.issues.push(StrategyIssue::DePoolMismatch);
+            }
+            if !is_active(&details.vault, node_tcp_rpc)
+                .await
+                .context("failed to get vault state")?
+            {
+                status.issues.push(StrategyIssue::VaultInactive);
+            }
+            status.details = Some(details);
+        }
+        None => status.issues.push(StrategyIssue::DetailsUnavailable),
+    }
+
+    Ok(status)
+}
+
+/// Returns the generation time of the block with the latest account transaction
+async fn get_last_transaction_at(
+    address: &ton_block::MsgAddressInt,
+    shard_account: &ton_block::ShardAccount,
+    node_tcp_rpc: &NodeTcpRpc,
+) -> Result<Option<u32>> {
+    let lt = shard_account.last_trans_lt();
+    if lt == 0 {
+        return Ok(None);
+    }
+
+    let transactions = node_tcp_rpc
+        .get_transactions(address, lt, shard_account.last_trans_hash(), 1)
+        .await?;
+    Ok(transactions.first().map(|tx| tx.data.now))
+}
+
+async fn is_active(address: &ton_block::MsgAddressInt, node_tcp_rpc: &NodeTcpRpc) -> Result<bool> {
+    let account = node_tcp_rpc
+        .get_shard_account_state(address)
+        .await?
+        .read_account()?;
+    Ok(matches!(
+        account,
+        ton_block::Account::Account(ton_block::AccountStuff {
+            storage: ton_block::AccountStorage {
+                state: ton_block::AccountState::AccountActive { .. },
+                ..
+            },
+            ..
+        })
+    ))
+}
+
+#[derive(Clone, Serialize)]
+pub struct StrategyStatus {
+    #[serde(with = "serde_string")]
+    pub address: ton_block::MsgAddressInt,
+    pub details: Option<Details>,
+    #[serde(with = "serde_string")]
+    pub balance: u128,
+    /// Timestamp of the latest strategy transaction
+    /// (`None` if the node doesn't serve transactions)
+    pub last_transaction_at: Option<u32>,
+    /// Strategy stake in the DePool
+    #[serde(with = "serde_string")]
+    pub total_stake: u64,
+    /// Stake in the pooling round
+    #[serde(with = "serde_string")]
+    pub pending_deposit: u64,
+    /// Requested withdrawal which is not processed yet
+    #[serde(with = "serde_string")]
+    pub pending_withdrawal: u64,
+    pub issues: Vec<StrategyIssue>,
+}
+
+impl StrategyStatus {
+    fn new(address: ton_block::MsgAddressInt) -> Self {
+        Self {
+            address,
+            details: None,
+            balance: 0,
+            last_transaction_at: None,
+            total_stake: 0,
+            pending_deposit: 0,
+            pending_withdrawal: 0,
+            issues: Vec::new(),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyIssue {
+    /// Strategy account doesn't exist or is not initialized
+    NotDeployed,
+    /// Strategy account was frozen due to the storage debt
+    Frozen,
+    /// Strategy balance is too low to process DePool answers
+    OutOfGas,
+    /// Strategy was deployed for the different DePool
+    DePoolMismatch,
+    /// Strategy vault doesn't exist or is not active
+    VaultInactive,
+    /// Strategy getter failed
+    DetailsUnavailable,
+    /// Strategy state could not be checked
+    CheckFailed,
+}
+
+impl std::fmt::Display for StrategyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Same names as in JSON
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => f.write_str(&name),
+            _ => Err(std::fmt::Error),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, UnpackAbi, KnownParamType)]
 pub struct Details {
     #[abi(address)]
+    #[serde(with = "serde_string")]
     pub vault: ton_block::MsgAddressInt,
     #[abi(address)]
+    #[serde(with = "serde_string")]
     pub depool: ton_block::MsgAddressInt,
     #[abi(uint32)]
     pub strategy_version: u32,
@@ -5,12 +5,13 @@ use nekoton_abi::{FunctionBuilder, KnownParamTypePlain, PackAbiPlain};
 use ton_abi::contract::ABI_VERSION_2_3;
 use ton_block::{Deserializable, GetRepresentationHash};
 
-use super::{InternalMessage, ONE_EVER};
-use crate::network::Subscription;
+use super::signer::{SigningAction, SigningContext};
+use super::{InternalMessage, Signer, ONE_EVER};
+use crate::network::{Subscription, TransactionsRx};
 use crate::util::{make_default_headers, TransactionWithHash};
 
 pub struct Wallet {
-    keypair: ed25519_dalek::Keypair,
+    signer: Signer,
     address: ton_block::MsgAddressInt,
     subscription: Arc<Subscription>,
 }
@@ -18,14 +19,15 @@ pub struct Wallet {
 impl Wallet {
     pub const INITIAL_BALANCE: u128 = 10 * ONE_EVER;
 
-    pub fn new(
+    pub fn new<S: Into<Signer>>(
         workchain_id: i8,
-        keypair: ed25519_dalek::Keypair,
+        signer: S,
         subscription: Arc<Subscription>,
     ) -> Self {
+        let signer = signer.into();
         Self {
-            address: compute_wallet_address(workchain_id, &keypair.public),
-            keypair,
+            address: compute_wallet_address(workchain_id, signer.public_key()),
+            signer,
             subscription,
         }
     }
@@ -42,44 +44,86 @@ impl Wallet {
     /// Sends the internal message to the recipient, returns the destination transaction
     pub async fn call(&self, internal_message: InternalMessage) -> Result<TransactionWithHash> {
         let dst = internal_message.dst.clone();
-        let mut dst_transactions = self.subscription.subscribe(&dst);
+        let dst_transactions = self.subscription.subscribe(&dst);
 
         let src_tx = self.transfer(internal_message).await?;
         tracing::debug!(source_tx_hash = ?src_tx.hash, "message sent from wallet");
 
-        let mut out_msg_hash = None;
-        src_tx
-            .data
-            .out_msgs
-            .iterate_slices(|msg| {
-                let Some(msg) = msg.reference_opt(0) else { return Ok(true) };
-
-                let msg_hash = msg.repr_hash();
-                let msg = ton_block::Message::construct_from_cell(msg)?;
-                let Some(header) = msg.int_header() else { return Ok(true) };
-
-                if header.dst == dst {
-                    out_msg_hash = Some(msg_hash);
-                    Ok(false)
-                } else {
-                    Ok(true)
-                }
-            })
-            .context("failed to find outgoing message")?;
-        let out_msg_hash = out_msg_hash.context("outgoing message not found")?;
-
-        while let Some(tx) = dst_transactions.recv().await {
-            tracing::debug!(source_tx_hash = ?src_tx.hash, tx_hash = ?tx.hash, "new transaction found");
-            let Some(msg) = tx.data.in_msg_cell() else { continue; };
-            if msg.repr_hash() == out_msg_hash {
-                return Ok(tx);
-            }
-        }
-        anyhow::bail!("destination transaction was not found")
+        find_destination_transaction(&src_tx, &dst, dst_transactions).await
+    }
+
+    /// Sends the whole wallet balance to the recipient, returns the destination transaction
+    pub async fn call_transfer_all(
+        &self,
+        dst: ton_block::MsgAddressInt,
+    ) -> Result<TransactionWithHash> {
+        let dst_transactions = self.subscription.subscribe(&dst);
+
+        let src_tx = self.transfer_all(dst.clone()).await?;
+        tracing::debug!(source_tx_hash = ?src_tx.hash, "balance sent from wallet");
+
+        find_destination_transaction(&src_tx, &dst, dst_transactions).await
     }
 
     /// Sends the internal message to the recipient, returns the source transaction
     pub async fn transfer(&self, internal_message: InternalMessage) -> Result<TransactionWithHash> {
+        self.send(internal_message, false).await
+    }
+
+    /// Sends the internal message with the specified bounce flag, returns the source transaction
+    pub async fn send(
+        &self,
+        internal_message: InternalMessage,
+        bounce: bool,
+    ) -> Result<TransactionWithHash> {
+        self.send_transaction(ever_wallet::SendTransactionInputs {
+            dest: internal_message.dst,
+            value: internal_message.amount,
+            bounce,
+            flags: 3,
+            payload: internal_message.payload,
+        })
+        .await
+    }
+
+    /// Sends the whole wallet balance to the recipient, returns the source transaction
+    pub async fn transfer_all(&self, dst: ton_block::MsgAddressInt) -> Result<TransactionWithHash> {
+        self.send_transaction(ever_wallet::SendTransactionInputs {
+            dest: dst,
+            value: 0,
+            bounce: false,
+            flags: 128 | 2,
+            payload: Default::default(),
+        })
+        .await
+    }
+
+    /// Deploys the wallet by sending an empty message to itself.
+    ///
+    /// Returns `None` if the wallet is already deployed
+    pub async fn deploy(&self) -> Result<Option<TransactionWithHash>> {
+        let account = self.get_account_state().await?;
+        if let Some(account) = &account {
+            if let ton_block::AccountState::AccountActive { .. } = account.storage.state {
+                return Ok(None);
+            }
+        }
+
+        self.send_transaction(ever_wallet::SendTransactionInputs {
+            dest: self.address.clone(),
+            value: 0,
+            bounce: false,
+            flags: 3,
+            payload: Default::default(),
+        })
+        .await
+        .map(Some)
+    }
+
+    async fn send_transaction(
+        &self,
+        inputs: ever_wallet::SendTransactionInputs,
+    ) -> Result<TransactionWithHash> {
         let account = self.get_account_state().await?;
 
         let state_init = match account {
@@ -89,50 +133,57 @@ impl Wallet {
                     anyhow::bail!("account frozen");
                 }
                 ton_block::AccountState::AccountUninit => Some(
-                    make_state_init(&self.keypair.public).context("failed to make state init")?,
+                    make_state_init(self.signer.public_key())
+                        .context("failed to make state init")?,
                 ),
             },
             None => anyhow::bail!("account not deployed"),
         };
 
-        let inputs = ever_wallet::SendTransactionInputs {
-            dest: internal_message.dst,
-            value: internal_message.amount,
-            bounce: false,
-            flags: 3,
-            payload: internal_message.payload,
-        }
-        .pack();
+        let context = SigningContext::new(
+            SigningAction::WalletTransfer,
+            &self.address,
+            ever_wallet::send_transaction(),
+        )
+        .with_transfer(&inputs.dest, inputs.value, inputs.flags, &inputs.payload);
+
+        let inputs = inputs.pack();
 
         let tx = self
             .subscription
             .send_message_with_retires(|timeout| {
-                let (expire_at, headers) = make_default_headers(Some(self.keypair.public), timeout);
-
-                let mut message = ton_block::Message::with_ext_in_header(
-                    ton_block::ExternalInboundMessageHeader {
-                        dst: self.address.clone(),
-                        ..Default::default()
-                    },
-                );
-
-                message.set_body(
-                    ever_wallet::send_transaction()
-                        .encode_input(
-                            &headers,
-                            &inputs,
-                            false,
-                            Some(&self.keypair),
-                            Some(self.address.clone()),
-                        )?
-                        .into(),
-                );
-
-                if let Some(state_init) = state_init.clone() {
-                    message.set_state_init(state_init);
-                }
+                let (inputs, context) = (&inputs, &context);
+                let state_init = state_init.clone();
+                async move {
+                    let (expire_at, headers) =
+                        make_default_headers(Some(*self.signer.public_key()), timeout);
+
+                    let mut message = ton_block::Message::with_ext_in_header(
+                        ton_block::ExternalInboundMessageHeader {
+                            dst: self.address.clone(),
+                            ..Default::default()
+                        },
+                    );
 
-                Ok((message, expire_at))
+                    message.set_body(
+                        self.signer
+                            .encode_input(
+                                ever_wallet::send_transaction(),
+                                &headers,
+                                inputs,
+                                &self.address,
+                                context,
+                            )
+                            .await?
+                            .into(),
+                    );
+
+                    if let Some(state_init) = state_init {
+                        message.set_state_init(state_init);
+                    }
+
+                    Ok((message, expire_at))
+                }
             })
             .await?;
 
@@ -147,6 +198,49 @@ impl Wallet {
     }
 }
 
+/// Waits for the transaction of the message sent to `dst` by the source transaction
+async fn find_destination_transaction(
+    src_tx: &TransactionWithHash,
+    dst: &ton_block::MsgAddressInt,
+    mut dst_transactions: TransactionsRx,
+) -> Result<TransactionWithHash> {
+    let mut out_msg_hash = None;
+    src_tx
+        .data
+        .out_msgs
+        .iterate_slices(|msg| {
+            let Some(msg) = msg.reference_opt(0) else {
+                return Ok(true);
+            };
+
+            let msg_hash = msg.repr_hash();
+            let msg = ton_block::Message::construct_from_cell(msg)?;
+            let Some(header) = msg.int_header() else {
+                return Ok(true);
+            };
+
+            if &header.dst == dst {
+                out_msg_hash = Some(msg_hash);
+                Ok(false)
+            } else {
+                Ok(true)
+            }
+        })
+        .context("failed to find outgoing message")?;
+    let out_msg_hash = out_msg_hash.context("outgoing message not found")?;
+
+    while let Some(tx) = dst_transactions.recv().await {
+        tracing::debug!(source_tx_hash = ?src_tx.hash, tx_hash = ?tx.hash, "new transaction found");
+        let Some(msg) = tx.data.in_msg_cell() else {
+            continue;
+        };
+        if msg.repr_hash() == out_msg_hash {
+            return Ok(tx);
+        }
+    }
+    anyhow::bail!("destination transaction was not found")
+}
+
 pub fn compute_wallet_address(
     workchain_id: i8,
     pubkey: &ed25519_dalek::PublicKey,
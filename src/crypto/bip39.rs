@@ -8,9 +8,13 @@ pub fn validate_phrase(phrase: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn derive_from_phrase(phrase: &str, path: &str) -> Result<ed25519_dalek::Keypair> {
+pub fn derive_from_phrase(
+    phrase: &str,
+    path: &str,
+    passphrase: &str,
+) -> Result<ed25519_dalek::Keypair> {
     let mnemonic = bip39::Mnemonic::from_phrase(phrase, LANGUAGE)?;
-    let hd = bip39::Seed::new(&mnemonic, "");
+    let hd = bip39::Seed::new(&mnemonic, passphrase);
     let seed_bytes = hd.as_bytes();
 
     let derived = ExtendedPrivKey::derive(seed_bytes, path)
@@ -4,13 +4,31 @@ use anyhow::Result;
 use hmac::digest::Digest;
 use rand::Rng;
 
+pub use self::shamir::{combine_shares, split_secret, Share};
+
 mod bip39;
 mod legacy;
+mod shamir;
 
 const LANGUAGE: ::bip39::Language = ::bip39::Language::English;
 
 pub const DEFAULT_PATH: &str = "m/44'/396'/0'/0/0";
 
+/// Returns the derivation path for the specified account index
+pub fn account_path(account: u32) -> String {
+    format!("m/44'/396'/0'/0/{account}")
+}
+
+/// Resolves the derivation path from either an explicit path or an account index
+pub fn derivation_path(path: Option<String>, account: Option<u32>) -> Result<String> {
+    match (path, account) {
+        (Some(_), Some(_)) => anyhow::bail!("derivation path and account index are exclusive"),
+        (Some(path), None) => Ok(path),
+        (None, Some(account)) => Ok(account_path(account)),
+        (None, None) => Ok(DEFAULT_PATH.to_owned()),
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum MnemonicType {
     /// Phrase with 24 words, used in Crystal Wallet
@@ -41,14 +59,24 @@ pub fn validate_phrase(phrase: &str, mnemonic_type: MnemonicType) -> Result<()>
     }
 }
 
+/// Derives keypair from the seed phrase.
+///
+/// Path and passphrase are only used for the bip39 mnemonic.
 pub fn derive_from_phrase(
     phrase: &str,
     mnemonic_type: MnemonicType,
     path: &str,
+    passphrase: &str,
 ) -> Result<ed25519_dalek::Keypair> {
     match mnemonic_type {
-        MnemonicType::Legacy => self::legacy::derive_from_phrase(phrase),
-        MnemonicType::Bip39 => self::bip39::derive_from_phrase(phrase, path),
+        MnemonicType::Legacy => {
+            anyhow::ensure!(
+                passphrase.is_empty(),
+                "passphrase is not supported for the legacy mnemonic"
+            );
+            self::legacy::derive_from_phrase(phrase)
+        }
+        MnemonicType::Bip39 => self::bip39::derive_from_phrase(phrase, path, passphrase),
     }
 }
 
@@ -0,0 +1,301 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use hmac::digest::Digest;
+use rand::Rng;
+
+/// Part of the secret split with Shamir's secret sharing over GF(256)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Share {
+    /// Number of shares required to recover the secret
+    pub threshold: u8,
+    /// Share index (x coordinate), starts from 1
+    pub index: u8,
+    /// Public key which corresponds to the secret
+    pub public: [u8; 32],
+    /// Share data (y coordinates)
+    pub data: Vec<u8>,
+}
+
+impl Share {
+    const PREFIX: &str = "stever-share";
+    const VERSION: &str = "v1";
+
+    fn checksum(&self) -> [u8; 4] {
+        let hash = sha2::Sha256::new()
+            .chain([self.threshold, self.index])
+            .chain(self.public)
+            .chain(&self.data)
+            .finalize();
+        let mut checksum = [0; 4];
+        checksum.copy_from_slice(&hash[..4]);
+        checksum
+    }
+}
+
+impl std::fmt::Display for Share {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}:{}:{}",
+            Self::PREFIX,
+            Self::VERSION,
+            self.threshold,
+            self.index,
+            hex::encode(self.public),
+            hex::encode(&self.data),
+            hex::encode(self.checksum()),
+        )
+    }
+}
+
+impl FromStr for Share {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split(':').collect::<Vec<_>>();
+        let [prefix, version, threshold, index, public, data, checksum] = parts[..] else {
+            anyhow::bail!("invalid share format");
+        };
+        anyhow::ensure!(prefix == Self::PREFIX, "invalid share prefix");
+        anyhow::ensure!(version == Self::VERSION, "unsupported share version");
+
+        let mut public_bytes = [0; 32];
+        hex::decode_to_slice(public, &mut public_bytes).context("invalid share public key")?;
+
+        let share = Self {
+            threshold: threshold.parse().context("invalid share threshold")?,
+            index: index.parse().context("invalid share index")?,
+            public: public_bytes,
+            data: hex::decode(data).context("invalid share data")?,
+        };
+        anyhow::ensure!(share.index > 0, "invalid share index");
+
+        let mut checksum_bytes = [0; 4];
+        hex::decode_to_slice(checksum, &mut checksum_bytes).context("invalid share checksum")?;
+        anyhow::ensure!(
+            share.checksum() == checksum_bytes,
+            "share checksum mismatch"
+        );
+
+        Ok(share)
+    }
+}
+
+/// Splits the secret into `shares` parts, `threshold` of which are required to recover it
+pub fn split_secret(
+    secret: &[u8],
+    public: &[u8; 32],
+    threshold: u8,
+    shares: u8,
+) -> Result<Vec<Share>> {
+    anyhow::ensure!(threshold > 0, "threshold must be greater than zero");
+    anyhow::ensure!(
+        threshold <= shares,
+        "threshold must not be greater than the number of shares"
+    );
+    anyhow::ensure!(shares < u8::MAX, "too many shares");
+
+    let rng = &mut rand::thread_rng();
+
+    let mut result = (1..=shares)
+        .map(|index| Share {
+            threshold,
+            index,
+            public: *public,
+            data: Vec::with_capacity(secret.len()),
+        })
+        .collect::<Vec<_>>();
+
+    let mut coefficients = vec![0u8; threshold as usize];
+    for &byte in secret {
+        // Random polynomial with the secret byte as a free term
+        coefficients[0] = byte;
+        rng.fill(&mut coefficients[1..]);
+
+        for share in &mut result {
+            // Horner's method
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0, |acc, &coeff| gf_mul(acc, share.index) ^ coeff);
+            share.data.push(y);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Recovers the secret from the shares
+pub fn combine_shares(shares: &[Share]) -> Result<Vec<u8>> {
+    let first = shares.first().context("no shares specified")?;
+
+    for share in shares {
+        anyhow::ensure!(
+            share.threshold == first.threshold
+                && share.public == first.public
+                && share.data.len() == first.data.len(),
+            "shares are from different secrets"
+        );
+    }
+
+    let mut indices = shares.iter().map(|share| share.index).collect::<Vec<_>>();
+    indices.sort_unstable();
+    indices.dedup();
+    anyhow::ensure!(indices.len() == shares.len(), "duplicate shares");
+    anyhow::ensure!(
+        shares.len() >= first.threshold as usize,
+        "not enough shares (required {})",
+        first.threshold
+    );
+
+    let shares = &shares[..first.threshold as usize];
+
+    // Lagrange basis polynomials at x = 0
+    let basis = shares
+        .iter()
+        .map(|share| {
+            let (num, den) = shares
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold((1, 1), |(num, den), other| {
+                    (
+                        gf_mul(num, other.index),
+                        gf_mul(den, other.index ^ share.index),
+                    )
+                });
+            gf_mul(num, gf_inv(den))
+        })
+        .collect::<Vec<_>>();
+
+    let secret = (0..first.data.len())
+        .map(|i| {
+            shares
+                .iter()
+                .zip(&basis)
+                .fold(0, |acc, (share, &l)| acc ^ gf_mul(share.data[i], l))
+        })
+        .collect();
+
+    Ok(secret)
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    result
+}
+
+/// Multiplicative inverse in GF(2^8) (`a^254`)
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp != 0 {
+        if exp & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subsets(shares: &[Share], size: usize) -> Vec<Vec<Share>> {
+        (0u32..1 << shares.len())
+            .filter(|mask| mask.count_ones() as usize == size)
+            .map(|mask| {
+                shares
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, share)| share.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_threshold_subset_recovers_secret() {
+        let secret: [u8; 32] = rand::thread_rng().gen();
+        let public = [0x55; 32];
+
+        for shares_count in 1..=5 {
+            for threshold in 1..=shares_count {
+                let shares = split_secret(&secret, &public, threshold, shares_count).unwrap();
+                assert_eq!(shares.len(), shares_count as usize);
+
+                for size in threshold..=shares_count {
+                    for subset in subsets(&shares, size as usize) {
+                        assert_eq!(combine_shares(&subset).unwrap(), secret);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn not_enough_shares_fail() {
+        let secret: [u8; 32] = rand::thread_rng().gen();
+        let public = [0x55; 32];
+
+        for shares_count in 2..=5 {
+            for threshold in 2..=shares_count {
+                let shares = split_secret(&secret, &public, threshold, shares_count).unwrap();
+
+                for subset in subsets(&shares, threshold as usize - 1) {
+                    assert!(combine_shares(&subset).is_err());
+
+                    // Interpolation over the insufficient shares yields a different secret
+                    let forged = subset
+                        .into_iter()
+                        .map(|share| Share {
+                            threshold: threshold - 1,
+                            ..share
+                        })
+                        .collect::<Vec<_>>();
+                    assert_ne!(combine_shares(&forged).unwrap(), secret);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn share_string_roundtrip() {
+        let secret: [u8; 32] = rand::thread_rng().gen();
+        let shares = split_secret(&secret, &[0x55; 32], 2, 3).unwrap();
+
+        for share in shares {
+            let encoded = share.to_string();
+            assert_eq!(encoded.parse::<Share>().unwrap(), share);
+
+            // Any change in the share data must fail the checksum
+            let mut tampered = share.clone();
+            tampered.data[0] ^= 1;
+            let tampered = encoded.replace(&hex::encode(&share.data), &hex::encode(&tampered.data));
+            assert!(tampered.parse::<Share>().is_err());
+        }
+    }
+
+    #[test]
+    fn gf_inverse() {
+        for a in 1..=u8::MAX {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+}
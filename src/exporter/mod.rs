@@ -1,10 +1,12 @@
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use pomfrit::formatter::DisplayPrometheusExt;
 
 pub use self::file_target::FileExporterTarget;
 pub use self::http_target::HttpExporterTarget;
+use crate::contracts::strategy::{self, StrategyStatus};
+use crate::contracts::DePool;
 use crate::network::{NodeStats, NodeTcpRpc, ValidatorSetEntry};
 
 mod file_target;
@@ -14,6 +16,7 @@ pub struct Exporter {
     node_rpc: NodeTcpRpc,
     interval: Duration,
     targets: Vec<Box<dyn ExporterTarget>>,
+    strategies: Option<StrategiesSource>,
 }
 
 impl Exporter {
@@ -26,9 +29,16 @@ impl Exporter {
             node_rpc,
             interval,
             targets,
+            strategies: None,
         }
     }
 
+    /// Enables stEVER strategies metrics
+    pub fn with_strategies(mut self, strategies: StrategiesSource) -> Self {
+        self.strategies = Some(strategies);
+        self
+    }
+
     pub async fn serve(self) {
         if self.targets.is_empty() {
             return;
@@ -49,9 +59,21 @@ impl Exporter {
 
             tracing::debug!("collected node stats");
 
+            let strategies = match &self.strategies {
+                Some(source) => match source.collect().await {
+                    Ok(strategies) => Some(strategies),
+                    Err(e) => {
+                        tracing::warn!("failed to collect strategies: {e:?}");
+                        None
+                    }
+                },
+                None => None,
+            };
+
             let metrics = Metrics {
                 collected_at,
                 stats: &stats,
+                strategies: strategies.as_deref(),
             };
 
             for target in &self.targets {
@@ -66,6 +88,29 @@ impl Exporter {
     }
 }
 
+/// stEVER DePool whose strategies are exported
+pub struct StrategiesSource {
+    pub depool: DePool,
+    pub node_rpc: NodeTcpRpc,
+}
+
+impl StrategiesSource {
+    pub async fn collect(&self) -> Result<Vec<StrategyStatus>> {
+        let depool_state = self
+            .node_rpc
+            .get_shard_account_state(self.depool.address())
+            .await
+            .context("failed to get DePool state")?
+            .read_account()
+            .context("failed to read DePool state")?;
+        let depool_state = match depool_state {
+            ton_block::Account::Account(state) => state,
+            ton_block::Account::AccountNone => anyhow::bail!("DePool not deployed"),
+        };
+        strategy::check_strategies(&self.depool, &depool_state, &self.node_rpc).await
+    }
+}
+
 pub trait ExporterTarget {
     fn target_name(&self) -> &'static str;
 
@@ -76,6 +121,7 @@ pub trait ExporterTarget {
 pub struct Metrics<'a> {
     pub collected_at: u32,
     pub stats: &'a NodeStats,
+    pub strategies: Option<&'a [StrategyStatus]>,
 }
 
 impl std::fmt::Display for Metrics<'_> {
@@ -84,6 +130,44 @@ impl std::fmt::Display for Metrics<'_> {
 
         f.begin_metric("collected_at").value(self.collected_at)?;
 
+        // Strategies don't depend on the node state
+        if let Some(strategies) = self.strategies {
+            const ADDRESS_LABEL: &str = "address";
+
+            f.begin_metric("strategy_count").value(strategies.len())?;
+            for status in strategies {
+                let address = status.address.to_string();
+
+                f.begin_metric("strategy_healthy")
+                    .label(ADDRESS_LABEL, &address)
+                    .value(status.is_healthy() as u8)?;
+                f.begin_metric("strategy_balance")
+                    .label(ADDRESS_LABEL, &address)
+                    .value(status.balance)?;
+                f.begin_metric("strategy_total_stake")
+                    .label(ADDRESS_LABEL, &address)
+                    .value(status.total_stake)?;
+                f.begin_metric("strategy_pending_deposit")
+                    .label(ADDRESS_LABEL, &address)
+                    .value(status.pending_deposit)?;
+                f.begin_metric("strategy_pending_withdrawal")
+                    .label(ADDRESS_LABEL, &address)
+                    .value(status.pending_withdrawal)?;
+                if let Some(last_transaction_at) = status.last_transaction_at {
+                    f.begin_metric("strategy_last_transaction_at")
+                        .label(ADDRESS_LABEL, &address)
+                        .value(last_transaction_at)?;
+                }
+
+                for issue in &status.issues {
+                    f.begin_metric("strategy_issue")
+                        .label(ADDRESS_LABEL, &address)
+                        .label("issue", issue)
+                        .value(1)?;
+                }
+            }
+        }
+
         let stats = match self.stats {
             NodeStats::NotReady => return f.begin_metric(NODE_READY).value(0),
             NodeStats::Running(stats) => {
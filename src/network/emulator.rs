@@ -0,0 +1,387 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use broxus_util::serde_string;
+use serde::Serialize;
+use ton_block::Serializable;
+
+use crate::util::TransactionWithHash;
+
+/// Result of the local message execution
+#[derive(Clone, Serialize)]
+pub struct Emulation {
+    /// Whether the message was accepted and the transaction was not aborted
+    pub success: bool,
+    /// Account balance before the execution
+    #[serde(with = "serde_string")]
+    pub balance: u128,
+    /// Whether the account had code and data before the execution
+    pub account_active: bool,
+    /// Estimated transaction fees
+    #[serde(with = "serde_string")]
+    pub total_fees: u128,
+    /// Whether the message was skipped due to the low account balance
+    pub insufficient_balance: bool,
+    pub compute: ComputePhaseResult,
+    pub action: Option<ActionPhaseResult>,
+    /// Emulated transaction (only for the accepted messages)
+    #[serde(skip)]
+    pub transaction: Option<TransactionWithHash>,
+    /// Execution of the produced internal messages on the destination accounts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub internal: Vec<InternalEmulation>,
+}
+
+impl Emulation {
+    /// Short description of the failed phase
+    pub fn error(&self) -> Option<String> {
+        match &self.compute {
+            ComputePhaseResult::Skipped { reason } => {
+                return Some(format!("compute phase skipped ({reason})"));
+            }
+            ComputePhaseResult::Vm {
+                accepted: false,
+                exit_code,
+                ..
+            } => {
+                let hint = describe_no_accept_code(*exit_code)
+                    .map(|hint| format!(", {hint}"))
+                    .unwrap_or_default();
+                return Some(format!(
+                    "message was not accepted (exit code: {exit_code}{hint})"
+                ));
+            }
+            ComputePhaseResult::Vm {
+                success: false,
+                exit_code,
+                ..
+            } => {
+                return Some(format!("compute phase failed (exit code: {exit_code})"));
+            }
+            ComputePhaseResult::Vm { .. } => {}
+        }
+
+        match &self.action {
+            Some(action) if !action.success => {
+                return Some(format!(
+                    "action phase failed (result code: {})",
+                    action.result_code
+                ));
+            }
+            _ if !self.success => return Some("transaction aborted".to_owned()),
+            _ => {}
+        }
+
+        // Contracts called through the wallet fail in the next transaction
+        self.internal.iter().find_map(|internal| {
+            if !internal.is_rejection() {
+                return None;
+            }
+            let error = internal.emulation.error()?;
+            Some(format!(
+                "internal message to {} failed: {error}",
+                internal.dst
+            ))
+        })
+    }
+
+    /// Internal messages produced by the successful transaction
+    pub fn internal_messages(&self) -> Result<Vec<(ton_block::MsgAddressInt, ton_block::Message)>> {
+        let mut messages = Vec::new();
+        if let Some(tx) = self.transaction.as_ref().filter(|_| self.success) {
+            tx.data.out_msgs.iterate(|ton_block::InRefValue(msg)| {
+                if let Some(header) = msg.int_header() {
+                    messages.push((header.dst.clone(), msg));
+                }
+                Ok(true)
+            })?;
+        }
+        Ok(messages)
+    }
+}
+
+/// Result of the local execution of the internal message
+#[derive(Clone, Serialize)]
+pub struct InternalEmulation {
+    #[serde(with = "serde_string")]
+    pub dst: ton_block::MsgAddressInt,
+    pub bounce: bool,
+    #[serde(flatten)]
+    pub emulation: Emulation,
+}
+
+impl InternalEmulation {
+    /// Whether the failed execution means that the destination rejected the message.
+    ///
+    /// Transfers to inactive accounts are not executed (e.g. the initial transfer
+    /// to a new wallet), so they only fail if the bounceable message deploys
+    /// the contract and its code fails
+    fn is_rejection(&self) -> bool {
+        self.emulation.account_active
+            || (self.bounce && matches!(self.emulation.compute, ComputePhaseResult::Vm { .. }))
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ComputePhaseResult {
+    Skipped {
+        reason: String,
+    },
+    Vm {
+        /// Whether the contract accepted the external message
+        accepted: bool,
+        success: bool,
+        exit_code: i32,
+        gas_used: u64,
+        #[serde(with = "serde_string")]
+        gas_fees: u128,
+    },
+}
+
+#[derive(Clone, Serialize)]
+pub struct ActionPhaseResult {
+    pub success: bool,
+    pub result_code: i32,
+    #[serde(with = "serde_string")]
+    pub total_fwd_fees: u128,
+    #[serde(with = "serde_string")]
+    pub total_action_fees: u128,
+    pub messages_created: i16,
+}
+
+/// Executes the message on top of the specified account state without saving the result.
+///
+/// `utime` is used as the block unix time (messages with the `expire` header
+/// are rejected if it is greater than their expiration time)
+pub fn emulate_message(
+    config: ton_block::ConfigParams,
+    shard_account: &ton_block::ShardAccount,
+    message: &ton_block::Message,
+    utime: u32,
+) -> Result<Emulation> {
+    use ton_executor::TransactionExecutor;
+
+    let (balance, account_active) = match shard_account
+        .read_account()
+        .context("failed to read account state")?
+    {
+        ton_block::Account::Account(state) => (
+            state.storage.balance.grams.0,
+            matches!(
+                state.storage.state,
+                ton_block::AccountState::AccountActive { .. }
+            ),
+        ),
+        ton_block::Account::AccountNone => (0, false),
+    };
+
+    let config =
+        ton_executor::BlockchainConfig::with_config(config).context("invalid blockchain config")?;
+    let executor = ton_executor::OrdinaryTransactionExecutor::new(config);
+
+    let last_trans_lt = shard_account.last_trans_lt();
+    let params = ton_executor::ExecuteParams {
+        block_unixtime: utime,
+        block_lt: last_trans_lt + 1,
+        last_tr_lt: Arc::new(AtomicU64::new(last_trans_lt)),
+        ..Default::default()
+    };
+
+    let mut account_root = shard_account.account_cell();
+    let tx = match executor.execute_with_libs_and_params(Some(message), &mut account_root, params) {
+        Ok(tx) => tx,
+        // Rejected external messages don't produce transactions
+        Err(e) => {
+            let mut insufficient_balance = false;
+            let compute = match e.downcast_ref::<ton_executor::ExecutorError>() {
+                Some(ton_executor::ExecutorError::NoAcceptError(exit_code, _)) => {
+                    ComputePhaseResult::Vm {
+                        accepted: false,
+                        success: false,
+                        exit_code: *exit_code,
+                        gas_used: 0,
+                        gas_fees: 0,
+                    }
+                }
+                Some(ton_executor::ExecutorError::ExtMsgComputeSkipped(reason)) => {
+                    insufficient_balance = matches!(reason, ton_block::ComputeSkipReason::NoGas);
+                    ComputePhaseResult::Skipped {
+                        reason: format!("{reason:?}"),
+                    }
+                }
+                Some(ton_executor::ExecutorError::NoFundsToImportMsg) => {
+                    insufficient_balance = true;
+                    ComputePhaseResult::Skipped {
+                        reason: "NoFundsToImportMsg".to_owned(),
+                    }
+                }
+                _ => return Err(e).context("local execution failed"),
+            };
+
+            return Ok(Emulation {
+                success: false,
+                balance,
+                account_active,
+                total_fees: 0,
+                insufficient_balance,
+                compute,
+                action: None,
+                transaction: None,
+                internal: Vec::new(),
+            });
+        }
+    };
+
+    let descr = match tx.description.read_struct()? {
+        ton_block::TransactionDescr::Ordinary(descr) => descr,
+        _ => anyhow::bail!("unexpected transaction type"),
+    };
+
+    let mut insufficient_balance = false;
+    let compute = match descr.compute_ph {
+        ton_block::TrComputePhase::Skipped(skipped) => {
+            insufficient_balance = matches!(skipped.reason, ton_block::ComputeSkipReason::NoGas);
+            ComputePhaseResult::Skipped {
+                reason: format!("{:?}", skipped.reason),
+            }
+        }
+        ton_block::TrComputePhase::Vm(vm) => ComputePhaseResult::Vm {
+            accepted: true,
+            success: vm.success,
+            exit_code: vm.exit_code,
+            gas_used: vm.gas_used.0,
+            gas_fees: vm.gas_fees.0,
+        },
+    };
+
+    let action = descr.action.map(|action| ActionPhaseResult {
+        success: action.success,
+        result_code: action.result_code,
+        total_fwd_fees: action.total_fwd_fees.map(|fees| fees.0).unwrap_or_default(),
+        total_action_fees: action
+            .total_action_fees
+            .map(|fees| fees.0)
+            .unwrap_or_default(),
+        messages_created: action.msgs_created,
+    });
+
+    let hash = tx.serialize()?.repr_hash();
+
+    Ok(Emulation {
+        success: !descr.aborted,
+        balance,
+        account_active,
+        total_fees: tx.total_fees.grams.0,
+        insufficient_balance,
+        compute,
+        action,
+        transaction: Some(TransactionWithHash { hash, data: tx }),
+        internal: Vec::new(),
+    })
+}
+
+/// Common reasons for the external message to be rejected by the contract
+fn describe_no_accept_code(exit_code: i32) -> Option<&'static str> {
+    Some(match exit_code {
+        40 => "invalid signature",
+        52 => "replay protection",
+        57 => "message expired",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallet_call(internal: InternalEmulation) -> Emulation {
+        Emulation {
+            success: true,
+            balance: 10_000_000_000,
+            account_active: true,
+            total_fees: 10_000_000,
+            insufficient_balance: false,
+            compute: ComputePhaseResult::Vm {
+                accepted: true,
+                success: true,
+                exit_code: 0,
+                gas_used: 10_000,
+                gas_fees: 10_000_000,
+            },
+            action: Some(ActionPhaseResult {
+                success: true,
+                result_code: 0,
+                total_fwd_fees: 1_000_000,
+                total_action_fees: 333_333,
+                messages_created: 1,
+            }),
+            transaction: None,
+            internal: vec![internal],
+        }
+    }
+
+    fn internal_hop(
+        bounce: bool,
+        account_active: bool,
+        compute: ComputePhaseResult,
+    ) -> InternalEmulation {
+        InternalEmulation {
+            dst: ton_block::MsgAddressInt::default(),
+            bounce,
+            emulation: Emulation {
+                success: false,
+                balance: 0,
+                account_active,
+                total_fees: 0,
+                insufficient_balance: false,
+                compute,
+                action: None,
+                transaction: None,
+                internal: Vec::new(),
+            },
+        }
+    }
+
+    fn no_state() -> ComputePhaseResult {
+        ComputePhaseResult::Skipped {
+            reason: format!("{:?}", ton_block::ComputeSkipReason::NoState),
+        }
+    }
+
+    fn vm_failed() -> ComputePhaseResult {
+        ComputePhaseResult::Vm {
+            accepted: true,
+            success: false,
+            exit_code: 60,
+            gas_used: 1_000,
+            gas_fees: 1_000_000,
+        }
+    }
+
+    #[test]
+    fn transfer_to_uninit_account_is_not_an_error() {
+        for bounce in [false, true] {
+            let emulation = wallet_call(internal_hop(bounce, false, no_state()));
+            assert_eq!(emulation.error(), None);
+        }
+    }
+
+    #[test]
+    fn rejected_internal_message_is_an_error() {
+        // Call of the existing contract
+        for bounce in [false, true] {
+            let emulation = wallet_call(internal_hop(bounce, true, vm_failed()));
+            assert!(emulation.error().is_some());
+        }
+
+        // Failed deployment with the bounceable message
+        let emulation = wallet_call(internal_hop(true, false, vm_failed()));
+        assert!(emulation.error().is_some());
+
+        // Failed deployment with the non-bounceable message keeps the value
+        let emulation = wallet_call(internal_hop(false, false, vm_failed()));
+        assert_eq!(emulation.error(), None);
+    }
+}
@@ -1,9 +1,13 @@
+pub use self::emulator::{Emulation, InternalEmulation};
 pub use self::node_tcp_rpc::{
-    ConfigParamWithId, ConfigWithId, NodeStats, NodeTcpRpc, RunningStats, ValidatorSetEntry,
+    AccountStateWithId, BlockHeaderWithId, BlockLookup, ConfigParamWithId, ConfigWithId,
+    NodeRpcError, NodeStats, NodeTcpRpc, RunMethodOutput, RunningStats, SendMessageStatus,
+    ValidatorSetEntry,
 };
 pub use self::node_udp_rpc::NodeUdpRpc;
-pub use self::subscription::Subscription;
+pub use self::subscription::{Subscription, TransactionsCursor, TransactionsPage, TransactionsRx};
 
+mod emulator;
 mod node_tcp_rpc;
 mod node_udp_rpc;
 mod subscription;
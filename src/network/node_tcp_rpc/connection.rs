@@ -0,0 +1,119 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use parking_lot::RwLock;
+
+use super::tcp_adnl::{TcpAdnl, TcpAdnlConfig, TcpAdnlError};
+
+/// Control server connection which is restored after the node restarts.
+///
+/// Dead connections are detected by socket errors and by periodic pings.
+pub struct Connection {
+    config: TcpAdnlConfig,
+    tcp_adnl: RwLock<TcpAdnl>,
+    reconnect_lock: tokio::sync::Mutex<()>,
+}
+
+impl Connection {
+    const MAX_RECONNECT_ATTEMPTS: usize = 4;
+    const MIN_RECONNECT_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(10);
+
+    pub async fn connect(
+        config: TcpAdnlConfig,
+        ping_interval: Duration,
+        ping_timeout: Duration,
+    ) -> Result<Arc<Self>, TcpAdnlError> {
+        let tcp_adnl = TcpAdnl::connect(config.clone()).await?;
+
+        let connection = Arc::new(Self {
+            config,
+            tcp_adnl: RwLock::new(tcp_adnl),
+            reconnect_lock: Default::default(),
+        });
+
+        if !ping_interval.is_zero() {
+            tokio::spawn(keepalive(
+                Arc::downgrade(&connection),
+                ping_interval,
+                ping_timeout,
+            ));
+        }
+
+        Ok(connection)
+    }
+
+    /// Returns an alive connection, reconnects if the current one was closed
+    pub async fn get(&self) -> Result<TcpAdnl, TcpAdnlError> {
+        let tcp_adnl = self.tcp_adnl.read().clone();
+        if !tcp_adnl.is_closed() {
+            return Ok(tcp_adnl);
+        }
+
+        self.reconnect(Self::MAX_RECONNECT_ATTEMPTS).await
+    }
+
+    async fn reconnect(&self, max_attempts: usize) -> Result<TcpAdnl, TcpAdnlError> {
+        let _guard = self.reconnect_lock.lock().await;
+
+        // Connection could have been restored while waiting for the lock
+        let tcp_adnl = self.tcp_adnl.read().clone();
+        if !tcp_adnl.is_closed() {
+            return Ok(tcp_adnl);
+        }
+
+        let mut backoff = Self::MIN_RECONNECT_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match TcpAdnl::connect(self.config.clone()).await {
+                Ok(tcp_adnl) => {
+                    tracing::info!("reconnected to the control server");
+                    *self.tcp_adnl.write() = tcp_adnl.clone();
+                    return Ok(tcp_adnl);
+                }
+                Err(e) if attempt >= max_attempts => return Err(e),
+                Err(e) => {
+                    tracing::warn!(attempt, "failed to reconnect to the control server: {e:?}");
+                    tokio::time::sleep(backoff).await;
+                    backoff = std::cmp::min(backoff * 2, Self::MAX_RECONNECT_BACKOFF);
+                }
+            }
+        }
+    }
+}
+
+async fn keepalive(connection: Weak<Connection>, interval: Duration, timeout: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+
+        // Stop when all clients are dropped
+        let Some(connection) = connection.upgrade() else {
+            break;
+        };
+
+        let tcp_adnl = connection.tcp_adnl.read().clone();
+        if tcp_adnl.is_closed() {
+            // Restore the connection before the next query
+            if let Err(e) = connection.reconnect(1).await {
+                tracing::debug!("failed to reconnect to the control server: {e:?}");
+            }
+            continue;
+        }
+
+        match tcp_adnl.ping(timeout).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!("control server doesn't respond to pings, closing connection");
+                tcp_adnl.close();
+            }
+            Err(e) => tracing::debug!("control server connection closed: {e:?}"),
+        }
+    }
+
+    tracing::debug!("keepalive loop finished");
+}
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
@@ -5,36 +6,43 @@ use everscale_crypto::ed25519;
 use tl_proto::{IntermediateBytes, TlRead, TlWrite};
 use ton_block::Deserializable;
 
+use self::connection::Connection;
 use self::stats::StatsError;
 pub use self::stats::{NodeStats, RunningStats, ValidatorSetEntry};
-use self::tcp_adnl::{TcpAdnl, TcpAdnlConfig, TcpAdnlError};
+use self::tcp_adnl::{TcpAdnlConfig, TcpAdnlError};
 use crate::config::AppConfigControl;
+use crate::util::{split_address, TransactionWithHash};
 
+mod connection;
 mod proto;
 mod stats;
 mod tcp_adnl;
 
 #[derive(Clone)]
 pub struct NodeTcpRpc {
-    tcp_adnl: TcpAdnl,
+    connection: Arc<Connection>,
     query_timeout: Duration,
 }
 
 impl NodeTcpRpc {
     pub async fn new(config: &AppConfigControl) -> Result<Self> {
-        let tcp_adnl = TcpAdnl::connect(TcpAdnlConfig {
-            server_address: config.server_address.into(),
-            server_pubkey: config.server_pubkey,
-            client_secret: config.client_secret,
-            connection_timeout: config.connection_timeout,
-        })
+        let query_timeout = config.query_timeout;
+
+        let connection = Connection::connect(
+            TcpAdnlConfig {
+                server_address: config.server_address.into(),
+                server_pubkey: config.server_pubkey,
+                client_secret: config.client_secret,
+                connection_timeout: config.connection_timeout,
+            },
+            config.ping_interval,
+            query_timeout,
+        )
         .await
         .map_err(NodeRpcError::ConnectionFailed)?;
 
-        let query_timeout = config.query_timeout;
-
         Ok(Self {
-            tcp_adnl,
+            connection,
             query_timeout,
         })
     }
@@ -99,13 +107,17 @@ impl NodeTcpRpc {
             .map(expect_success)
     }
 
-    pub async fn send_message<T: AsRef<[u8]>>(&self, message: T) -> Result<()> {
-        // NOTE: proto::Success is used here on purpose instead of SendMsgStatus
-        self.query(proto::SendMessage {
-            body: message.as_ref(),
+    pub async fn send_message<T: AsRef<[u8]>>(&self, message: T) -> Result<SendMessageStatus> {
+        let status = self
+            .query(proto::SendMessage {
+                body: message.as_ref(),
+            })
+            .await?;
+
+        Ok(match status {
+            proto::SendMsgStatus::Status(status) => SendMessageStatus::Status(status),
+            proto::SendMsgStatus::Success => SendMessageStatus::Success,
         })
-        .await
-        .map(expect_success)
     }
 
     pub async fn get_config_all(&self) -> Result<ConfigWithId> {
@@ -161,26 +173,271 @@ impl NodeTcpRpc {
         }
     }
 
+    /// Returns the account state at the specified masterchain block
+    pub async fn get_account_state_at(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        block_id: &ton_block::BlockIdExt,
+    ) -> Result<AccountStateWithId> {
+        let (workchain, account) = split_address(address)?;
+
+        let proto::AccountState {
+            id,
+            shardblk,
+            state,
+            ..
+        } = self
+            .query(proto::GetAccountState {
+                id: convert_block_id_to_proto(block_id),
+                account: proto::AccountId {
+                    workchain,
+                    id: account.as_slice(),
+                },
+            })
+            .await?;
+
+        let account = if state.is_empty() {
+            ton_block::Account::AccountNone
+        } else {
+            ton_block::Account::construct_from_bytes(&state)
+                .map_err(|_| NodeRpcError::InvalidAccountState)?
+        };
+
+        Ok(AccountStateWithId {
+            block_id: convert_proto_to_block_id(id)?,
+            shard_block_id: convert_proto_to_block_id(shardblk)?,
+            account,
+        })
+    }
+
+    /// Runs the get-method of the account at the specified masterchain block.
+    ///
+    /// `params` is a serialized `VmStack` with method arguments
+    pub async fn run_get_method(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        block_id: &ton_block::BlockIdExt,
+        method_id: u64,
+        params: &[u8],
+    ) -> Result<RunMethodOutput> {
+        /// Request only the result without proofs
+        const MODE_RESULT: u32 = 0b100;
+
+        let (workchain, account) = split_address(address)?;
+
+        let proto::RunMethodResult {
+            id,
+            shardblk,
+            exit_code,
+            result,
+            ..
+        } = self
+            .query(proto::RunSmcMethod {
+                mode: MODE_RESULT,
+                id: convert_block_id_to_proto(block_id),
+                account: proto::AccountId {
+                    workchain,
+                    id: account.as_slice(),
+                },
+                method_id,
+                params,
+            })
+            .await?;
+
+        Ok(RunMethodOutput {
+            block_id: convert_proto_to_block_id(id)?,
+            shard_block_id: convert_proto_to_block_id(shardblk)?,
+            exit_code,
+            stack: result,
+        })
+    }
+
+    /// Finds the shard block by seqno, logical time or unix time
+    pub async fn lookup_block(
+        &self,
+        shard: &ton_block::ShardIdent,
+        lookup: BlockLookup,
+    ) -> Result<BlockHeaderWithId> {
+        let id = |seqno| proto::BlockId {
+            workchain: shard.workchain_id(),
+            shard: shard.shard_prefix_with_tag(),
+            seqno,
+        };
+
+        let header = match lookup {
+            BlockLookup::Seqno(seqno) => {
+                self.query(proto::LookupBlockBySeqno {
+                    mode: 0b001,
+                    id: id(seqno),
+                })
+                .await?
+            }
+            BlockLookup::Lt(lt) => {
+                self.query(proto::LookupBlockByLt {
+                    mode: 0b010,
+                    id: id(0),
+                    lt,
+                })
+                .await?
+            }
+            BlockLookup::Utime(utime) => {
+                self.query(proto::LookupBlockByUtime {
+                    mode: 0b100,
+                    id: id(0),
+                    utime,
+                })
+                .await?
+            }
+        };
+
+        parse_block_header(header)
+    }
+
+    /// Returns at most `count` account transactions, starting from the specified one
+    /// and going back in time
+    pub async fn get_transactions(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        lt: u64,
+        hash: &ton_types::UInt256,
+        count: u32,
+    ) -> Result<Vec<TransactionWithHash>> {
+        let (workchain, account) = split_address(address)?;
+
+        let proto::TransactionList { transactions, .. } = self
+            .query(proto::GetTransactions {
+                count,
+                account: proto::AccountId {
+                    workchain,
+                    id: account.as_slice(),
+                },
+                lt,
+                hash: hash.as_slice(),
+            })
+            .await?;
+
+        if transactions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let roots = ton_types::deserialize_cells_tree(&mut transactions.as_slice())
+            .map_err(|_| NodeRpcError::InvalidTransactions)?;
+        roots
+            .into_iter()
+            .map(|cell| {
+                Ok(TransactionWithHash {
+                    hash: cell.repr_hash(),
+                    data: ton_block::Transaction::construct_from_cell(cell)
+                        .map_err(|_| NodeRpcError::InvalidTransactions)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Fetches at most `limit` account transactions going back in time.
+    ///
+    /// Starts from the specified transaction or from the latest one if `from` is `None`
+    pub async fn get_account_transactions(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        from: Option<(u64, ton_types::UInt256)>,
+        limit: usize,
+    ) -> Result<Vec<TransactionWithHash>> {
+        const BATCH_SIZE: usize = 16;
+
+        let (mut lt, mut hash) = match from {
+            Some(from) => from,
+            None => {
+                let state = self.get_shard_account_state(address).await?;
+                (state.last_trans_lt(), state.last_trans_hash().clone())
+            }
+        };
+
+        let mut result = Vec::new();
+        while lt != 0 && result.len() < limit {
+            let count = std::cmp::min(limit - result.len(), BATCH_SIZE);
+            let batch = self
+                .get_transactions(address, lt, &hash, count as u32)
+                .await?;
+
+            let Some(last) = batch.last() else {
+                break;
+            };
+            lt = last.data.prev_trans_lt;
+            hash = last.data.prev_trans_hash.clone();
+
+            result.extend(batch);
+        }
+
+        Ok(result)
+    }
+
     async fn query<Q, R>(&self, query: Q) -> Result<R>
     where
         Q: TlWrite<Repr = tl_proto::Boxed>,
         for<'a> R: TlRead<'a>,
     {
-        match self
-            .tcp_adnl
+        let tcp_adnl = self
+            .connection
+            .get()
+            .await
+            .map_err(NodeRpcError::ConnectionFailed)?;
+
+        let answer = match tcp_adnl
             .query(
                 proto::ControlQuery(IntermediateBytes(query)),
                 self.query_timeout,
             )
             .await
         {
-            Ok(Some(data)) => Ok(data),
-            Ok(None) => Err(NodeRpcError::QueryTimeout.into()),
-            Err(e) => Err(NodeRpcError::QueryFailed(e).into()),
+            Ok(Some(answer)) => answer,
+            Ok(None) => return Err(NodeRpcError::QueryTimeout.into()),
+            Err(e) => return Err(NodeRpcError::QueryFailed(e).into()),
+        };
+
+        match tl_proto::deserialize::<R>(&answer) {
+            Ok(data) => Ok(data),
+            // Node rejected the query
+            Err(e) => match tl_proto::deserialize::<proto::Error>(&answer) {
+                Ok(proto::Error::LiteServer(error) | proto::Error::ControlQuery(error)) => {
+                    Err(NodeRpcError::NodeError {
+                        code: error.code,
+                        message: String::from_utf8_lossy(&error.message).into_owned(),
+                    }
+                    .into())
+                }
+                Err(_) => Err(NodeRpcError::InvalidAnswer(e).into()),
+            },
         }
     }
 }
 
+fn parse_block_header(header: proto::BlockHeader) -> Result<BlockHeaderWithId> {
+    let block_id = convert_proto_to_block_id(header.id)?;
+
+    let info =
+        read_block_info(&header.header_proof).map_err(|_| NodeRpcError::InvalidBlockHeader)?;
+    Ok(BlockHeaderWithId { block_id, info })
+}
+
+/// Reads block info from the block header Merkle proof
+fn read_block_info(mut header_proof: &[u8]) -> Result<ton_block::BlockInfo> {
+    let cell = ton_types::deserialize_tree_of_cells(&mut header_proof)?;
+    let block: ton_block::Block =
+        ton_block::MerkleProof::construct_from_cell(cell)?.virtualize()?;
+    Ok(block.read_info()?)
+}
+
+fn convert_block_id_to_proto(id: &ton_block::BlockIdExt) -> proto::BlockIdExt<'_> {
+    proto::BlockIdExt {
+        workchain: id.shard_id.workchain_id(),
+        shard: id.shard_id.shard_prefix_with_tag(),
+        seqno: id.seq_no,
+        root_hash: id.root_hash.as_slice(),
+        file_hash: id.file_hash.as_slice(),
+    }
+}
+
 fn convert_proto_to_block_id(
     id: proto::BlockIdExtOwned,
 ) -> Result<ton_block::BlockIdExt, NodeRpcError> {
@@ -203,6 +460,41 @@ pub struct ConfigParamWithId {
     pub param: String,
 }
 
+pub struct AccountStateWithId {
+    pub block_id: ton_block::BlockIdExt,
+    pub shard_block_id: ton_block::BlockIdExt,
+    pub account: ton_block::Account,
+}
+
+pub struct RunMethodOutput {
+    pub block_id: ton_block::BlockIdExt,
+    pub shard_block_id: ton_block::BlockIdExt,
+    pub exit_code: i32,
+    /// Serialized `VmStack` (empty if the method failed)
+    pub stack: Vec<u8>,
+}
+
+pub struct BlockHeaderWithId {
+    pub block_id: ton_block::BlockIdExt,
+    pub info: ton_block::BlockInfo,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum BlockLookup {
+    Seqno(u32),
+    Lt(u64),
+    Utime(u32),
+}
+
+/// Node answer to the broadcasted external message
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SendMessageStatus {
+    /// Message was accepted without an explicit status
+    Success,
+    /// Status code returned by the node
+    Status(i32),
+}
+
 fn expect_success(_: proto::Success) {}
 
 #[derive(thiserror::Error, Debug)]
@@ -213,6 +505,10 @@ pub enum NodeRpcError {
     QueryFailed(#[source] TcpAdnlError),
     #[error("query timeout")]
     QueryTimeout,
+    #[error("node error {code}: {message}")]
+    NodeError { code: i32, message: String },
+    #[error("invalid answer")]
+    InvalidAnswer(#[source] tl_proto::TlError),
     #[error("invalid stats")]
     InvalidStats(#[source] StatsError),
     #[error("invalid pubkey")]
@@ -227,4 +523,8 @@ pub enum NodeRpcError {
     InvalidBlockId,
     #[error("invalid blockchain config")]
     InvalidBlockchainConfig,
+    #[error("invalid transactions")]
+    InvalidTransactions,
+    #[error("invalid block header")]
+    InvalidBlockHeader,
 }
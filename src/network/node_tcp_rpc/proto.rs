@@ -88,6 +88,67 @@ pub struct GetConfigParams<'tl> {
     pub param_list: &'tl [u32],
 }
 
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "liteServer.getTransactions", scheme = "proto.tl")]
+pub struct GetTransactions<'tl> {
+    pub count: u32,
+    pub account: AccountId<'tl>,
+    pub lt: u64,
+    #[tl(size_hint = 32)]
+    pub hash: HashRef<'tl>,
+}
+
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "liteServer.getAccountState", scheme = "proto.tl")]
+pub struct GetAccountState<'tl> {
+    pub id: BlockIdExt<'tl>,
+    pub account: AccountId<'tl>,
+}
+
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "liteServer.runSmcMethod", scheme = "proto.tl")]
+pub struct RunSmcMethod<'tl> {
+    pub mode: u32,
+    pub id: BlockIdExt<'tl>,
+    pub account: AccountId<'tl>,
+    pub method_id: u64,
+    /// Serialized `VmStack`
+    pub params: &'tl [u8],
+}
+
+/// `liteServer.lookupBlock` with mode 1
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "liteServer.lookupBlock", scheme = "proto.tl")]
+pub struct LookupBlockBySeqno {
+    pub mode: u32,
+    pub id: BlockId,
+}
+
+/// `liteServer.lookupBlock` with mode 2
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "liteServer.lookupBlock", scheme = "proto.tl")]
+pub struct LookupBlockByLt {
+    pub mode: u32,
+    pub id: BlockId,
+    pub lt: u64,
+}
+
+/// `liteServer.lookupBlock` with mode 4
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "liteServer.lookupBlock", scheme = "proto.tl")]
+pub struct LookupBlockByUtime {
+    pub mode: u32,
+    pub id: BlockId,
+    pub utime: u32,
+}
+
+#[derive(Copy, Clone, TlWrite)]
+#[tl(size_hint = 36)]
+pub struct AccountId<'tl> {
+    pub workchain: i32,
+    pub id: HashRef<'tl>,
+}
+
 #[derive(Copy, Clone, TlWrite)]
 #[tl(boxed, id = "raw.getShardAccountState", scheme = "proto.tl")]
 pub struct GetShardAccountState<'tl> {
@@ -116,6 +177,14 @@ impl Default for BlockIdExt<'static> {
     }
 }
 
+#[derive(Copy, Clone, TlWrite)]
+#[tl(size_hint = 16)]
+pub struct BlockId {
+    pub workchain: i32,
+    pub shard: u64,
+    pub seqno: u32,
+}
+
 #[derive(Copy, Clone, Debug, TlRead, TlWrite)]
 #[tl(size_hint = 80)]
 pub struct BlockIdExtOwned {
@@ -130,6 +199,18 @@ pub struct BlockIdExtOwned {
 #[tl(boxed, id = "engine.validator.success", scheme = "proto.tl")]
 pub struct Success;
 
+/// Answer to the `liteServer.sendMessage` query.
+///
+/// Old nodes reply with `engine.validator.success` instead of the status
+#[derive(Copy, Clone, TlRead)]
+#[tl(boxed, scheme = "proto.tl")]
+pub enum SendMsgStatus {
+    #[tl(id = "liteServer.sendMsgStatus")]
+    Status(i32),
+    #[tl(id = "engine.validator.success")]
+    Success,
+}
+
 #[derive(Copy, Clone, TlRead)]
 #[tl(boxed, id = "engine.validator.keyHash", scheme = "proto.tl")]
 pub struct KeyHash {
@@ -163,6 +244,60 @@ pub struct ConfigInfo {
     pub config_proof: Vec<u8>,
 }
 
+#[derive(Clone, TlRead)]
+#[tl(boxed, id = "liteServer.transactionList", scheme = "proto.tl")]
+pub struct TransactionList {
+    pub ids: Vec<BlockIdExtOwned>,
+    pub transactions: Vec<u8>,
+}
+
+#[derive(Clone, TlRead)]
+#[tl(boxed, id = "liteServer.accountState", scheme = "proto.tl")]
+pub struct AccountState {
+    pub id: BlockIdExtOwned,
+    pub shardblk: BlockIdExtOwned,
+    pub shard_proof: Vec<u8>,
+    pub proof: Vec<u8>,
+    /// Serialized `Account` (empty if the account doesn't exist)
+    pub state: Vec<u8>,
+}
+
+/// Result of `liteServer.runSmcMethod` with mode 4 (only the result is requested)
+#[derive(Clone, TlRead)]
+#[tl(boxed, id = "liteServer.runMethodResult", scheme = "proto.tl")]
+pub struct RunMethodResult {
+    pub mode: u32,
+    pub id: BlockIdExtOwned,
+    pub shardblk: BlockIdExtOwned,
+    pub exit_code: i32,
+    /// Serialized `VmStack`
+    pub result: Vec<u8>,
+}
+
+#[derive(Clone, TlRead)]
+#[tl(boxed, id = "liteServer.blockHeader", scheme = "proto.tl")]
+pub struct BlockHeader {
+    pub id: BlockIdExtOwned,
+    pub mode: u32,
+    pub header_proof: Vec<u8>,
+}
+
+/// Error object which is returned by the node instead of the answer
+#[derive(Clone, TlRead)]
+#[tl(boxed, scheme = "proto.tl")]
+pub enum Error {
+    #[tl(id = "liteServer.error")]
+    LiteServer(ErrorMessage),
+    #[tl(id = "engine.validator.controlQueryError")]
+    ControlQuery(ErrorMessage),
+}
+
+#[derive(Clone, TlRead)]
+pub struct ErrorMessage {
+    pub code: i32,
+    pub message: Vec<u8>,
+}
+
 #[derive(Clone, TlRead)]
 #[tl(boxed, scheme = "proto.tl")]
 pub enum ShardAccount {
@@ -17,6 +17,7 @@ use self::queries_cache::QueriesCache;
 
 mod queries_cache;
 
+#[derive(Clone)]
 pub struct TcpAdnlConfig {
     pub server_address: SocketAddr,
     pub server_pubkey: ed25519::PublicKey,
@@ -91,10 +92,14 @@ impl TcpAdnl {
         Ok(Self { state })
     }
 
-    pub async fn query<Q, R>(&self, query: Q, timeout: Duration) -> Result<Option<R>, TcpAdnlError>
+    /// Sends the query and returns the raw answer (or `None` on timeout)
+    pub async fn query<Q>(
+        &self,
+        query: Q,
+        timeout: Duration,
+    ) -> Result<Option<Vec<u8>>, TcpAdnlError>
     where
         Q: TlWrite<Repr = tl_proto::Boxed>,
-        for<'a> R: TlRead<'a>,
     {
         let cancelled = self.state.cancellation_token.cancelled();
         if self.state.cancellation_token.is_cancelled() {
@@ -120,19 +125,46 @@ impl TcpAdnl {
             return Err(TcpAdnlError::SocketClosed);
         }
 
-        let answer = tokio::select! {
+        tokio::select! {
             res = tokio::time::timeout(timeout, pending_query.wait()) => {
-                res.ok().flatten()
+                Ok(res.ok().flatten())
             }
-            _  = cancelled => return Err(TcpAdnlError::SocketClosed),
-        };
+            _  = cancelled => Err(TcpAdnlError::SocketClosed),
+        }
+    }
+
+    /// Sends `tcp.ping` and waits for `tcp.pong`.
+    ///
+    /// Returns `false` if there was no pong during the timeout.
+    pub async fn ping(&self, timeout: Duration) -> Result<bool, TcpAdnlError> {
+        let cancelled = self.state.cancellation_token.cancelled();
+        if self.state.cancellation_token.is_cancelled() {
+            return Err(TcpAdnlError::SocketClosed);
+        }
+
+        let random_id = rand::thread_rng().gen();
+        let data = tl_proto::serialize(TcpPing { random_id });
+
+        let pending_pong = self.state.queries_cache.add_query(ping_query_id(random_id));
+        if self.state.packets_tx.send(Packet::encrypted(data)).is_err() {
+            return Err(TcpAdnlError::SocketClosed);
+        }
 
-        Ok(match answer {
-            Some(query) => {
-                Some(tl_proto::deserialize(&query).map_err(TcpAdnlError::InvalidAnswer)?)
+        tokio::select! {
+            res = tokio::time::timeout(timeout, pending_pong.wait()) => {
+                Ok(matches!(res, Ok(Some(_))))
             }
-            None => None,
-        })
+            _ = cancelled => Err(TcpAdnlError::SocketClosed),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.cancellation_token.is_cancelled()
+    }
+
+    /// Stops socket loops, all pending and further queries will fail
+    pub fn close(&self) {
+        self.state.cancellation_token.cancel();
     }
 }
 
@@ -261,7 +293,12 @@ async fn socket_reader<T>(
             Ok(AdnlMessageAnswer { query_id, data }) => {
                 queries_cache.update_query(query_id, data);
             }
-            Err(e) => tracing::warn!("invalid response: {e:?}"),
+            Err(e) => match tl_proto::deserialize::<TcpPong>(&buffer) {
+                Ok(TcpPong { random_id }) => {
+                    queries_cache.update_query(&ping_query_id(random_id), &[]);
+                }
+                Err(_) => tracing::warn!("invalid response: {e:?}"),
+            },
         };
     }
 
@@ -345,6 +382,27 @@ struct AdnlMessageAnswer<'tl> {
     data: &'tl [u8],
 }
 
+/// Pings share the queries cache, so their ids must not intersect with query ids
+fn ping_query_id(random_id: u64) -> [u8; 32] {
+    let mut query_id = [0xff; 32];
+    query_id[..8].copy_from_slice(&random_id.to_le_bytes());
+    query_id
+}
+
+#[derive(Copy, Clone, TlWrite)]
+#[tl(boxed, id = "tcp.ping", scheme = "proto.tl")]
+struct TcpPing {
+    #[tl(size_hint = 8)]
+    random_id: u64,
+}
+
+#[derive(Copy, Clone, TlRead)]
+#[tl(boxed, id = "tcp.pong", scheme = "proto.tl")]
+struct TcpPong {
+    #[tl(size_hint = 8)]
+    random_id: u64,
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum TcpAdnlError {
     #[error("connection timeout")]
@@ -353,8 +411,6 @@ pub enum TcpAdnlError {
     ConnectionError(#[source] std::io::Error),
     #[error("socket closed")]
     SocketClosed,
-    #[error("invalid answer")]
-    InvalidAnswer(#[source] tl_proto::TlError),
 }
 
 pub type Aes256Ctr = ctr::Ctr64BE<aes::Aes256>;
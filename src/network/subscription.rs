@@ -1,5 +1,6 @@
 use std::collections::hash_map;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 
 use anyhow::{Context, Result};
@@ -11,7 +12,10 @@ use tokio::sync::{mpsc, oneshot, Notify};
 use tokio_util::sync::{CancellationToken, DropGuard};
 use ton_block::{Deserializable, Serializable};
 
-use super::node_tcp_rpc::NodeTcpRpc;
+use super::emulator::{self, Emulation, InternalEmulation};
+use super::node_tcp_rpc::{
+    AccountStateWithId, BlockHeaderWithId, BlockLookup, NodeRpcError, NodeTcpRpc,
+};
 use super::node_udp_rpc::NodeUdpRpc;
 use crate::util::{split_address, BlockStuff, FxDashMap, TransactionWithHash};
 
@@ -19,6 +23,7 @@ pub struct Subscription {
     node_tcp_rpc: NodeTcpRpc,
     node_udp_rpc: NodeUdpRpc,
     last_mc_block: ArcSwapOption<StoredMcBlock>,
+    emulate_messages: AtomicBool,
     subscription_count: AtomicUsize,
     subscriptions_changed: Arc<Notify>,
     mc_subscriptions: AccountSubscriptions,
@@ -34,6 +39,7 @@ impl Subscription {
             node_tcp_rpc,
             node_udp_rpc,
             last_mc_block: Default::default(),
+            emulate_messages: Default::default(),
             subscription_count: Default::default(),
             subscriptions_changed: Default::default(),
             mc_subscriptions: Default::default(),
@@ -68,6 +74,13 @@ impl Subscription {
         Ok(())
     }
 
+    /// Execute external messages locally before sending them.
+    ///
+    /// Messages which would fail are not broadcasted
+    pub fn set_emulate_messages(&self, enabled: bool) {
+        self.emulate_messages.store(enabled, Ordering::Release);
+    }
+
     pub fn tcp_rpc(&self) -> &NodeTcpRpc {
         &self.node_tcp_rpc
     }
@@ -111,13 +124,149 @@ impl Subscription {
         }
     }
 
-    pub async fn send_message_with_retires<F>(&self, mut f: F) -> Result<TransactionWithHash>
+    /// Returns the account state at the specified masterchain block.
+    ///
+    /// Falls back to the current account state if the node doesn't support
+    /// liteServer queries. It is only possible when the account was not changed
+    /// after the block, otherwise the historical state can't be restored.
+    pub async fn get_account_state_at(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        block_id: &ton_block::BlockIdExt,
+    ) -> Result<AccountStateWithId> {
+        match self
+            .node_tcp_rpc
+            .get_account_state_at(address, block_id)
+            .await
+        {
+            Ok(state) => Ok(state),
+            Err(e) if matches!(e.downcast_ref(), Some(NodeRpcError::NodeError { .. })) => {
+                tracing::debug!("falling back to the current account state: {e:?}");
+
+                let mc_block = self.node_udp_rpc.get_block(block_id).await?;
+                let shard_block_id = if address.workchain_id() == ton_block::MASTERCHAIN_ID {
+                    block_id.clone()
+                } else {
+                    let prefix = ton_block::AccountIdPrefixFull::prefix(address)?;
+                    mc_block
+                        .shard_blocks()?
+                        .into_values()
+                        .find(|id| id.shard_id.contains_full_prefix(&prefix))
+                        .context("account shard not found")?
+                };
+                let shard_block = match &shard_block_id == block_id {
+                    true => mc_block,
+                    false => self.node_udp_rpc.get_block(&shard_block_id).await?,
+                };
+                let end_lt = shard_block.block().read_info()?.end_lt();
+
+                let state = self.node_tcp_rpc.get_shard_account_state(address).await?;
+                let last_trans_lt = state.last_trans_lt();
+                anyhow::ensure!(
+                    last_trans_lt != 0 && last_trans_lt < end_lt,
+                    "account doesn't exist or was changed after the block, \
+                    its state at the block requires liteServer.getAccountState support"
+                );
+
+                Ok(AccountStateWithId {
+                    block_id: block_id.clone(),
+                    shard_block_id,
+                    account: state.read_account()?,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Finds the shard block by seqno, logical time or unix time.
+    ///
+    /// Falls back to walking blocks back from the latest one if the node
+    /// doesn't support liteServer queries.
+    pub async fn lookup_block(
+        &self,
+        shard: &ton_block::ShardIdent,
+        lookup: BlockLookup,
+    ) -> Result<BlockHeaderWithId> {
+        match self.node_tcp_rpc.lookup_block(shard, lookup).await {
+            Ok(header) => Ok(header),
+            Err(e) if matches!(e.downcast_ref(), Some(NodeRpcError::NodeError { .. })) => {
+                tracing::debug!("falling back to blocks walk: {e:?}");
+                self.walk_lookup_block(shard, lookup).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Goes back from the latest shard block until the block which matches the lookup
+    async fn walk_lookup_block(
+        &self,
+        shard: &ton_block::ShardIdent,
+        lookup: BlockLookup,
+    ) -> Result<BlockHeaderWithId> {
+        let prefix = ton_block::AccountIdPrefixFull {
+            workchain_id: shard.workchain_id(),
+            prefix: shard.shard_prefix_with_tag(),
+        };
+
+        let last_mc_block = self.get_last_mc_block().await?;
+        let mut block_id = if shard.is_masterchain() {
+            last_mc_block.data.id().clone()
+        } else {
+            last_mc_block
+                .data
+                .shard_blocks()?
+                .into_values()
+                .find(|id| id.shard_id.contains_full_prefix(&prefix))
+                .context("shard not found")?
+        };
+
+        loop {
+            let block = self.node_udp_rpc.get_block(&block_id).await?;
+            let info = block.block().read_info()?;
+
+            let found = match lookup {
+                BlockLookup::Seqno(seqno) => {
+                    if block_id.seq_no == seqno && &block_id.shard_id == shard {
+                        true
+                    } else {
+                        anyhow::ensure!(block_id.seq_no > seqno, "block not found");
+                        false
+                    }
+                }
+                BlockLookup::Lt(lt) => {
+                    if info.start_lt() <= lt {
+                        anyhow::ensure!(lt < info.end_lt(), "block not found");
+                        true
+                    } else {
+                        false
+                    }
+                }
+                BlockLookup::Utime(utime) => info.gen_utime().0 <= utime,
+            };
+            if found {
+                return Ok(BlockHeaderWithId { block_id, info });
+            }
+
+            // The first block refers to the zerostate
+            anyhow::ensure!(block_id.seq_no > 1, "block not found");
+
+            // Follow the shard through splits and merges
+            let info = block.read_brief_info()?;
+            block_id = match info.prev2 {
+                Some(prev2) if prev2.shard_id.contains_full_prefix(&prefix) => prev2,
+                _ => info.prev1,
+            };
+        }
+    }
+
+    pub async fn send_message_with_retires<F, R>(&self, mut f: F) -> Result<TransactionWithHash>
     where
-        F: FnMut(u32) -> Result<(ton_block::Message, u32)>,
+        F: FnMut(u32) -> R,
+        R: Future<Output = Result<(ton_block::Message, u32)>>,
     {
         let timeout = 60;
         loop {
-            let (message, expire_at) = f(timeout)?;
+            let (message, expire_at) = f(timeout).await?;
             if let Some(tx) = self.send_message(&message, expire_at).await? {
                 break Ok(tx);
             }
@@ -141,6 +290,23 @@ impl Subscription {
         let msg_hash = msg_cell.repr_hash();
         let data = ton_types::serialize_toc(&msg_cell)?;
 
+        // Check the message before paying for it
+        if self.emulate_messages.load(Ordering::Acquire) {
+            let emulation = self
+                .emulate_message(message, broxus_util::now())
+                .await
+                .context("failed to emulate external message")?;
+            if let Some(error) = emulation.error() {
+                anyhow::bail!("external message emulation failed: {error}");
+            }
+            tracing::debug!(
+                dst = %raw_dst,
+                ?msg_hash,
+                total_fees = emulation.total_fees,
+                "external message emulated"
+            );
+        }
+
         // Find pending messages map
         let subscriptions = match workchain {
             ton_block::MASTERCHAIN_ID => &self.mc_subscriptions,
@@ -173,29 +339,33 @@ impl Subscription {
         };
 
         // Send the message
-        if let Err(e) = self.node_tcp_rpc.send_message(data).await {
-            // Remove pending message from the map before returning an error
-            match subscriptions.entry(dst) {
-                dashmap::mapref::entry::Entry::Occupied(mut entry) => {
-                    let should_remove = {
-                        let subscription = entry.get_mut();
-                        subscription.pending_messages.remove(&msg_hash);
-                        self.subscription_count.fetch_sub(1, Ordering::Release);
-                        self.subscriptions_changed.notify_waiters();
-                        subscription.is_empty()
-                    };
+        let sent_at = broxus_util::now();
+        let status = match self.node_tcp_rpc.send_message(data).await {
+            Ok(status) => status,
+            Err(e) => {
+                // Remove pending message from the map before returning an error
+                match subscriptions.entry(dst) {
+                    dashmap::mapref::entry::Entry::Occupied(mut entry) => {
+                        let should_remove = {
+                            let subscription = entry.get_mut();
+                            subscription.pending_messages.remove(&msg_hash);
+                            self.subscription_count.fetch_sub(1, Ordering::Release);
+                            self.subscriptions_changed.notify_waiters();
+                            subscription.is_empty()
+                        };
 
-                    if should_remove {
-                        entry.remove();
+                        if should_remove {
+                            entry.remove();
+                        }
                     }
-                }
-                dashmap::mapref::entry::Entry::Vacant(_) => {
-                    tracing::warn!("pending messages entry not found");
-                }
-            };
-            return Err(e);
-        }
-        tracing::debug!(dst = %raw_dst, ?msg_hash, "external message broadcasted");
+                    dashmap::mapref::entry::Entry::Vacant(_) => {
+                        tracing::warn!("pending messages entry not found");
+                    }
+                };
+                return Err(e);
+            }
+        };
+        tracing::debug!(dst = %raw_dst, ?msg_hash, ?status, "external message broadcasted");
 
         // Wait for the message execution
         let tx = rx.await?;
@@ -208,18 +378,112 @@ impl Subscription {
                     "external message delivered"
                 );
             }
-            None => {
-                tracing::warn!(
-                    dst = %raw_dst,
-                    ?msg_hash,
-                    "external message expired"
-                );
-            }
+            None => match self.diagnose_expired_message(message, sent_at).await {
+                Ok(diagnostic) => {
+                    tracing::warn!(
+                        dst = %raw_dst,
+                        ?msg_hash,
+                        balance = diagnostic.balance,
+                        can_afford = ?diagnostic.can_afford,
+                        "external message expired: {}",
+                        diagnostic.reason
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        dst = %raw_dst,
+                        ?msg_hash,
+                        "external message expired (diagnostic failed: {e:?})"
+                    );
+                }
+            },
         }
 
         Ok(tx)
     }
 
+    /// Executes the external message locally on top of the current account state
+    /// as if it was included into the block at `utime`, together with the produced
+    /// internal messages
+    pub async fn emulate_message(
+        &self,
+        message: &ton_block::Message,
+        utime: u32,
+    ) -> Result<Emulation> {
+        let dst = match message.ext_in_header() {
+            Some(header) => &header.dst,
+            None => anyhow::bail!("expected external message"),
+        };
+
+        let (shard_account, config) = futures_util::future::join(
+            self.node_tcp_rpc.get_shard_account_state(dst),
+            self.node_tcp_rpc.get_config_all(),
+        )
+        .await;
+        let shard_account = shard_account.context("failed to get shard account state")?;
+        let config = config.context("failed to get blockchain config")?.config;
+
+        let mut emulation =
+            emulator::emulate_message(config.clone(), &shard_account, message, utime)?;
+
+        // Contracts are usually called through the wallet, so the produced
+        // internal messages are also executed (only one hop)
+        for (dst, message) in emulation.internal_messages()? {
+            let shard_account = self
+                .node_tcp_rpc
+                .get_shard_account_state(&dst)
+                .await
+                .context("failed to get destination account state")?;
+            let result = emulator::emulate_message(config.clone(), &shard_account, &message, utime)
+                .with_context(|| format!("failed to emulate internal message to {dst}"))?;
+            emulation.internal.push(InternalEmulation {
+                dst,
+                bounce: message
+                    .int_header()
+                    .map(|header| header.bounce)
+                    .unwrap_or_default(),
+                emulation: result,
+            });
+        }
+
+        Ok(emulation)
+    }
+
+    /// Executes the expired message locally to find out why it wasn't included into blocks.
+    ///
+    /// The message is executed at the time it was broadcasted, otherwise
+    /// it would always be rejected by the expiration check
+    async fn diagnose_expired_message(
+        &self,
+        message: &ton_block::Message,
+        sent_at: u32,
+    ) -> Result<ExpiredMessageDiagnostic> {
+        let emulation = self.emulate_message(message, sent_at).await?;
+
+        let can_afford = if emulation.insufficient_balance {
+            Some(false)
+        } else if emulation.success {
+            Some(true)
+        } else {
+            None
+        };
+
+        let reason = match emulation.error() {
+            Some(error) => error,
+            None => format!(
+                "local execution succeeded (fees: {}), \
+                message was probably not delivered to validators",
+                emulation.total_fees
+            ),
+        };
+
+        Ok(ExpiredMessageDiagnostic {
+            balance: emulation.balance,
+            can_afford,
+            reason,
+        })
+    }
+
     pub fn subscribe(&self, address: &ton_block::MsgAddressInt) -> TransactionsRx {
         let (tx, rx) = mpsc::unbounded_channel();
         let subscriptions = if address.workchain_id() == ton_block::MASTERCHAIN_ID {
@@ -242,6 +506,154 @@ impl Subscription {
         rx
     }
 
+    /// Fetches at most `limit` account transactions going back in time.
+    ///
+    /// Starts from the specified transaction or from the latest one if `from` is `None`.
+    /// Falls back to walking blocks if the node doesn't support liteServer queries.
+    pub async fn get_account_transactions(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        from: Option<TransactionsCursor>,
+        limit: usize,
+    ) -> Result<TransactionsPage> {
+        match self
+            .node_tcp_rpc
+            .get_account_transactions(
+                address,
+                from.as_ref().map(|from| (from.lt, from.hash.clone())),
+                limit,
+            )
+            .await
+        {
+            Ok(transactions) => {
+                let next = transactions
+                    .last()
+                    .filter(|tx| tx.data.prev_trans_lt != 0)
+                    .map(|tx| TransactionsCursor {
+                        lt: tx.data.prev_trans_lt,
+                        hash: tx.data.prev_trans_hash.clone(),
+                        block_id: None,
+                    });
+                Ok(TransactionsPage { transactions, next })
+            }
+            Err(e) if matches!(e.downcast_ref(), Some(NodeRpcError::NodeError { .. })) => {
+                tracing::debug!("falling back to blocks walk: {e:?}");
+                self.walk_account_transactions(address, from, limit).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Collects account transactions from the blocks of the account shard.
+    ///
+    /// Goes back from the cursor block (or from the last masterchain block)
+    /// until the block which contains the required transaction.
+    async fn walk_account_transactions(
+        &self,
+        address: &ton_block::MsgAddressInt,
+        from: Option<TransactionsCursor>,
+        limit: usize,
+    ) -> Result<TransactionsPage> {
+        use ton_block::HashmapAugType;
+
+        let prefix = ton_block::AccountIdPrefixFull::prefix(address)?;
+        let account =
+            ton_types::UInt256::from_le_bytes(&address.address().get_bytestring_on_stack(0));
+
+        let (mut lt, mut hash, block_id) = match from {
+            Some(from) => (from.lt, from.hash, from.block_id),
+            None => {
+                let state = self.node_tcp_rpc.get_shard_account_state(address).await?;
+                (state.last_trans_lt(), state.last_trans_hash().clone(), None)
+            }
+        };
+
+        let mut result = Vec::new();
+        if lt == 0 {
+            return Ok(TransactionsPage {
+                transactions: result,
+                next: None,
+            });
+        }
+
+        let mut block_id = match block_id {
+            Some(block_id) => block_id,
+            None => {
+                let last_mc_block = self.get_last_mc_block().await?;
+                if address.workchain_id() == ton_block::MASTERCHAIN_ID {
+                    last_mc_block.data.id().clone()
+                } else {
+                    last_mc_block
+                        .data
+                        .shard_blocks()?
+                        .into_values()
+                        .find(|id| id.shard_id.contains_full_prefix(&prefix))
+                        .context("account shard not found")?
+                }
+            }
+        };
+
+        loop {
+            let block = self.node_udp_rpc.get_block(&block_id).await?;
+
+            // Older blocks can't contain the required transaction
+            let info = block.block().read_info()?;
+            anyhow::ensure!(info.end_lt() > lt, "transaction {lt} not found");
+
+            if info.start_lt() <= lt {
+                let mut transactions = Vec::new();
+                let account_blocks = block.block().read_extra()?.read_account_blocks()?;
+                if let Some(account_block) = account_blocks.get(&account)? {
+                    account_block.transactions().iterate_slices(|tx| {
+                        let cell = tx.reference(0)?;
+                        let hash = cell.repr_hash();
+                        let data = ton_block::Transaction::construct_from_cell(cell)?;
+                        transactions.push(TransactionWithHash { hash, data });
+                        Ok(true)
+                    })?;
+                }
+                transactions.sort_unstable_by_key(|tx| std::cmp::Reverse(tx.data.lt));
+
+                for tx in transactions {
+                    if tx.data.lt > lt {
+                        continue;
+                    }
+                    anyhow::ensure!(
+                        tx.data.lt == lt && tx.hash == hash,
+                        "transaction {lt} not found"
+                    );
+
+                    lt = tx.data.prev_trans_lt;
+                    hash = tx.data.prev_trans_hash.clone();
+                    result.push(tx);
+
+                    if lt == 0 {
+                        return Ok(TransactionsPage {
+                            transactions: result,
+                            next: None,
+                        });
+                    } else if result.len() >= limit {
+                        return Ok(TransactionsPage {
+                            transactions: result,
+                            next: Some(TransactionsCursor {
+                                lt,
+                                hash,
+                                block_id: Some(block_id),
+                            }),
+                        });
+                    }
+                }
+            }
+
+            // Follow the account shard through splits and merges
+            let info = block.read_brief_info()?;
+            block_id = match info.prev2 {
+                Some(prev2) if prev2.shard_id.contains_full_prefix(&prefix) => prev2,
+                _ => info.prev1,
+            };
+        }
+    }
+
     async fn make_blocks_step(&self) -> Result<bool> {
         // Get last masterchain block
         let last_mc_block = self
@@ -452,6 +864,22 @@ type AccountSubscriptions = FxDashMap<ton_types::UInt256, AccountSubscription>;
 pub type TransactionsTx = mpsc::UnboundedSender<TransactionWithHash>;
 pub type TransactionsRx = mpsc::UnboundedReceiver<TransactionWithHash>;
 
+/// Position in the account transactions history
+#[derive(Debug, Clone)]
+pub struct TransactionsCursor {
+    pub lt: u64,
+    pub hash: ton_types::UInt256,
+    /// Account shard block which is not older than the transaction.
+    /// Used to resume the blocks walk instead of starting from the latest block.
+    pub block_id: Option<ton_block::BlockIdExt>,
+}
+
+pub struct TransactionsPage {
+    pub transactions: Vec<TransactionWithHash>,
+    /// Cursor for the next page, `None` if the first account transaction was reached
+    pub next: Option<TransactionsCursor>,
+}
+
 async fn walk_blocks(subscription: Weak<Subscription>) {
     loop {
         let subscription = match subscription.upgrade() {
@@ -515,4 +943,11 @@ impl Drop for PendingMessage {
     }
 }
 
+struct ExpiredMessageDiagnostic {
+    balance: u128,
+    /// Whether the account balance is enough to pay for the message (if known)
+    can_afford: Option<bool>,
+    reason: String,
+}
+
 const LAST_MC_BLOCK_TTL_SEC: u32 = 10;
@@ -32,15 +32,53 @@ impl<T: Into<u128> + Copy> std::fmt::Display for Ever<T> {
 
         int.fmt(f)?;
         if frac > 0 {
+            let mut width = 9;
             while frac % 10 == 0 && frac > 0 {
                 frac /= 10;
+                width -= 1;
             }
-            f.write_fmt(format_args!(".{frac}"))?;
+            f.write_fmt(format_args!(".{frac:0width$}"))?;
         }
         Ok(())
     }
 }
 
+impl FromStr for Ever<u128> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const DECIMALS: usize = 9;
+
+        let s = s.trim();
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        anyhow::ensure!(!int.is_empty() || !frac.is_empty(), "empty amount");
+        anyhow::ensure!(
+            frac.len() <= DECIMALS,
+            "too many decimal places (max {DECIMALS})"
+        );
+        anyhow::ensure!(
+            int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()),
+            "invalid amount"
+        );
+
+        let int = if int.is_empty() {
+            0
+        } else {
+            u128::from_str(int)?
+        };
+        let frac = if frac.is_empty() {
+            0
+        } else {
+            u128::from_str(frac)? * 10u128.pow((DECIMALS - frac.len()) as u32)
+        };
+
+        int.checked_mul(1000000000)
+            .and_then(|int| int.checked_add(frac))
+            .map(Self)
+            .context("amount is too big")
+    }
+}
+
 #[derive(Clone)]
 pub struct AddressInput(pub ton_block::MsgAddressInt);
 
@@ -130,6 +168,32 @@ pub fn parse_key_hash(hash: &str) -> Result<[u8; 32]> {
         .map_err(|_| anyhow::Error::msg("invalid key hash length"))
 }
 
+/// Parses block id in the `workchain:shard:seqno:root_hash:file_hash` format
+pub fn parse_block_id(block_id: &str) -> Result<ton_block::BlockIdExt> {
+    let parts = block_id.split(':').collect::<Vec<_>>();
+    let [workchain, shard, seqno, root_hash, file_hash] = parts.as_slice() else {
+        anyhow::bail!("invalid block id format");
+    };
+
+    let parse_hash = |hash: &str| -> Result<ton_types::UInt256> {
+        let hash: [u8; 32] = hex::decode(hash)?
+            .try_into()
+            .map_err(|_| anyhow::Error::msg("invalid hash length"))?;
+        Ok(hash.into())
+    };
+
+    let workchain = workchain.parse::<i32>().context("invalid workchain")?;
+    let shard = u64::from_str_radix(shard, 16).context("invalid shard")?;
+    let seqno = seqno.parse::<u32>().context("invalid seqno")?;
+
+    Ok(ton_block::BlockIdExt {
+        shard_id: ton_block::ShardIdent::with_tagged_prefix(workchain, shard)?,
+        seq_no: seqno,
+        root_hash: parse_hash(root_hash).context("invalid root hash")?,
+        file_hash: parse_hash(file_hash).context("invalid file hash")?,
+    })
+}
+
 pub fn parse_optional_state_init(data: Option<String>) -> Result<Option<ton_block::StateInit>> {
     data.as_deref().map(parse_state_init).transpose()
 }
@@ -162,6 +226,25 @@ where
         .interact()
 }
 
+/// Env with the bip39 passphrase for the seed
+pub const SEED_PASSPHRASE_ENV: &str = "STEVER_SEED_PASSPHRASE";
+
+/// Reads the bip39 passphrase from the env or asks for it
+pub fn seed_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(SEED_PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    anyhow::ensure!(
+        console::user_attended(),
+        "bip39 passphrase not found, use `{SEED_PASSPHRASE_ENV}` env"
+    );
+    dialoguer::Password::new()
+        .with_prompt("Seed passphrase")
+        .interact()
+        .context("failed to read passphrase")
+}
+
 pub fn print_output<T: std::fmt::Display>(arg: T) {
     if console::user_attended() {
         writeln!(std::io::stdout(), "{arg:#}")
@@ -108,3 +108,25 @@ pub mod serde_block_id {
         ))
     }
 }
+
+pub mod serde_uint256 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &ton_types::UInt256,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_hex_string())
+    }
+}
+
+pub mod serde_address_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        addresses: &[ton_block::MsgAddressInt],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(addresses.iter().map(ToString::to_string))
+    }
+}
@@ -1,7 +1,7 @@
 use std::ffi::{CStr, OsString};
 use std::mem::MaybeUninit;
 use std::os::unix::ffi::OsStringExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::ptr;
 
 use anyhow::{Context, Result};
@@ -44,6 +44,34 @@ pub fn home_dir(uid: u32) -> Option<PathBuf> {
     }
 }
 
+pub fn disk_space<P: AsRef<Path>>(path: P) -> Result<DiskSpace> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path =
+        std::ffi::CString::new(path.as_ref().as_os_str().as_bytes()).context("invalid path")?;
+
+    // SAFETY: `path` is a valid null-terminated string
+    let stat = unsafe {
+        let mut stat: MaybeUninit<libc::statvfs> = MaybeUninit::uninit();
+        if libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(std::io::Error::last_os_error()).context("failed to get fs stats");
+        }
+        stat.assume_init()
+    };
+
+    let block_size = stat.f_frsize as u64;
+    Ok(DiskSpace {
+        total: stat.f_blocks as u64 * block_size,
+        available: stat.f_bavail as u64 * block_size,
+    })
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DiskSpace {
+    pub total: u64,
+    pub available: u64,
+}
+
 unsafe fn get_passwd(uid: u32, buf: &mut Buffer) -> Option<libc::passwd> {
     let mut pwd: MaybeUninit<libc::passwd> = MaybeUninit::uninit();
     let mut pwdp = ptr::null_mut();
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use ton_block::Deserializable;
 
 pub fn split_address(address: &ton_block::MsgAddressInt) -> Result<(i32, ton_types::UInt256)> {
     match address {
@@ -37,3 +38,139 @@ pub struct TransactionWithHash {
     pub hash: ton_types::UInt256,
     pub data: ton_block::Transaction,
 }
+
+/// Returns whether the ordinary or ticktock transaction was aborted
+pub fn is_aborted(tx: &ton_block::Transaction) -> Result<bool> {
+    Ok(match tx.description.read_struct()? {
+        ton_block::TransactionDescr::Ordinary(descr) => descr.aborted,
+        ton_block::TransactionDescr::TickTock(descr) => descr.aborted,
+        _ => false,
+    })
+}
+
+/// Short JSON description of the transaction and its messages.
+///
+/// Message bodies are decoded with the specified contract ABI if possible
+pub fn describe_transaction(
+    tx: &TransactionWithHash,
+    abi: Option<&ton_abi::Contract>,
+) -> Result<serde_json::Value> {
+    let data = &tx.data;
+
+    let aborted = is_aborted(data)?;
+
+    let in_msg = match &data.in_msg {
+        Some(msg) => Some(describe_message(&msg.hash(), &msg.read_struct()?, abi)),
+        None => None,
+    };
+
+    let mut out_msgs = Vec::new();
+    data.out_msgs.iterate_slices(|slice| {
+        if let Some(cell) = slice.reference_opt(0) {
+            let msg = ton_block::Message::construct_from_cell(cell.clone())?;
+            out_msgs.push(describe_message(&cell.repr_hash(), &msg, abi));
+        }
+        Ok(true)
+    })?;
+
+    Ok(serde_json::json!({
+        "tx_hash": tx.hash.to_hex_string(),
+        "lt": data.lt,
+        "time": data.now,
+        "aborted": aborted,
+        "total_fees": data.total_fees.grams.0.to_string(),
+        "prev_tx_lt": data.prev_trans_lt,
+        "prev_tx_hash": data.prev_trans_hash.to_hex_string(),
+        "in_msg": in_msg,
+        "out_msgs": out_msgs,
+    }))
+}
+
+fn describe_message(
+    hash: &ton_types::UInt256,
+    msg: &ton_block::Message,
+    abi: Option<&ton_abi::Contract>,
+) -> serde_json::Value {
+    let (ty, value, bounce) = match msg.header() {
+        ton_block::CommonMsgInfo::IntMsgInfo(header) => (
+            "internal",
+            Some(header.value.grams.0.to_string()),
+            Some(header.bounce),
+        ),
+        ton_block::CommonMsgInfo::ExtInMsgInfo(_) => ("external_in", None, None),
+        ton_block::CommonMsgInfo::ExtOutMsgInfo(_) => ("external_out", None, None),
+    };
+
+    serde_json::json!({
+        "msg_hash": hash.to_hex_string(),
+        "type": ty,
+        "src": msg.src().map(|addr| addr.to_string()),
+        "dst": msg.dst().map(|addr| addr.to_string()),
+        "value": value,
+        "bounce": bounce,
+        "body": describe_message_body(msg, abi),
+    })
+}
+
+fn describe_message_body(
+    msg: &ton_block::Message,
+    abi: Option<&ton_abi::Contract>,
+) -> Option<serde_json::Value> {
+    const COMMENT_ID: u32 = 0;
+    const BOUNCED_ID: u32 = 0xffffffff;
+
+    let body = msg.body()?;
+
+    let function_id = body.clone().get_next_u32().ok();
+    match function_id {
+        Some(COMMENT_ID) => {
+            if let Some(comment) = parse_comment(body.clone()) {
+                return Some(serde_json::json!({ "comment": comment }));
+            }
+        }
+        Some(BOUNCED_ID) if msg.is_internal() => {
+            return Some(serde_json::json!({ "bounced": true }));
+        }
+        _ => {}
+    }
+
+    let decoded = abi.and_then(|abi| {
+        let decoded = match msg.header() {
+            ton_block::CommonMsgInfo::IntMsgInfo(_) => abi.decode_input(body.clone(), true),
+            ton_block::CommonMsgInfo::ExtInMsgInfo(_) => abi.decode_input(body.clone(), false),
+            ton_block::CommonMsgInfo::ExtOutMsgInfo(_) => abi.decode_output(body.clone(), false),
+        };
+        let decoded = decoded.ok()?;
+        let data = nekoton_abi::make_abi_tokens(&decoded.tokens).ok()?;
+        Some((decoded.function_name, data))
+    });
+
+    Some(match decoded {
+        Some((name, data)) => serde_json::json!({
+            "function_id": function_id.map(|id| format!("0x{id:08x}")),
+            "name": name,
+            "data": data,
+        }),
+        None => serde_json::json!({
+            "function_id": function_id.map(|id| format!("0x{id:08x}")),
+        }),
+    })
+}
+
+/// Reads a UTF-8 comment with chained cells (after the zero function id)
+fn parse_comment(mut body: ton_types::SliceData) -> Option<String> {
+    body.get_next_u32().ok()?;
+
+    let mut bytes = Vec::new();
+    loop {
+        let len = body.remaining_bits() / 8;
+        bytes.extend(body.get_next_bytes(len).ok()?);
+
+        if body.remaining_references() == 0 {
+            break;
+        }
+        body = ton_types::SliceData::from(body.reference(0).ok()?);
+    }
+
+    String::from_utf8(bytes).ok()
+}